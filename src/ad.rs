@@ -0,0 +1,41 @@
+pub mod dual;
+pub mod scalar;
+pub mod tape;
+
+use crate::bs::black_scholes::{black_scholes, CalcInput, OptionType};
+use dual::Dual;
+use tape::gradient;
+
+pub fn run() {
+    // 前進型: [原資産価格, ボラティリティ, 金利, 満期, 権利行使価格] に関する微分を1回で計算する。
+    let input = CalcInput {
+        underlying: Dual::<5>::variable(62.0, 0),
+        vol: Dual::variable(0.2, 1),
+        zero_rate: Dual::variable(0.1, 2),
+        term_annu: Dual::variable(5.0 / 12.0, 3),
+        strike: Dual::variable(60.0, 4),
+    };
+    let price = black_scholes(&input, OptionType::Call);
+    println!("(ad) dual price: {}", price.value);
+    println!(
+        "(ad) dual [delta, vega, rho, dterm, dstrike]: {:?}",
+        price.grad
+    );
+
+    // 後退型: 同じ感応度を1回の逆伝播で計算する。
+    let (value, grad) = gradient(
+        |x| {
+            let input = CalcInput {
+                underlying: x[0],
+                vol: x[1],
+                zero_rate: x[2],
+                term_annu: x[3],
+                strike: x[4],
+            };
+            black_scholes(&input, OptionType::Call)
+        },
+        &[62.0, 0.2, 0.1, 5.0 / 12.0, 60.0],
+    );
+    println!("(ad) tape price: {}", value);
+    println!("(ad) tape gradient: {:?}", grad);
+}
@@ -0,0 +1,199 @@
+use super::scalar::Scalar;
+use libm::erf;
+use std::f64::consts::PI;
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign};
+
+/* 前進型自動微分の二重数 x + Σ ε_i dx/dθ_i
+N個の入力の方向微分を同時に持つため、1回の計算で全ての入力に関する微分が求まる。 */
+
+#[derive(Debug, Copy, Clone)]
+pub struct Dual<const N: usize> {
+    pub value: f64,
+    pub grad: [f64; N], // 各入力に関する微分
+}
+
+impl<const N: usize> Dual<N> {
+    /// index番目の入力(微分の種)を作成します。
+    /// * `value` - 値
+    /// * `index` - 入力のインデックス
+    pub fn variable(value: f64, index: usize) -> Self {
+        let mut grad = [0.0; N];
+        grad[index] = 1.0;
+        Self { value, grad }
+    }
+
+    // 値がvalue、微分が元の微分のderiv倍となる二重数(合成関数の微分)
+    fn chain(&self, value: f64, deriv: f64) -> Self {
+        let mut grad = self.grad;
+        grad.iter_mut().for_each(|g| *g *= deriv);
+        Self { value, grad }
+    }
+
+    // 2つの二重数の微分の線形結合
+    fn combine(&self, other: &Self, value: f64, deriv_self: f64, deriv_other: f64) -> Self {
+        let mut grad = [0.0; N];
+        for (i, g) in grad.iter_mut().enumerate() {
+            *g = deriv_self * self.grad[i] + deriv_other * other.grad[i];
+        }
+        Self { value, grad }
+    }
+}
+
+impl<const N: usize> Add for Dual<N> {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        self.combine(&rhs, self.value + rhs.value, 1.0, 1.0)
+    }
+}
+
+impl<const N: usize> Sub for Dual<N> {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        self.combine(&rhs, self.value - rhs.value, 1.0, -1.0)
+    }
+}
+
+impl<const N: usize> Mul for Dual<N> {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        self.combine(&rhs, self.value * rhs.value, rhs.value, self.value)
+    }
+}
+
+impl<const N: usize> Div for Dual<N> {
+    type Output = Self;
+    fn div(self, rhs: Self) -> Self {
+        let value = self.value / rhs.value;
+        self.combine(&rhs, value, 1.0 / rhs.value, -value / rhs.value)
+    }
+}
+
+impl<const N: usize> Neg for Dual<N> {
+    type Output = Self;
+    fn neg(self) -> Self {
+        self.chain(-self.value, -1.0)
+    }
+}
+
+impl<const N: usize> Add<f64> for Dual<N> {
+    type Output = Self;
+    fn add(self, rhs: f64) -> Self {
+        Self {
+            value: self.value + rhs,
+            grad: self.grad,
+        }
+    }
+}
+
+impl<const N: usize> Sub<f64> for Dual<N> {
+    type Output = Self;
+    fn sub(self, rhs: f64) -> Self {
+        Self {
+            value: self.value - rhs,
+            grad: self.grad,
+        }
+    }
+}
+
+impl<const N: usize> Mul<f64> for Dual<N> {
+    type Output = Self;
+    fn mul(self, rhs: f64) -> Self {
+        self.chain(self.value * rhs, rhs)
+    }
+}
+
+impl<const N: usize> Div<f64> for Dual<N> {
+    type Output = Self;
+    fn div(self, rhs: f64) -> Self {
+        self.chain(self.value / rhs, 1.0 / rhs)
+    }
+}
+
+impl<const N: usize> AddAssign for Dual<N> {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl<const N: usize> SubAssign for Dual<N> {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl<const N: usize> MulAssign for Dual<N> {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl<const N: usize> Scalar for Dual<N> {
+    fn from_f64(value: f64) -> Self {
+        Self {
+            value,
+            grad: [0.0; N],
+        }
+    }
+
+    fn value(&self) -> f64 {
+        self.value
+    }
+
+    fn exp(self) -> Self {
+        let value = self.value.exp();
+        self.chain(value, value)
+    }
+
+    fn ln(self) -> Self {
+        self.chain(self.value.ln(), 1.0 / self.value)
+    }
+
+    fn sqrt(self) -> Self {
+        let value = self.value.sqrt();
+        self.chain(value, 0.5 / value)
+    }
+
+    fn powi(self, n: i32) -> Self {
+        self.chain(self.value.powi(n), n as f64 * self.value.powi(n - 1))
+    }
+
+    fn erf(self) -> Self {
+        let deriv = 2.0 / PI.sqrt() * (-self.value.powi(2)).exp();
+        self.chain(erf(self.value), deriv)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bs::black_scholes::{black_scholes, CalcInput, OptionType};
+    use crate::hull_white::math::std_normal_cdf;
+
+    #[test]
+    fn test_black_scholes_dual() {
+        // [原資産価格, ボラティリティ, 金利, 満期, 権利行使価格] の全てに関する微分を1回で求める。
+        let input = CalcInput {
+            underlying: Dual::<5>::variable(62.0, 0),
+            vol: Dual::variable(0.2, 1),
+            zero_rate: Dual::variable(0.1, 2),
+            term_annu: Dual::variable(5.0 / 12.0, 3),
+            strike: Dual::variable(60.0, 4),
+        };
+        let price = black_scholes(&input, OptionType::Call);
+        let (s, vol, r, t, k) = (62.0_f64, 0.2_f64, 0.1_f64, 5.0_f64 / 12.0, 60.0_f64);
+        let d1 = ((s / k).ln() + (r + 0.5 * vol.powi(2)) * t) / (vol * t.sqrt());
+        let d2 = d1 - vol * t.sqrt();
+        let pdf = (-0.5 * d1.powi(2)).exp() / (2.0 * PI).sqrt();
+        let expected = [
+            std_normal_cdf(d1),
+            s * pdf * t.sqrt(),
+            k * t * (-r * t).exp() * std_normal_cdf(d2),
+            s * pdf * vol / (2.0 * t.sqrt()) + r * k * (-r * t).exp() * std_normal_cdf(d2),
+            -(-r * t).exp() * std_normal_cdf(d2),
+        ];
+        // black_scholesの正規分布の分布関数は近似式のため、許容誤差は近似の精度に合わせる。
+        for (actual, expected) in price.grad.iter().zip(expected.iter()) {
+            assert!((actual - expected).abs() < 1e-4 * expected.abs().max(1.0));
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use libm::erf;
+use std::fmt::Debug;
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign};
+
+/* 自動微分に対応する数値の型
+f64、前進型の二重数(Dual)、後退型のテープ変数(Var)が実装し、解析解やパスの計算をこのトレイトで総称化すると、
+同じコードで値と全ての入力に関する感応度を計算できる。
+f64との演算はスカラーを右辺に置く(x * 0.5)。左辺に置く場合はScalar::from_f64で変換する。 */
+
+pub trait Scalar:
+    Copy
+    + Debug
+    + Send
+    + Sync
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + Add<f64, Output = Self>
+    + Sub<f64, Output = Self>
+    + Mul<f64, Output = Self>
+    + Div<f64, Output = Self>
+    + AddAssign
+    + SubAssign
+    + MulAssign
+{
+    /// 定数(微分が0の値)
+    fn from_f64(value: f64) -> Self;
+
+    /// 値
+    fn value(&self) -> f64;
+
+    fn exp(self) -> Self;
+
+    fn ln(self) -> Self;
+
+    fn sqrt(self) -> Self;
+
+    fn powi(self, n: i32) -> Self;
+
+    /// 指数もScalarのべき乗 x^y = exp(y ln x) (x > 0)
+    fn pow(self, exponent: Self) -> Self {
+        (self.ln() * exponent).exp()
+    }
+
+    /// 誤差関数
+    fn erf(self) -> Self;
+}
+
+impl Scalar for f64 {
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+
+    fn value(&self) -> f64 {
+        *self
+    }
+
+    fn exp(self) -> Self {
+        f64::exp(self)
+    }
+
+    fn ln(self) -> Self {
+        f64::ln(self)
+    }
+
+    fn sqrt(self) -> Self {
+        f64::sqrt(self)
+    }
+
+    fn powi(self, n: i32) -> Self {
+        f64::powi(self, n)
+    }
+
+    fn erf(self) -> Self {
+        erf(self)
+    }
+}
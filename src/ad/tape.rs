@@ -0,0 +1,246 @@
+use super::scalar::Scalar;
+use libm::erf;
+use std::cell::RefCell;
+use std::f64::consts::PI;
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign};
+
+/* 後退型自動微分のテープ
+演算ごとに(親のインデックス, 偏微分)をスレッドローカルなテープに記録し、出力から逆順に随伴変数を伝播する。
+出力が1つで入力が多い場合(キャリブレーションの残差の勾配など)に、1回の逆伝播で全ての入力に関する微分が求まる。
+テープはgradientの呼び出しごとに初期化するため、gradientを入れ子に呼び出すことはできない。 */
+
+// 親を持たない(定数の)インデックス
+const CONSTANT: usize = usize::MAX;
+
+thread_local! {
+    // 各ノードの親(最大2つ)のインデックスと偏微分
+    static TAPE: RefCell<Vec<[(usize, f64); 2]>> = const { RefCell::new(Vec::new()) };
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Var {
+    value: f64,
+    index: usize, // テープ上のインデックス(定数はCONSTANT)
+}
+
+impl Var {
+    // 親と偏微分を記録したノードを作成する。
+    fn push(value: f64, parents: [(usize, f64); 2]) -> Self {
+        let index = TAPE.with(|tape| {
+            let mut tape = tape.borrow_mut();
+            tape.push(parents);
+            tape.len() - 1
+        });
+        Self { value, index }
+    }
+
+    fn unary(&self, value: f64, deriv: f64) -> Self {
+        if self.index == CONSTANT {
+            return Self::from_f64(value);
+        }
+        Self::push(value, [(self.index, deriv), (CONSTANT, 0.0)])
+    }
+
+    fn binary(&self, other: &Self, value: f64, deriv_self: f64, deriv_other: f64) -> Self {
+        if self.index == CONSTANT && other.index == CONSTANT {
+            return Self::from_f64(value);
+        }
+        Self::push(
+            value,
+            [(self.index, deriv_self), (other.index, deriv_other)],
+        )
+    }
+}
+
+/// 関数の値と全ての入力に関する勾配を後退型自動微分で返します。
+/// * `f` - 入力のVarから出力のVarを計算する関数
+/// * `inputs` - 入力の値
+pub fn gradient<F>(f: F, inputs: &[f64]) -> (f64, Vec<f64>)
+where
+    F: FnOnce(&[Var]) -> Var,
+{
+    TAPE.with(|tape| tape.borrow_mut().clear());
+    let vars: Vec<Var> = inputs
+        .iter()
+        .map(|x| Var::push(*x, [(CONSTANT, 0.0); 2]))
+        .collect();
+    let output = f(&vars);
+    let mut grad = vec![0.0; inputs.len()];
+    if output.index == CONSTANT {
+        return (output.value, grad);
+    }
+    TAPE.with(|tape| {
+        let tape = tape.borrow();
+        let mut adjoints = vec![0.0; output.index + 1];
+        adjoints[output.index] = 1.0;
+        for i in (0..output.index + 1).rev() {
+            if adjoints[i] == 0.0 {
+                continue;
+            }
+            for (parent, deriv) in tape[i].iter() {
+                if *parent != CONSTANT {
+                    adjoints[*parent] += adjoints[i] * deriv;
+                }
+            }
+        }
+        grad.copy_from_slice(&adjoints[..inputs.len()]);
+    });
+    (output.value, grad)
+}
+
+impl Add for Var {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        self.binary(&rhs, self.value + rhs.value, 1.0, 1.0)
+    }
+}
+
+impl Sub for Var {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        self.binary(&rhs, self.value - rhs.value, 1.0, -1.0)
+    }
+}
+
+impl Mul for Var {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        self.binary(&rhs, self.value * rhs.value, rhs.value, self.value)
+    }
+}
+
+impl Div for Var {
+    type Output = Self;
+    fn div(self, rhs: Self) -> Self {
+        let value = self.value / rhs.value;
+        self.binary(&rhs, value, 1.0 / rhs.value, -value / rhs.value)
+    }
+}
+
+impl Neg for Var {
+    type Output = Self;
+    fn neg(self) -> Self {
+        self.unary(-self.value, -1.0)
+    }
+}
+
+impl Add<f64> for Var {
+    type Output = Self;
+    fn add(self, rhs: f64) -> Self {
+        self.unary(self.value + rhs, 1.0)
+    }
+}
+
+impl Sub<f64> for Var {
+    type Output = Self;
+    fn sub(self, rhs: f64) -> Self {
+        self.unary(self.value - rhs, 1.0)
+    }
+}
+
+impl Mul<f64> for Var {
+    type Output = Self;
+    fn mul(self, rhs: f64) -> Self {
+        self.unary(self.value * rhs, rhs)
+    }
+}
+
+impl Div<f64> for Var {
+    type Output = Self;
+    fn div(self, rhs: f64) -> Self {
+        self.unary(self.value / rhs, 1.0 / rhs)
+    }
+}
+
+impl AddAssign for Var {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Var {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl MulAssign for Var {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl Scalar for Var {
+    fn from_f64(value: f64) -> Self {
+        Self {
+            value,
+            index: CONSTANT,
+        }
+    }
+
+    fn value(&self) -> f64 {
+        self.value
+    }
+
+    fn exp(self) -> Self {
+        let value = self.value.exp();
+        self.unary(value, value)
+    }
+
+    fn ln(self) -> Self {
+        self.unary(self.value.ln(), 1.0 / self.value)
+    }
+
+    fn sqrt(self) -> Self {
+        let value = self.value.sqrt();
+        self.unary(value, 0.5 / value)
+    }
+
+    fn powi(self, n: i32) -> Self {
+        self.unary(self.value.powi(n), n as f64 * self.value.powi(n - 1))
+    }
+
+    fn erf(self) -> Self {
+        let deriv = 2.0 / PI.sqrt() * (-self.value.powi(2)).exp();
+        self.unary(erf(self.value), deriv)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ad::dual::Dual;
+    use crate::sabr::sabr_lognormal::sabr_lognormal;
+
+    #[test]
+    fn test_gradient_matches_dual() {
+        // SABRのインプライド・ボラティリティの(α, ρ, ν)に関する勾配が前進型と一致する。
+        let (strike, fwd, term, beta) = (0.03, 0.035, 10.0, 0.5);
+        let params = [0.03, -0.2, 0.4];
+        let (value, grad) = gradient(
+            |p| {
+                let c = Var::from_f64;
+                sabr_lognormal(c(strike), c(fwd), c(term), c(beta), p[0], p[1], p[2])
+            },
+            &params,
+        );
+        let c = Dual::<3>::from_f64;
+        let dual = sabr_lognormal(
+            c(strike),
+            c(fwd),
+            c(term),
+            c(beta),
+            Dual::variable(params[0], 0),
+            Dual::variable(params[1], 1),
+            Dual::variable(params[2], 2),
+        );
+        assert!((value - dual.value).abs() < 1e-15);
+        for (a, b) in grad.iter().zip(dual.grad.iter()) {
+            assert!((a - b).abs() < 1e-12 * b.abs().max(1.0));
+        }
+        // 有限差分とも一致する。
+        let h = 1e-7;
+        let bumped = sabr_lognormal(strike, fwd, term, beta, params[0] + h, params[1], params[2]);
+        assert!(((bumped - value) / h - grad[0]).abs() < 1e-4 * grad[0].abs());
+    }
+}
@@ -1,6 +1,10 @@
 use self::black_scholes::black_scholes;
+use self::exotic::forward_start_option;
+use self::variance_swap::variance_swap_fair_strike;
 
-mod black_scholes;
+pub mod black_scholes;
+pub mod exotic;
+pub mod variance_swap;
 
 pub fn run() {
     let input = black_scholes::CalcInput {
@@ -15,4 +19,19 @@ pub fn run() {
 
     let opt_val = black_scholes(&input, option_type);
     println!("(analytical)price of european call option: {}", opt_val);
+
+    let opt_val = forward_start_option(&input, 1.0 / 12.0, 1.0, option_type);
+    println!(
+        "(analytical)price of forward start call option: {}",
+        opt_val
+    );
+
+    // 線形のスキューからバリアンス・スワップの公正な行使価格を求める。
+    let skew = |strike: f64| 0.2 - 0.1 * (strike / input.underlying).ln();
+    let fair_var = variance_swap_fair_strike(&input, &skew, 200);
+    println!(
+        "(analytical)variance swap fair strike: {} (vol: {})",
+        fair_var,
+        fair_var.sqrt()
+    );
 }
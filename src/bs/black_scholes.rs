@@ -1,39 +1,61 @@
+use crate::ad::scalar::Scalar;
 use std::f64::consts::PI;
 
+// 入力の型をDualやVarにすると全ての入力に関する感応度を同時に計算できる。
 #[derive(Debug, Copy, Clone)]
-pub struct CalcInput {
-    pub zero_rate: f64,
-    pub vol: f64,
-    pub term_annu: f64,
-    pub strike: f64,
-    pub underlying: f64,
+pub struct CalcInput<T = f64> {
+    pub zero_rate: T,
+    pub vol: T,
+    pub term_annu: T,
+    pub strike: T,
+    pub underlying: T,
 }
 
+#[derive(Debug, Copy, Clone)]
 pub enum OptionType {
     Call,
     Put,
 }
-pub fn black_scholes(input: &CalcInput, option_type: OptionType) -> f64 {
+pub fn black_scholes<T: Scalar>(input: &CalcInput<T>, option_type: OptionType) -> T {
     let CalcInput {
         zero_rate,
         vol,
         term_annu,
         strike,
         underlying,
-    } = input;
-    let d1 = ((underlying / strike).ln() + (zero_rate + 0.5 * vol.powi(2)) * term_annu)
+    } = *input;
+    let d1 = ((underlying / strike).ln() + (zero_rate + vol.powi(2) * 0.5) * term_annu)
         / (vol * term_annu.sqrt());
     let d2 = d1 - vol * term_annu.sqrt();
     let sgn = match option_type {
         OptionType::Call => 1.0,
         OptionType::Put => -1.0,
     };
-    sgn * (underlying * norm_cdf_matic2016(sgn * d1)
-        - strike * (-zero_rate * term_annu).exp() * norm_cdf_matic2016(sgn * d2))
+    (underlying * norm_cdf_matic2016(d1 * sgn)
+        - strike * (-zero_rate * term_annu).exp() * norm_cdf_matic2016(d2 * sgn))
+        * sgn
+}
+
+/// 価格からBlack-Scholesのインプライド・ボラティリティを二分法で求めます。
+/// * `price` - オプション価格
+/// * `input` - ボラティリティ以外の入力(volは使わない)
+/// * `option_type` - Call/Put
+pub fn implied_vol(price: f64, input: &CalcInput, option_type: OptionType) -> f64 {
+    let (mut lower, mut upper) = (1e-6, 5.0);
+    for _ in 0..100 {
+        let mid = 0.5 * (lower + upper);
+        let trial = CalcInput { vol: mid, ..*input };
+        if black_scholes(&trial, option_type) > price {
+            upper = mid;
+        } else {
+            lower = mid;
+        }
+    }
+    0.5 * (lower + upper)
 }
 
 // Matic et al.(2016)
-pub fn norm_cdf_matic2016(x: f64) -> f64 {
+pub fn norm_cdf_matic2016<T: Scalar>(x: T) -> T {
     let gamma2 = -1.0 / 3.0 + 1.0 / PI;
     let gamma4 = 7.0 / 90.0 - 2.0 / (3.0 * PI) + 4.0 / (3.0 * PI.powi(2));
     let gamma6 = -1.0 / 70.0 + 4.0 / (15.0 * PI) - 4.0 / (3.0 * PI.powi(2)) + 2.0 / PI.powi(3);
@@ -45,16 +67,12 @@ pub fn norm_cdf_matic2016(x: f64) -> f64 {
         - 16.0 / (3.0 * PI.powi(4))
         + 16.0 / (3.0 * PI.powi(5));
 
-    0.5 + x.signum()
-        * 0.5
-        * (1.0
-            - (-2.0 * x.powi(2) / PI
-                * (1.0
-                    + gamma2 * x.powi(2)
-                    + gamma4 * x.powi(4)
-                    + gamma6 * x.powi(6)
-                    + gamma8 * x.powi(8)
-                    + gamma10 * x.powi(10)))
-            .exp())
-        .sqrt()
+    let x2 = x.powi(2);
+    let series = x2 * gamma2
+        + x.powi(4) * gamma4
+        + x.powi(6) * gamma6
+        + x.powi(8) * gamma8
+        + x.powi(10) * gamma10
+        + 1.0;
+    (T::from_f64(1.0) - (x2 * (-2.0 / PI) * series).exp()).sqrt() * (0.5 * x.value().signum()) + 0.5
 }
@@ -0,0 +1,188 @@
+use super::black_scholes::{CalcInput, OptionType};
+use crate::hull_white::math::std_normal_cdf;
+use std::f64::consts::PI;
+
+// 2r/σ^2 がこれより小さければルックバックの r -> 0 の極限の式を使う
+const SMALL_EXPONENT: f64 = 1e-8;
+
+/* 連続観測のバリアオプションとルックバックオプションの解析解(配当なし、リベートなし)
+バリア: Reiner-Rubinstein(1991)。ノックアウトを求め、ノックインはパリティ(イン + アウト = バニラ)から求める。
+ルックバック: 変動ストライクはGoldman-Sosin-Gatto(1979)、固定ストライクはConze-Viswanathan(1991)。
+いずれも時刻0で開始し、それまでの最大値・最小値は原資産価格に等しいとする。
+ルックバックの式は 2r/σ^2 で割る項を含み r = 0 で 0/0 となるため、r -> 0 の極限を別に使う。
+    l = ln(S/L)、d0 = (l + σ^2 T/2)/(σ√T) として
+    変動ストライク: S (σ√T n(d0) - φ (l + σ^2 T/2) N(-φ d0))、固定ストライク: S (σ√T n(d0) + φ (l + σ^2 T/2) N(φ d0)) */
+
+#[derive(Debug, Copy, Clone)]
+pub enum BarrierType {
+    UpOut,
+    UpIn,
+    DownOut,
+    DownIn,
+}
+
+impl BarrierType {
+    /// バリアが原資産価格より上ならtrue
+    pub fn is_up(&self) -> bool {
+        matches!(self, BarrierType::UpOut | BarrierType::UpIn)
+    }
+
+    /// ノックアウトならtrue
+    pub fn is_out(&self) -> bool {
+        matches!(self, BarrierType::UpOut | BarrierType::DownOut)
+    }
+}
+
+/// 連続観測のバリアオプションの価格を返します。
+/// * `input` - Black-Scholesの入力
+/// * `barrier` - バリア
+/// * `barrier_type` - バリアの種類
+/// * `option_type` - Call/Put
+pub fn barrier_option(
+    input: &CalcInput,
+    barrier: f64,
+    barrier_type: BarrierType,
+    option_type: OptionType,
+) -> f64 {
+    let vanilla = super::black_scholes::black_scholes(input, option_type);
+    let knocked_out = if barrier_type.is_up() {
+        input.underlying >= barrier
+    } else {
+        input.underlying <= barrier
+    };
+    let out = if knocked_out {
+        0.0
+    } else {
+        knock_out(input, barrier, barrier_type.is_up(), option_type)
+    };
+    if barrier_type.is_out() {
+        out
+    } else {
+        vanilla - out
+    }
+}
+
+// Reiner-Rubinsteinのノックアウトの価格(原資産価格はバリアの内側)
+fn knock_out(input: &CalcInput, barrier: f64, is_up: bool, option_type: OptionType) -> f64 {
+    let CalcInput {
+        zero_rate,
+        vol,
+        term_annu,
+        strike,
+        underlying,
+    } = *input;
+    let phi = match option_type {
+        OptionType::Call => 1.0,
+        OptionType::Put => -1.0,
+    };
+    let eta = if is_up { -1.0 } else { 1.0 };
+    let std_dev = vol * term_annu.sqrt();
+    let mu = (zero_rate - 0.5 * vol.powi(2)) / vol.powi(2);
+    let df = (-zero_rate * term_annu).exp();
+    let ratio = barrier / underlying;
+    let shift = (1.0 + mu) * std_dev;
+    let x1 = (underlying / strike).ln() / std_dev + shift;
+    let x2 = (underlying / barrier).ln() / std_dev + shift;
+    let y1 = (barrier.powi(2) / (underlying * strike)).ln() / std_dev + shift;
+    let y2 = (barrier / underlying).ln() / std_dev + shift;
+    let vanilla_term = |x: f64| {
+        phi * underlying * std_normal_cdf(phi * x)
+            - phi * strike * df * std_normal_cdf(phi * (x - std_dev))
+    };
+    let reflected_term = |y: f64| {
+        phi * underlying * ratio.powf(2.0 * (mu + 1.0)) * std_normal_cdf(eta * y)
+            - phi * strike * df * ratio.powf(2.0 * mu) * std_normal_cdf(eta * (y - std_dev))
+    };
+    let (a, b, c, d) = (
+        vanilla_term(x1),
+        vanilla_term(x2),
+        reflected_term(y1),
+        reflected_term(y2),
+    );
+    let above = strike > barrier;
+    match (option_type, is_up) {
+        (OptionType::Call, false) if above => a - c,
+        (OptionType::Call, false) => b - d,
+        (OptionType::Call, true) if above => 0.0,
+        (OptionType::Call, true) => a - b + c - d,
+        (OptionType::Put, false) if above => a - b + c - d,
+        (OptionType::Put, false) => 0.0,
+        (OptionType::Put, true) if above => b - d,
+        (OptionType::Put, true) => a - c,
+    }
+}
+
+/// 連続観測のルックバックオプションの価格を返します。<br>
+/// 変動ストライクのCallは S_T - min S、Putは max S - S_T、
+/// 固定ストライクのCallは max(max S - K, 0)、Putは max(K - min S, 0) を支払います。
+/// * `input` - Black-Scholesの入力(変動ストライクではstrikeは使わない)
+/// * `fixed_strike` - 固定ストライクならtrue
+/// * `option_type` - Call/Put
+pub fn lookback_option(input: &CalcInput, fixed_strike: bool, option_type: OptionType) -> f64 {
+    let CalcInput {
+        zero_rate,
+        vol,
+        term_annu,
+        strike,
+        underlying,
+    } = *input;
+    let phi = match option_type {
+        OptionType::Call => 1.0,
+        OptionType::Put => -1.0,
+    };
+    let std_dev = vol * term_annu.sqrt();
+    let df = (-zero_rate * term_annu).exp();
+    let exponent = 2.0 * zero_rate / vol.powi(2);
+    // 変動ストライクは時刻0の最小値(Call)・最大値(Put)、固定ストライクは行使価格と時刻0の最大値・最小値の外側
+    let level = match (fixed_strike, option_type) {
+        (false, _) => underlying,
+        (true, OptionType::Call) => strike.max(underlying),
+        (true, OptionType::Put) => strike.min(underlying),
+    };
+    let d1 = ((underlying / level).ln() + (zero_rate + 0.5 * vol.powi(2)) * term_annu) / std_dev;
+    let d2 = d1 - std_dev;
+    let reflection = (underlying / level).powf(-exponent);
+    let vanilla_term =
+        phi * (underlying * std_normal_cdf(phi * d1) - level * df * std_normal_cdf(phi * d2));
+    // 時刻0の最大値・最小値を超えて動く部分(反射の項)
+    let extreme_term = if exponent.abs() < SMALL_EXPONENT {
+        let drift = (underlying / level).ln() + 0.5 * vol.powi(2) * term_annu;
+        let density = (-0.5 * d1.powi(2)).exp() / (2.0 * PI).sqrt();
+        let sign = if fixed_strike { phi } else { -phi };
+        underlying * (std_dev * density + sign * drift * std_normal_cdf(sign * d1))
+    } else if fixed_strike {
+        phi * underlying * df / exponent
+            * ((zero_rate * term_annu).exp() * std_normal_cdf(phi * d1)
+                - reflection * std_normal_cdf(phi * (d1 - exponent * std_dev)))
+    } else {
+        phi * underlying * df / exponent
+            * (reflection * std_normal_cdf(-phi * (d1 - exponent * std_dev))
+                - (zero_rate * term_annu).exp() * std_normal_cdf(-phi * d1))
+    };
+    if fixed_strike {
+        phi * df * (level - strike) + vanilla_term + extreme_term
+    } else {
+        vanilla_term + extreme_term
+    }
+}
+
+/// 前進開始オプション(時刻startに行使価格を moneyness * S(start) と決める)の価格をRubinstein(1991)の解析解で返します。<br>
+/// S(start)に比例するため、S(0)倍した期間 T - start のBlack-Scholesの価格となります。
+/// * `input` - Black-Scholesの入力(term_annuは満期、strikeは使わない)
+/// * `start` - 行使価格を決める時刻
+/// * `moneyness` - 行使価格の S(start) に対する割合
+/// * `option_type` - Call/Put
+pub fn forward_start_option(
+    input: &CalcInput,
+    start: f64,
+    moneyness: f64,
+    option_type: OptionType,
+) -> f64 {
+    let unit = CalcInput {
+        underlying: 1.0,
+        strike: moneyness,
+        term_annu: input.term_annu - start,
+        ..*input
+    };
+    input.underlying * super::black_scholes::black_scholes(&unit, option_type)
+}
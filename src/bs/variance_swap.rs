@@ -0,0 +1,56 @@
+use super::black_scholes::{black_scholes, CalcInput, OptionType};
+
+// 積分範囲の対数マネーネスの幅(ATMのボラティリティの標準偏差の倍数)
+const NUM_STD_DEV: f64 = 10.0;
+
+/* バリアンス・スワップの複製(Demeterfi et al. 1999)
+連続観測の実現分散の期待値は、フォワードFを境にOTMのPut/Callを1/K^2で重み付けしたポートフォリオで複製できる。
+K_var = 2e^{rT}/T (∫_0^F P(K)/K^2 dK + ∫_F^∞ C(K)/K^2 dK)
+K = F e^x と変数変換し、xについてSimpson公式で積分する。 */
+
+/// ボラティリティ・スマイルからバリアンス・スワップの公正な行使価格(年率の分散)を返します。
+/// * `input` - Black-Scholesの入力(underlying, zero_rate, term_annuを使う)
+/// * `smile` - 行使価格に対するインプライド・ボラティリティ
+/// * `num_interval` - Simpson公式の分割数(奇数なら1つ増やして偶数にする)
+pub fn variance_swap_fair_strike(
+    input: &CalcInput,
+    smile: &dyn Fn(f64) -> f64,
+    num_interval: usize,
+) -> f64 {
+    let num_interval = (num_interval + num_interval % 2).max(2);
+    let term = input.term_annu;
+    let growth = (input.zero_rate * term).exp();
+    let fwd = input.underlying * growth;
+    let width = NUM_STD_DEV * smile(fwd) * term.sqrt();
+    let h = 2.0 * width / num_interval as f64;
+    // OTMのオプション価格 / K
+    let integrand = |x: f64| {
+        let strike = fwd * x.exp();
+        let option_type = if x < 0.0 {
+            OptionType::Put
+        } else {
+            OptionType::Call
+        };
+        let option_input = CalcInput {
+            strike,
+            vol: smile(strike),
+            ..*input
+        };
+        black_scholes(&option_input, option_type) / strike
+    };
+    let integral: f64 = (0..num_interval + 1)
+        .map(|i| {
+            let weight = if i == 0 || i == num_interval {
+                1.0
+            } else if i % 2 == 1 {
+                4.0
+            } else {
+                2.0
+            };
+            weight * integrand(-width + i as f64 * h)
+        })
+        .sum::<f64>()
+        * h
+        / 3.0;
+    2.0 * growth / term * integral
+}
@@ -1,18 +1,27 @@
 mod analysis;
+mod bermudan_swaption;
 mod calibration;
-mod curve;
+pub mod curve;
 mod data;
-mod hw_lib;
+pub mod hw_lib;
 mod interpolation;
-mod math;
+pub mod math;
 mod node;
 mod optimization;
+pub mod simulation;
 mod tree;
 
+use crate::mc::rand_num::{InverseCdf, RandGen, RandType};
+use analysis::SwaptionType;
+use bermudan_swaption::BermudanSwaption;
+use curve::Curve;
+use hw_lib::HullWhite;
+use simulation::HullWhiteSimulation;
+
 pub fn run() {
-    /// Bermudan Swaptionの価格を計算する
+    // Bermudan Swaptionの価格を計算する
 
-    /// キャリブレーションのためのマーケットデータ
+    // キャリブレーションのためのマーケットデータ
     // 満期
     let maturities = vec![0.5, 1.0, 1.5, 2.0, 2.5, 3.0];
     // 各満期共通のStrike
@@ -34,4 +43,44 @@ pub fn run() {
 
     // キャリブレーション
     // maturitiesをfor文で回してHWのパラメータの値と区間の値をvecに追加していく
+    // 各満期に行使できるBermudan Swaption(LSMとトリノミアルツリー)
+    let rand_gen = RandGen {
+        rand_type: RandType::Pseudo,
+        seed: 1234,
+        brownian_bridge: false,
+        inverse_cdf: InverseCdf::Moro,
+    };
+    for (strike, prices) in strikes.into_iter().zip(&market_prices) {
+        // Strikeごとに各満期のPayer Swaptionの市場価格へa、σをキャリブレーションする
+        let (a, sigma) = calibration::swaption_with_maturities(
+            init_a,
+            init_sigma,
+            &maturities,
+            strike,
+            &swap_dates,
+            SwaptionType::Payer,
+            Curve::Ois,
+            prices,
+        );
+        println!(
+            "(hull white) calibrated strike {}: a {} sigma {}",
+            strike, a, sigma
+        );
+        let hw = HullWhite::new(vec![a], vec![0.0], vec![sigma], vec![0.0]);
+        let simulation = HullWhiteSimulation::new(hw.clone(), Curve::Ois);
+        for swaption_type in [SwaptionType::Payer, SwaptionType::Receiver] {
+            let swaption = BermudanSwaption {
+                exercise_dates: maturities.clone(),
+                swap_dates: swap_dates.clone(),
+                strike,
+                swaption_type,
+            };
+            let lsm = swaption.lsm_price(&simulation, 60, 50000, &rand_gen).result;
+            let tree = swaption.tree_price(hw.clone(), 200);
+            println!(
+                "(hull white) bermudan swaption {:?} strike {}: lsm {} ± {} tree {}",
+                swaption_type, strike, lsm.price, lsm.std_error, tree
+            );
+        }
+    }
 }
@@ -3,11 +3,13 @@ use super::curve::{
     Curve::{self, Ois},
 };
 use super::math::std_normal_cdf;
+use crate::ad::scalar::Scalar;
 
 /* One Factor Hull White
 dr_t = (θ_t - a * r_t) * dt - σ * dW_t */
 
 // Day Count Conventionは考慮しない。
+// a、σ、権利行使価格、ボラティリティはScalarで総称化しており、DualやVarを渡すと感応度を同時に計算できる。
 
 #[derive(Clone, Copy, Debug)]
 pub enum OptionType {
@@ -35,14 +37,14 @@ pub fn df(curve: curve::Curve, t: f64) -> f64 {
 }
 
 /// 割引債オプションの理論価格を返します。
-pub fn discount_bond_option(
-    a: f64,
-    sigma: f64,
+pub fn discount_bond_option<T: Scalar>(
+    a: T,
+    sigma: T,
     mat_u: f64,
     mat_o: f64,
-    strike: f64,
+    strike: T,
     op_type: OptionType,
-) -> f64 {
+) -> T {
     let vol = dbo_vol(a, sigma, mat_u, mat_o);
     discount_bond_option_given_vol(mat_u, mat_o, strike, vol, op_type)
 }
@@ -53,91 +55,93 @@ pub fn discount_bond_option(
 /// * `strike` - 権利行使価格
 /// * `vol` - ボラティリティ
 /// * `op_type` - Call/Put
-pub fn discount_bond_option_given_vol(
+pub fn discount_bond_option_given_vol<T: Scalar>(
     mat_u: f64,
     mat_o: f64,
-    strike: f64,
-    vol: f64,
+    strike: T,
+    vol: T,
     op_type: OptionType,
-) -> f64 {
+) -> T {
     let sign = match op_type {
         OptionType::Call => 1.0,
         OptionType::Put => -1.0,
     };
-    let d = (df(Ois, mat_u) / (strike * df(Ois, mat_o))).ln() + 0.5 * vol.powi(2) / vol;
-    sign * df(Ois, mat_u) * std_normal_cdf(sign * d)
-        - sign * df(Ois, mat_o) * std_normal_cdf(sign * (d - vol))
+    let d =
+        (T::from_f64(df(Ois, mat_u)) / (strike * df(Ois, mat_o))).ln() + vol.powi(2) * 0.5 / vol;
+    std_normal_cdf(d * sign) * (sign * df(Ois, mat_u))
+        - std_normal_cdf((d - vol) * sign) * (sign * df(Ois, mat_o))
 }
 
 /// 割引債オプションのボラティリティを返します。
-pub fn dbo_vol(a: f64, sigma: f64, mat_u: f64, mat_o: f64) -> f64 {
+pub fn dbo_vol<T: Scalar>(a: T, sigma: T, mat_u: f64, mat_o: f64) -> T {
+    let one = T::from_f64(1.0);
     sigma / a
-        * (1.0 - (-2.0 * a * mat_o).exp() / (2.0 * a)).sqrt()
-        * (1.0 - (-a * (mat_u - mat_o)).exp())
+        * (one - (a * (-2.0 * mat_o)).exp() / (a * 2.0)).sqrt()
+        * (one - (a * -(mat_u - mat_o)).exp())
 }
 
 /// Caplet、Floorletの理論価格を返します。
-pub fn capfloorlet(
-    a: f64,
-    sigma: f64,
+pub fn capfloorlet<T: Scalar>(
+    a: T,
+    sigma: T,
     date_s: f64,
     date_e: f64,
-    strike: f64,
+    strike: T,
     cf_type: CapFloorType,
     curve: curve::Curve,
-) -> f64 {
+) -> T {
     let vol = capfloorlet_vol(a, sigma, date_s, date_e);
     capfloorlet_given_vol(date_s, date_e, strike, vol, cf_type, curve)
 }
 
-pub fn capfloorlet_given_vol(
+pub fn capfloorlet_given_vol<T: Scalar>(
     date_s: f64,
     date_e: f64,
-    strike: f64,
-    vol: f64,
+    strike: T,
+    vol: T,
     cf_type: CapFloorType,
     curve: Curve,
-) -> f64 {
+) -> T {
     let sign = match cf_type {
         CapFloorType::Cap => -1.0,
         CapFloorType::Floor => 1.0,
     };
     let delta = date_e - date_s;
-    let d = (((1.0 + delta * strike) * df(curve, date_e) / df(curve, date_s)).ln()
-        + 0.5 * vol.powi(2))
+    let d = (((strike * delta + 1.0) * df(curve, date_e) / df(curve, date_s)).ln()
+        + vol.powi(2) * 0.5)
         / vol;
-    df(Ois, date_e)
-        * (sign * (1.0 + delta * strike) * std_normal_cdf(sign * d)
-            - sign * df(curve, date_s) / df(curve, date_e) * std_normal_cdf(sign * (d - vol)))
+    ((strike * delta + 1.0) * std_normal_cdf(d * sign) * sign
+        - std_normal_cdf((d - vol) * sign) * (sign * df(curve, date_s) / df(curve, date_e)))
+        * df(Ois, date_e)
 }
 
 /// Caplet、Floorletのボラティリティを返します。
-pub fn capfloorlet_vol(a: f64, sigma: f64, date_s: f64, date_e: f64) -> f64 {
-    sigma / a * (1.0 - (-2.0 * date_s).exp()) / (0.5 * a).sqrt() * (1.0 - (-a * (date_e - date_s)))
+pub fn capfloorlet_vol<T: Scalar>(a: T, sigma: T, date_s: f64, date_e: f64) -> T {
+    sigma / a * (1.0 - (-2.0 * date_s).exp()) / (a * 0.5).sqrt() * (a * (date_e - date_s) + 1.0)
 }
 
 /// Cap、Floorの理論価格を返します。
-pub fn capfloor(
+pub fn capfloor<T: Scalar>(
     dates: &Vec<f64>,
-    vols: &Vec<f64>,
-    strike: f64,
+    vols: &Vec<T>,
+    strike: T,
     cf_type: CapFloorType,
     curve: curve::Curve,
-) -> f64 {
+) -> T {
     capfloor_given_vols(dates, vols, strike, cf_type, curve)
 }
 
-pub fn capfloor_given_vols(
+pub fn capfloor_given_vols<T: Scalar>(
     dates: &Vec<f64>, // 各Caplet/Floorletの参照レートのスタートとエンドの日付(エンドが次のCFのスタートと一致すると仮定)
-    vols: &Vec<f64>,  // 各Caplet/Floorletのボラティリティ
-    strike: f64,
+    vols: &Vec<T>,    // 各Caplet/Floorletのボラティリティ
+    strike: T,
     cf_type: CapFloorType,
     curve: Curve,
-) -> f64 {
+) -> T {
     if dates.len() != vols.len() {
         panic!("CFの数とボラティリティの数が合っていません");
     }
-    let mut price = 0.0;
+    let mut price = T::from_f64(0.0);
     for i in 0..dates.len() {
         price += capfloorlet_given_vol(dates[i], dates[i + 1], strike, vols[i], cf_type, curve);
     }
@@ -146,31 +150,31 @@ pub fn capfloor_given_vols(
 
 /// Swaptionの理論価格を返します。
 /// ATMとなるショートレートの逆算を省略するための近似値を返します。
-pub fn swaption(
-    a: f64,
-    sigma: f64,
+pub fn swaption<T: Scalar>(
+    a: T,
+    sigma: T,
     mat_op: f64,
-    strike: f64,
+    strike: T,
     swap_dates: &Vec<f64>,
     op_type: SwaptionType,
     curve: curve::Curve,
-) -> f64 {
+) -> T {
     let vol = swaption_vol(a, sigma, mat_op, swap_dates, strike, curve);
     swaption_given_vol(swap_dates, strike, vol, op_type, curve)
 }
 
-pub fn swaption_given_vol(
+pub fn swaption_given_vol<T: Scalar>(
     swap_dates: &Vec<f64>,
-    strike: f64,
-    vol: f64,
+    strike: T,
+    vol: T,
     op_type: SwaptionType,
     curve: Curve,
-) -> f64 {
+) -> T {
     let coupon_bearing_bond = coupon_bearing_bond(&swap_dates, strike, curve);
     let d = ((coupon_bearing_bond * df(curve, swap_dates[1])
         / (df(Ois, swap_dates[1]) * df(curve, swap_dates[0])))
     .ln()
-        + 0.5 * vol.powi(2))
+        + vol.powi(2) * 0.5)
         / vol;
 
     let sign = match op_type {
@@ -178,49 +182,55 @@ pub fn swaption_given_vol(
         SwaptionType::Receiver => 1.0,
     };
 
-    sign * coupon_bearing_bond * std_normal_cdf(sign * d)
-        - sign * df(Ois, swap_dates[1]) * df(curve, swap_dates[0]) / df(curve, swap_dates[1])
-            * std_normal_cdf(sign * (d - vol))
+    coupon_bearing_bond * std_normal_cdf(d * sign) * sign
+        - std_normal_cdf((d - vol) * sign)
+            * (sign * df(Ois, swap_dates[1]) * df(curve, swap_dates[0]) / df(curve, swap_dates[1]))
 }
 
-pub fn swaption_vol(
-    a: f64,
-    sigma: f64,
+pub fn swaption_vol<T: Scalar>(
+    a: T,
+    sigma: T,
     mat_op: f64,
     swap_dates: &Vec<f64>,
-    strike: f64,
+    strike: T,
     curve: Curve,
-) -> f64 {
-    let b = |mat: f64| 1.0 / a * (1.0 - (-a * mat).exp());
+) -> T {
+    let one = T::from_f64(1.0);
+    let b = |mat: f64| (one - (a * -mat).exp()) / a;
     let coupons = coupons(swap_dates, strike, curve);
     let coupon_bearing_bond = coupon_bearing_bond(swap_dates, strike, curve);
-    let weighted_b = coupons.iter().enumerate().fold(0.0, |acc, (i, coupon)| {
-        acc + coupon * df(Ois, swap_dates[i + 1]) * (b(swap_dates[0]) - b(swap_dates[i + 1]))
-    });
-    let modified_mat =
-        -1.0 / a * (1.0 - a * (b(swap_dates[0]) - 1.0 / coupon_bearing_bond * weighted_b)).ln();
+    let weighted_b = coupons
+        .iter()
+        .enumerate()
+        .fold(T::from_f64(0.0), |acc, (i, coupon)| {
+            acc + *coupon * df(Ois, swap_dates[i + 1]) * (b(swap_dates[0]) - b(swap_dates[i + 1]))
+        });
+    let modified_mat = -(one - a * (b(swap_dates[0]) - weighted_b / coupon_bearing_bond)).ln() / a;
     sigma / a
-        * ((1.0 - (-2.0 * a * mat_op).exp()) / (2.0 * a)).sqrt()
-        * ((-a * (swap_dates[0] - mat_op)).exp() - (-a * (modified_mat - mat_op)).exp())
+        * ((one - (a * (-2.0 * mat_op)).exp()) / (a * 2.0)).sqrt()
+        * ((a * -(swap_dates[0] - mat_op)).exp() - (-a * (modified_mat - mat_op)).exp())
 }
 
-fn coupons(swap_dates: &Vec<f64>, strike: f64, curve: Curve) -> Vec<f64> {
+fn coupons<T: Scalar>(swap_dates: &Vec<f64>, strike: T, curve: Curve) -> Vec<T> {
     let swap_length = swap_dates.len();
-    let mut coupons: Vec<f64> = Vec::with_capacity(swap_length - 1);
+    let mut coupons: Vec<T> = Vec::with_capacity(swap_length - 1);
     for i in 0..swap_length - 2 {
         coupons.push(
-            1.0 - (df(curve, swap_dates[i + 1]) * df(Ois, swap_dates[i + 2]))
-                / (df(curve, swap_dates[i + 2]) * df(Ois, swap_dates[i + 1]))
-                + strike * (swap_dates[i + 1] - swap_dates[i]),
+            strike * (swap_dates[i + 1] - swap_dates[i])
+                + (1.0
+                    - (df(curve, swap_dates[i + 1]) * df(Ois, swap_dates[i + 2]))
+                        / (df(curve, swap_dates[i + 2]) * df(Ois, swap_dates[i + 1]))),
         );
     }
-    coupons.push(1.0 + strike * (swap_dates[swap_length - 1] - swap_dates[swap_length - 2]));
+    coupons.push(strike * (swap_dates[swap_length - 1] - swap_dates[swap_length - 2]) + 1.0);
     coupons
 }
 
-fn coupon_bearing_bond(swap_dates: &Vec<f64>, strike: f64, curve: Curve) -> f64 {
+fn coupon_bearing_bond<T: Scalar>(swap_dates: &Vec<f64>, strike: T, curve: Curve) -> T {
     coupons(swap_dates, strike, curve)
         .iter()
         .zip(swap_dates[1..].iter())
-        .fold(0.0, |acc, (coupon, date)| acc + coupon * df(Ois, *date))
+        .fold(T::from_f64(0.0), |acc, (coupon, date)| {
+            acc + *coupon * df(Ois, *date)
+        })
 }
@@ -1,6 +1,8 @@
 use super::analysis;
 use super::curve;
 use super::optimization;
+use crate::ad::scalar::Scalar;
+use crate::ad::tape::Var;
 
 pub fn swaption_with_maturities(
     init_a: f64,
@@ -16,10 +18,14 @@ pub fn swaption_with_maturities(
         let [a, sigma] = [params[0], params[1]];
         swaption_shifted_maturity(a, sigma, maturity, strike, &swap_dates, op_type, curve)
     };
+    let rap_swaption_ad = move |maturity: f64, params: &[Var]| {
+        let [a, sigma] = [params[0], params[1]];
+        let strike = Var::from_f64(strike);
+        swaption_shifted_maturity(a, sigma, maturity, strike, &swap_dates, op_type, curve)
+    };
     let func_args = vec![init_a, init_sigma];
     let are_adjustings = vec![true, true];
-    let derivative_funcs =
-        optimization::derivative_funcs_numerical_difference_for_lm(&rap_swaption, 2);
+    let derivative_funcs = optimization::derivative_funcs_reverse_ad_for_lm(&rap_swaption_ad, 2);
     let adjusted_params = optimization::levenberg_marquardt(
         &rap_swaption,
         func_args,
@@ -31,15 +37,15 @@ pub fn swaption_with_maturities(
     (adjusted_params[0], adjusted_params[1])
 }
 
-fn swaption_shifted_maturity(
-    a: f64,
-    sigma: f64,
+fn swaption_shifted_maturity<T: Scalar>(
+    a: T,
+    sigma: T,
     mat_op: f64,
-    strike: f64,
+    strike: T,
     swap_dates: &Vec<f64>,
     op_type: analysis::SwaptionType,
     curve: curve::Curve,
-) -> f64 {
+) -> T {
     let sliced_swap_dates = slice_swap_dates(mat_op, swap_dates);
     analysis::swaption(a, sigma, mat_op, strike, &sliced_swap_dates, op_type, curve)
 }
@@ -66,10 +72,14 @@ pub fn swaption_with_strikes(
         let [a, sigma] = [params[0], params[1]];
         analysis::swaption(a, sigma, maturity, strike, &swap_dates, op_type, curve)
     };
+    let rap_swaption_ad = move |strike: f64, params: &[Var]| {
+        let [a, sigma] = [params[0], params[1]];
+        let strike = Var::from_f64(strike);
+        analysis::swaption(a, sigma, maturity, strike, &swap_dates, op_type, curve)
+    };
     let func_args = vec![init_a, init_sigma];
     let are_adjustings = vec![true, true];
-    let derivative_funcs =
-        optimization::derivative_funcs_numerical_difference_for_lm(&rap_swaption, 2);
+    let derivative_funcs = optimization::derivative_funcs_reverse_ad_for_lm(&rap_swaption_ad, 2);
     let adjusted_params = optimization::levenberg_marquardt(
         &rap_swaption,
         func_args,
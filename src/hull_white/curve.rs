@@ -1,6 +1,8 @@
 use super::data;
 use super::interpolation::cubic_spline;
-use super::optimization::{derivative_funcs_numerical_difference_for_lm, levenberg_marquardt};
+use super::optimization::{derivative_funcs_reverse_ad_for_lm, levenberg_marquardt};
+use crate::ad::scalar::Scalar;
+use crate::ad::tape::Var;
 
 #[derive(Clone, Copy, Debug)]
 pub enum Curve {
@@ -42,10 +44,10 @@ pub fn instantaneous_forward_rate(curve: Curve, t: f64) -> f64 {
     let params = vec![beta0, beta1, beta2, beta3, tau1, tau2];
     let are_adjustings = params.iter().map(|_| true).collect::<Vec<bool>>();
     let derivative_funcs =
-        derivative_funcs_numerical_difference_for_lm(&zero_rate_svensson, params.len());
+        derivative_funcs_reverse_ad_for_lm(&zero_rate_svensson::<Var>, params.len());
 
     let adjusted_params = levenberg_marquardt(
-        &zero_rate_svensson,
+        &zero_rate_svensson::<f64>,
         params,
         are_adjustings,
         derivative_funcs,
@@ -70,17 +72,20 @@ fn instantaneous_forward_rate_svensson(t: f64, params: &[f64]) -> f64 {
         + beta3 * (t / tau2) * (-t / tau2).exp()
 }
 
-fn zero_rate_svensson(t: f64, params: &[f64]) -> f64 {
+fn zero_rate_svensson<T: Scalar>(t: f64, params: &[T]) -> T {
     let beta0 = params[0];
     let beta1 = params[1];
     let beta2 = params[2];
     let beta3 = params[3];
     let tau1 = params[4];
     let tau2 = params[5];
+    let one = T::from_f64(1.0);
+    let ratio1 = T::from_f64(t) / tau1;
+    let ratio2 = T::from_f64(t) / tau2;
     beta0
-        + beta1 * (1.0 - (-t / tau1).exp()) / (t / tau1)
-        + beta2 * ((1.0 - (-t / tau1).exp()) / (t / tau1) - (-t / tau1).exp())
-        + beta3 * ((1.0 - (-t / tau2).exp()) / (t / tau2) - (-t / tau2).exp())
+        + beta1 * (one - (-ratio1).exp()) / ratio1
+        + beta2 * ((one - (-ratio1).exp()) / ratio1 - (-ratio1).exp())
+        + beta3 * ((one - (-ratio2).exp()) / ratio2 - (-ratio2).exp())
 }
 
 #[cfg(test)]
@@ -1,6 +1,17 @@
-use super::analysis;
-use super::curve;
-use super::tree;
+// aをゼロとみなす閾値(a * 期間がこれより小さければ極限の式を使う)
+const SMALL_DECAY: f64 = 1e-8;
+
+// a * 期間がこれより小さければ φ(w) = (1 - e^{-aw}) / a を含む積分を級数で計算する(閉じた式は a * 期間で桁落ちする)
+const SERIES_DECAY: f64 = 1e-3;
+
+/* 区分的に一定なa(t)、σ(t)のHull-Whiteモデル
+r(t) = f(0, t) + x(t), dx = (y(t) - a(t) x) dt + σ(t) dW (x(0) = 0) とする。
+E(t) = exp(∫_0^t a(u) du)、G(t, T) = ∫_t^T E(t) / E(u) du とすると
+y(t) = ∫_0^t σ(u)^2 (E(u) / E(t))^2 du、x(t)の期待値は ψ(t) = ∫_0^t σ(u)^2 E(u) / E(t) G(u, t) du、
+割引債価格は P(t, T) = P(0, T) / P(0, t) exp(-G(t, T) x(t) - G(t, T)^2 y(t) / 2) となる。
+x(t0)に対して ∫_{t0}^{t1} x(u) du と x(t1) は2変量正規分布に従い、
+Var(∫x) = ∫ σ(s)^2 G(s, t1)^2 ds、Cov(∫x, x(t1)) = ∫ σ(s)^2 E(s) / E(t1) G(s, t1) ds となる。
+a、σが一定の区間ごとに積分を閉じた式で計算する。 */
 
 #[derive(Clone, Debug)]
 pub struct HullWhite {
@@ -24,4 +35,232 @@ impl HullWhite {
             sigma_interval,
         }
     }
+
+    /// 指定したポイントでのpiecewise-constantなaを返します。
+    /// * `target` - 戻り値のポイント
+    pub fn a_at(&self, target: f64) -> f64 {
+        piecewise_constant_value(&self.a, &self.a_interval, target)
+    }
+
+    /// 指定したポイントでのpiecewise-constantなsigmaを返します。
+    /// * `target` - 戻り値のポイント
+    pub fn sigma_at(&self, target: f64) -> f64 {
+        piecewise_constant_value(&self.sigma, &self.sigma_interval, target)
+    }
+
+    /// G(t, T) = ∫_t^T E(t) / E(u) du (割引債価格の状態変数xに対する感応度)を返します。
+    /// * `t` - 時点
+    /// * `mat` - 割引債の満期
+    pub fn g(&self, t: f64, mat: f64) -> f64 {
+        let mut decay: f64 = 0.0; // ∫_t^{s0} a
+        let mut g = 0.0;
+        for (s0, s1, a, _) in self.pieces(t, mat) {
+            g += (-decay).exp() * decay_integral(a, s1 - s0);
+            decay += a * (s1 - s0);
+        }
+        g
+    }
+
+    /// x(t0)に対するx(t1)の条件付き分散 ∫_{t0}^{t1} σ(u)^2 (E(u) / E(t1))^2 du を返します。
+    /// * `t0` - 始点
+    /// * `t1` - 終点
+    pub fn variance(&self, t0: f64, t1: f64) -> f64 {
+        let mut decay: f64 = 0.0; // ∫_{s1}^{t1} a
+        let mut variance = 0.0;
+        for (s0, s1, a, sigma) in self.pieces(t0, t1).into_iter().rev() {
+            variance += sigma.powi(2) * (-2.0 * decay).exp() * decay_integral(2.0 * a, s1 - s0);
+            decay += a * (s1 - s0);
+        }
+        variance
+    }
+
+    /// y(t) = x(t)の分散 を返します。
+    /// * `t` - 時点
+    pub fn y(&self, t: f64) -> f64 {
+        self.variance(0.0, t)
+    }
+
+    /// ψ(t) = x(t)の期待値 を返します。
+    /// * `t` - 時点
+    pub fn psi(&self, t: f64) -> f64 {
+        let mut decay: f64 = 0.0; // ∫_{s1}^t a
+        let mut g = 0.0; // G(s1, t)
+        let mut psi = 0.0;
+        for (s0, s1, a, sigma) in self.pieces(0.0, t).into_iter().rev() {
+            let width = s1 - s0;
+            // ∫_0^W e^{-aw} G(s1 - w, t) dw、G(s1 - w, t) = (1 - e^{-aw}) / a + e^{-aw} G(s1, t)
+            let integral = decay_phi(a, width) + g * decay_integral(2.0 * a, width);
+            psi += sigma.powi(2) * (-decay).exp() * integral;
+            g = decay_integral(a, width) + (-a * width).exp() * g;
+            decay += a * width;
+        }
+        psi
+    }
+
+    /// x(t0)に対する ∫_{t0}^{t1} x(u) du の条件付き分散と、x(t1)との条件付き共分散をtupleで返します。
+    /// * `t0` - 始点
+    /// * `t1` - 終点
+    pub fn integral_covariance(&self, t0: f64, t1: f64) -> (f64, f64) {
+        let mut decay: f64 = 0.0; // ∫_{s1}^{t1} a
+        let mut g = 0.0; // G(s1, t1)
+        let (mut variance, mut covariance) = (0.0, 0.0);
+        for (s0, s1, a, sigma) in self.pieces(t0, t1).into_iter().rev() {
+            let width = s1 - s0;
+            // G(s1 - w, t1) = φ(w) + e^{-aw} G(s1, t1)
+            let (decay_phi, phi_squared) = (decay_phi(a, width), phi_squared(a, width));
+            let double_decay = decay_integral(2.0 * a, width);
+            variance +=
+                sigma.powi(2) * (phi_squared + 2.0 * g * decay_phi + g.powi(2) * double_decay);
+            covariance += sigma.powi(2) * (-decay).exp() * (decay_phi + g * double_decay);
+            g = decay_integral(a, width) + (-a * width).exp() * g;
+            decay += a * width;
+        }
+        (variance, covariance)
+    }
+
+    /// x(t0)に対するx(t1)の条件付き期待値の x(t0) の係数 E(t0) / E(t1) を返します。
+    /// * `t0` - 始点
+    /// * `t1` - 終点
+    pub fn decay_factor(&self, t0: f64, t1: f64) -> f64 {
+        let decay: f64 = self
+            .pieces(t0, t1)
+            .iter()
+            .map(|(s0, s1, a, _)| a * (s1 - s0))
+            .sum();
+        (-decay).exp()
+    }
+
+    // [t0, t1]をaとσのintervalで区切った区間ごとの(始点, 終点, a, σ)
+    fn pieces(&self, t0: f64, t1: f64) -> Vec<(f64, f64, f64, f64)> {
+        let mut knots: Vec<f64> = self
+            .a_interval
+            .iter()
+            .chain(self.sigma_interval.iter())
+            .cloned()
+            .filter(|knot| t0 < *knot && *knot < t1)
+            .collect();
+        knots.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        knots.dedup();
+        let mut bounds = vec![t0];
+        bounds.append(&mut knots);
+        bounds.push(t1);
+        bounds
+            .windows(2)
+            .filter(|w| w[1] > w[0])
+            .map(|w| (w[0], w[1], self.a_at(w[0]), self.sigma_at(w[0])))
+            .collect()
+    }
+}
+
+/// piecewise-constantなパラメータの指定したポイントでの値を返します。<br>
+/// intervalの外側の値については端の値に一致するものとします。
+/// * `val` - piecewise-constantなパラメータの値のベクタ
+/// * `interval` - piecewise-constantなパラメータの値に対応する時間間隔のベクタ（左端）
+/// * `target` - 戻り値のポイント
+pub fn piecewise_constant_value(val: &[f64], interval: &[f64], target: f64) -> f64 {
+    if target < interval[0] {
+        return val[0];
+    }
+    if target > interval[interval.len() - 1] {
+        return val[interval.len() - 1];
+    }
+    let val_index = interval[1..]
+        .iter()
+        .take_while(|knot| target >= **knot)
+        .count();
+    val[val_index]
+}
+
+// ∫_0^W e^{-aw} dw = (1 - e^{-aW}) / a
+fn decay_integral(a: f64, width: f64) -> f64 {
+    if (a * width).abs() < SMALL_DECAY {
+        width
+    } else {
+        -(-a * width).exp_m1() / a
+    }
+}
+
+// ∫_0^W e^{-aw} φ(w) dw、φ(w) = (1 - e^{-aw}) / a
+fn decay_phi(a: f64, width: f64) -> f64 {
+    if (a * width).abs() < SERIES_DECAY {
+        width.powi(2) / 2.0 - a * width.powi(3) / 2.0 + 7.0 * a.powi(2) * width.powi(4) / 24.0
+    } else {
+        (decay_integral(a, width) - decay_integral(2.0 * a, width)) / a
+    }
+}
+
+// ∫_0^W φ(w)^2 dw
+fn phi_squared(a: f64, width: f64) -> f64 {
+    if (a * width).abs() < SERIES_DECAY {
+        width.powi(3) / 3.0 - a * width.powi(4) / 4.0 + 7.0 * a.powi(2) * width.powi(5) / 60.0
+    } else {
+        (width - 2.0 * decay_integral(a, width) + decay_integral(2.0 * a, width)) / a.powi(2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_parameters() {
+        // 区間で区切っても値が同じなら、定数のa、σの閉じた式に一致する。
+        let (a, sigma, t) = (0.1, 0.01, 3.0);
+        let hw = HullWhite::new(
+            vec![a, a],
+            vec![0.0, 1.0],
+            vec![sigma; 3],
+            vec![0.0, 0.5, 2.0],
+        );
+        let g = (1.0 - (-a * (5.0 - t)).exp()) / a;
+        let y = sigma.powi(2) * (1.0 - (-2.0 * a * t).exp()) / (2.0 * a);
+        let psi = sigma.powi(2) / (2.0 * a.powi(2)) * (1.0 - (-a * t).exp()).powi(2);
+        assert!((hw.g(t, 5.0) - g).abs() < 1e-14);
+        assert!((hw.y(t) - y).abs() < 1e-14);
+        assert!((hw.psi(t) - psi).abs() < 1e-14);
+        assert!((hw.decay_factor(1.0, t) - (-a * (t - 1.0)).exp()).abs() < 1e-14);
+    }
+
+    #[test]
+    fn test_psi_piecewise() {
+        // ψ' = y - a ψ を差分で確認する。
+        let hw = HullWhite::new(
+            vec![0.05, 0.2, 0.001],
+            vec![0.0, 1.0, 2.5],
+            vec![0.01, 0.006],
+            vec![0.0, 1.7],
+        );
+        let h = 1e-5;
+        for t in [0.5, 1.3, 2.0, 3.0] {
+            let derivative = (hw.psi(t + h) - hw.psi(t - h)) / (2.0 * h);
+            assert!((derivative - (hw.y(t) - hw.a_at(t) * hw.psi(t))).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_integral_covariance() {
+        // 区間ごとの閉じた式(aが小さい区間を含む)を中点公式の数値積分と比べる。
+        let hw = HullWhite::new(
+            vec![0.05, 1e-5, 0.3],
+            vec![0.0, 1.0, 2.5],
+            vec![0.01, 0.006],
+            vec![0.0, 1.7],
+        );
+        // 中点公式の格子がパラメータの区間の端点に揃うようにする。
+        let (t0, t1) = (0.5, 3.0);
+        let num = 25000;
+        let h = (t1 - t0) / num as f64;
+        let (mut variance, mut covariance) = (0.0, 0.0);
+        for i in 0..num {
+            let s = t0 + (i as f64 + 0.5) * h;
+            let g = hw.g(s, t1);
+            variance += hw.sigma_at(s).powi(2) * g.powi(2) * h;
+            covariance += hw.sigma_at(s).powi(2) * hw.decay_factor(s, t1) * g * h;
+        }
+        let (exact_variance, exact_covariance) = hw.integral_covariance(t0, t1);
+        assert!((exact_variance / variance - 1.0).abs() < 1e-7);
+        assert!((exact_covariance / covariance - 1.0).abs() < 1e-7);
+        // x(t1)との相関は1未満
+        assert!(exact_covariance.powi(2) < exact_variance * hw.variance(t0, t1));
+    }
 }
@@ -5,45 +5,82 @@
 // ④targetにおけるレートを算出する。
 
 pub fn cubic_spline(dates: &Vec<f64>, rates: &Vec<f64>, target: f64) -> f64 {
-    // TODO 引数チェックを入れる
-
-    let equation_matrix = set_up_equation(&dates, &rates);
-    let mut solution_of_equation = solve_equation(equation_matrix);
-
-    // natural spline
-    let mut second_derive = vec![0.0];
-    second_derive.append(&mut solution_of_equation);
-    second_derive.append(&mut vec![0.0]);
-
-    // targetの値が属する区間の始点側のindexを算出する。
-    // extrapolationとなる点は分岐に入る頻度が少ないので後ろに記述
-    let mut target_idx = 0;
-    if dates[0] <= target && target <= dates[dates.len() - 1] {
-        for (i, date) in dates.iter().enumerate() {
-            if date > &target {
-                target_idx = i - 1;
-                break;
-            }
+    CubicSpline::new(dates, rates).value(target)
+}
+
+/// 連立方程式の解(2階微分)を保持する natural cubic spline です。<br>
+/// 同じデータで何度も補間する場合は cubic_spline の代わりに使い、連立方程式を解くのを1回にします。
+#[derive(Clone, Debug)]
+pub struct CubicSpline {
+    dates: Vec<f64>,
+    rates: Vec<f64>,
+    second_derive: Vec<f64>,
+}
+
+impl CubicSpline {
+    pub fn new(dates: &Vec<f64>, rates: &Vec<f64>) -> Self {
+        // TODO 引数チェックを入れる
+
+        let equation_matrix = set_up_equation(dates, rates);
+        let mut solution_of_equation = solve_equation(equation_matrix);
+
+        // natural spline
+        let mut second_derive = vec![0.0];
+        second_derive.append(&mut solution_of_equation);
+        second_derive.append(&mut vec![0.0]);
+        Self {
+            dates: dates.clone(),
+            rates: rates.clone(),
+            second_derive,
         }
-    } else if target <= dates[0] {
-        target_idx = 0;
-    } else {
-        target_idx = dates.len() - 2;
     }
 
-    // 多項式の係数を算出する。a_j * (x - x_j)^3 + b_j * (x - x_j)^2 + c_j * (x - x_j) + d_j
-    let a = (second_derive[target_idx + 1] - second_derive[target_idx])
-        / (6.0 * (dates[target_idx + 1] - dates[target_idx]));
-    let b = second_derive[target_idx] * 0.5;
-    let c = (rates[target_idx + 1] - rates[target_idx])
-        / (dates[target_idx + 1] - dates[target_idx]) as f64
-        - (dates[target_idx + 1] - dates[target_idx]) as f64
-            * (2.0 * second_derive[target_idx] + second_derive[target_idx + 1])
-            / 6.0;
-    let d = rates[target_idx];
-
-    let period = (target - dates[target_idx]) as f64;
-    a * period.powf(3.0) + b * period.powf(2.0) + c * period + d
+    /// targetにおける値を返します。
+    pub fn value(&self, target: f64) -> f64 {
+        let (target_idx, (a, b, c, d)) = self.coefficients(target);
+        let period = target - self.dates[target_idx];
+        a * period.powf(3.0) + b * period.powf(2.0) + c * period + d
+    }
+
+    /// targetにおける1階微分を返します。
+    pub fn derivative(&self, target: f64) -> f64 {
+        let (target_idx, (a, b, c, _)) = self.coefficients(target);
+        let period = target - self.dates[target_idx];
+        3.0 * a * period.powi(2) + 2.0 * b * period + c
+    }
+
+    // targetの値が属する区間の始点側のindexと、その区間の多項式の係数を返す。
+    fn coefficients(&self, target: f64) -> (usize, (f64, f64, f64, f64)) {
+        let dates = &self.dates;
+        let rates = &self.rates;
+        let second_derive = &self.second_derive;
+
+        // targetの値が属する区間の始点側のindexを算出する。
+        // extrapolationとなる点は分岐に入る頻度が少ないので後ろに記述
+        let target_idx = if dates[0] <= target && target <= dates[dates.len() - 1] {
+            // targetが最後の点に一致する場合は最後の区間
+            dates
+                .iter()
+                .position(|date| date > &target)
+                .map_or(dates.len() - 2, |i| i - 1)
+        } else if target <= dates[0] {
+            0
+        } else {
+            dates.len() - 2
+        };
+
+        // 多項式の係数を算出する。a_j * (x - x_j)^3 + b_j * (x - x_j)^2 + c_j * (x - x_j) + d_j
+        let a = (second_derive[target_idx + 1] - second_derive[target_idx])
+            / (6.0 * (dates[target_idx + 1] - dates[target_idx]));
+        let b = second_derive[target_idx] * 0.5;
+        let c = (rates[target_idx + 1] - rates[target_idx])
+            / (dates[target_idx + 1] - dates[target_idx])
+            - (dates[target_idx + 1] - dates[target_idx])
+                * (2.0 * second_derive[target_idx] + second_derive[target_idx + 1])
+                / 6.0;
+        let d = rates[target_idx];
+        (target_idx, (a, b, c, d))
+    }
 }
 
 // cubic spline のための連立方程式を表す3行対角行列の上中下の対角成分で構成される各ベクタと右辺のベクタをtupleで返す
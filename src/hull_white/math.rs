@@ -1,9 +1,10 @@
-use libm::erf;
+use crate::ad::scalar::Scalar;
+use std::f64::consts::PI;
 use std::f64::{INFINITY, NEG_INFINITY};
 
 /// 標準正規分布の分布関数です。
-pub fn std_normal_cdf(x: f64) -> f64 {
-    0.5 * (1.0 + erf(x / 2_f64.sqrt()))
+pub fn std_normal_cdf<T: Scalar>(x: T) -> T {
+    ((x / 2_f64.sqrt()).erf() + 1.0) * 0.5
 }
 
 /// Acklam's algorithm による標準正規分布の分布関数の逆関数です。
@@ -211,6 +212,139 @@ pub fn moro_inverse_std_normal_cdf(x: f64) -> f64 {
     moro_inverse_normal_cdf(0.0, 1.0, x)
 }
 
+/// 相関rhoの2変量標準正規分布の分布関数 P(X <= a, Y <= b) です。<br>
+/// M(a, b; ρ) = ∫_{-∞}^{a} φ(x) N((b - ρx) / √(1 - ρ^2)) dx をSimpson公式で数値積分します。
+pub fn bivariate_std_normal_cdf(a: f64, b: f64, rho: f64) -> f64 {
+    if rho >= 1.0 {
+        return std_normal_cdf(a.min(b));
+    }
+    if rho <= -1.0 {
+        return (std_normal_cdf(a) + std_normal_cdf(b) - 1.0).max(0.0);
+    }
+    // 積分の下限は-∞の代わりに十分小さい値で打ち切る。
+    let lower = -10.0;
+    if a <= lower {
+        return 0.0;
+    }
+    let num_interval = 2000; // 偶数
+    let h = (a - lower) / num_interval as f64;
+    let rho_sqrt = (1.0 - rho.powi(2)).sqrt();
+    let integrand = |x: f64| -> f64 {
+        (-0.5 * x.powi(2)).exp() / (2.0 * PI).sqrt() * std_normal_cdf((b - rho * x) / rho_sqrt)
+    };
+    let mut sum = integrand(lower) + integrand(a);
+    for i in 1..num_interval {
+        let weight = if i % 2 == 1 { 4.0 } else { 2.0 };
+        sum += weight * integrand(lower + i as f64 * h);
+    }
+    sum * h / 3.0
+}
+
+/// Lanczos近似によるガンマ関数の対数 ln Γ(x) (x > 0) です。
+pub fn ln_gamma(x: f64) -> f64 {
+    let coef = [
+        76.18009172947146,
+        -86.50532032941677,
+        24.01409824083091,
+        -1.231739572450155,
+        0.1208650973866179e-2,
+        -0.5395239384953e-5,
+    ];
+    let tmp = x + 5.5 - (x + 0.5) * (x + 5.5).ln();
+    let mut ser = 1.000000000190015;
+    for (j, c) in coef.iter().enumerate() {
+        ser += c / (x + 1.0 + j as f64);
+    }
+    -tmp + (2.5066282746310005 * ser / x).ln()
+}
+
+/// 正則化された下側不完全ガンマ関数 P(a, x) = γ(a, x) / Γ(a) です。<br>
+/// x < a + 1 では級数展開、それ以外では連分数展開で計算します。
+pub fn regularized_lower_gamma(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    let max_iter = 500;
+    let eps = 1e-15;
+    let log_prefactor = -x + a * x.ln() - ln_gamma(a);
+    if x < a + 1.0 {
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        for n in 1..max_iter {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term.abs() < sum.abs() * eps {
+                break;
+            }
+        }
+        sum * log_prefactor.exp()
+    } else {
+        // Lentz法による連分数
+        let tiny = 1e-300;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for i in 1..max_iter {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = b + an / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < eps {
+                break;
+            }
+        }
+        1.0 - log_prefactor.exp() * h
+    }
+}
+
+/// 非心カイ二乗分布の分布関数です。ポアソン分布で重み付けしたガンマ分布の分布関数の和で計算します。
+/// * `x` - 値
+/// * `dof` - 自由度
+/// * `non_centrality` - 非心度
+pub fn noncentral_chi_square_cdf(x: f64, dof: f64, non_centrality: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    let half_nc = 0.5 * non_centrality;
+    if half_nc <= 0.0 {
+        return regularized_lower_gamma(0.5 * dof, 0.5 * x);
+    }
+    // ポアソン分布の重みが最大となる項から両側に足していく。
+    let mode = half_nc.floor();
+    let log_weight = |j: f64| -half_nc + j * half_nc.ln() - ln_gamma(j + 1.0);
+    let mut sum = 0.0;
+    let mut j = mode;
+    loop {
+        let weight = log_weight(j).exp();
+        sum += weight * regularized_lower_gamma(0.5 * dof + j, 0.5 * x);
+        if j <= 0.0 || weight < 1e-16 {
+            break;
+        }
+        j -= 1.0;
+    }
+    let mut j = mode + 1.0;
+    loop {
+        let weight = log_weight(j).exp();
+        let term = weight * regularized_lower_gamma(0.5 * dof + j, 0.5 * x);
+        sum += term;
+        if weight < 1e-16 {
+            break;
+        }
+        j += 1.0;
+    }
+    sum.min(1.0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -366,4 +500,31 @@ mod tests {
         assert!((std_normal_cdf(3.5) - 0.9997673709209645).abs() < threshold);
         assert!((std_normal_cdf(4.0) - 0.9999683287581669).abs() < threshold);
     }
+
+    #[test]
+    fn test_bivariate_std_normal_cdf() {
+        let threshold = 10_f64.powi(-8);
+        // M(0, 0; ρ) = 1/4 + arcsin(ρ) / 2π
+        for rho in [-0.9, -0.5, 0.0, 0.3, 0.95] {
+            let expected = 0.25 + f64::asin(rho) / (2.0 * PI);
+            assert!((bivariate_std_normal_cdf(0.0, 0.0, rho) - expected).abs() < threshold);
+        }
+        // 無相関なら周辺分布の積
+        let expected = std_normal_cdf(0.5) * std_normal_cdf(-1.2);
+        assert!((bivariate_std_normal_cdf(0.5, -1.2, 0.0) - expected).abs() < threshold);
+    }
+
+    #[test]
+    fn test_incomplete_gamma() {
+        // P(1/2, x^2 / 2) = 2N(x) - 1, P(1, x) = 1 - e^{-x}
+        for x in [0.1_f64, 0.7, 1.5, 3.0, 6.0] {
+            let expected = 2.0 * std_normal_cdf(x) - 1.0;
+            assert!((regularized_lower_gamma(0.5, 0.5 * x * x) - expected).abs() < 1e-7);
+            assert!((regularized_lower_gamma(1.0, x) - (1.0 - (-x).exp())).abs() < 1e-12);
+        }
+        // 自由度1の非心カイ二乗: P(X <= x) = N(√x - √λ) - N(-√x - √λ)
+        let (x, nc) = (2.3_f64, 1.7_f64);
+        let expected = std_normal_cdf(x.sqrt() - nc.sqrt()) - std_normal_cdf(-x.sqrt() - nc.sqrt());
+        assert!((noncentral_chi_square_cdf(x, 1.0, nc) - expected).abs() < 1e-7);
+    }
 }
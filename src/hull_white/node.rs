@@ -35,7 +35,7 @@ impl Node {
 
     /// 金利の変化幅の期待値を返します。
     pub fn calc_fluctuation_mean(a: f64, rate: f64, time_interval: f64) -> f64 {
-        ((-a + time_interval).exp() - 1.0) * rate
+        ((-a * time_interval).exp() - 1.0) * rate
     }
 
     /// 金利の変化幅の分散を返します。
@@ -77,7 +77,7 @@ impl Node {
 
     /// 遷移確率(上昇)を返します。
     fn calc_prob_up(alpha: f64, rate_fluctuation_var: f64, next_rate_fluctuation: f64) -> f64 {
-        rate_fluctuation_var / (2.0 * next_rate_fluctuation.powi(2)) + 0.5 * alpha + (alpha + 1.0)
+        rate_fluctuation_var / (2.0 * next_rate_fluctuation.powi(2)) + 0.5 * alpha * (alpha + 1.0)
     }
 
     /// 遷移確率(中間)を返します。
@@ -87,7 +87,7 @@ impl Node {
 
     /// 遷移確率(下落)を返します。
     fn calc_prob_down(alpha: f64, rate_fluctuation_var: f64, next_rate_fluctuation: f64) -> f64 {
-        rate_fluctuation_var / (2.0 * next_rate_fluctuation.powi(2)) + 0.5 * alpha + (alpha - 1.0)
+        rate_fluctuation_var / (2.0 * next_rate_fluctuation.powi(2)) + 0.5 * alpha * (alpha - 1.0)
     }
 
     /// 遷移先(上昇)のインデックスを返します。
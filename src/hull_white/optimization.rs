@@ -1,3 +1,4 @@
+use crate::ad::tape::{gradient, Var};
 use ndarray::{Array1, Array2};
 use ndarray_linalg::{FactorizeInto, Solve};
 
@@ -273,6 +274,27 @@ where
     derivative_funcs
 }
 
+/// levenberg_marquardtのderivative_funcs引数にそのまま設定できる形式で、後退型自動微分による偏微分関数を返します。
+/// 返すクロージャの最初の引数は独立変数、2番目は調整パラメータのベクタです。
+/// * `f` - 調整パラメータをVarとした関数
+/// * `index_num` - 偏微分するパラメータの数(先頭から)
+pub fn derivative_funcs_reverse_ad_for_lm<'a, F>(
+    f: &'a F,
+    index_num: usize,
+) -> Vec<Box<dyn Fn(f64, &[f64]) -> f64 + 'a>>
+where
+    F: Fn(f64, &[Var]) -> Var + 'a,
+{
+    let mut derivative_funcs: Vec<Box<dyn Fn(f64, &[f64]) -> f64 + 'a>> =
+        Vec::with_capacity(index_num);
+    for i in 0..index_num {
+        derivative_funcs.push(Box::new(move |x: f64, v: &[f64]| {
+            gradient(|p| f(x, p), v).1[i]
+        }));
+    }
+    derivative_funcs
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -398,4 +420,31 @@ mod tests {
         assert_eq!(actual_vec[1], 1.9);
         assert!((actual_vec[2] - 1.04425287356321).abs() < tolerance);
     }
+
+    #[test]
+    fn test_levenberg_marquardt_reverse_ad() {
+        fn f<T: crate::ad::scalar::Scalar>(w: f64, v: &[T]) -> T {
+            v[0] + v[1] * w + v[2] * w.powi(2)
+        }
+
+        let func_args = vec![1.0, 1.9, 1.0];
+        let are_adjustings = vec![true, false, true];
+        // 調整するパラメータ(x, z)に関する偏微分のみを使う。
+        let derivative_funcs = derivative_funcs_reverse_ad_for_lm(&f::<Var>, 3);
+        let derivative_funcs = vec![&derivative_funcs[0], &derivative_funcs[2]];
+        let independent_vars = vec![0.0, 1.0, 2.0, 3.0, 4.0];
+        let dependent_vars = vec![-0.9, 1.9, 7.3, 13.8, 23.5];
+        let actual_vec = levenberg_marquardt(
+            &f::<f64>,
+            func_args,
+            are_adjustings,
+            derivative_funcs,
+            independent_vars,
+            dependent_vars,
+        );
+        let tolerance = 1e-10;
+        assert!((actual_vec[0] - (-0.945517241379294)).abs() < tolerance);
+        assert_eq!(actual_vec[1], 1.9);
+        assert!((actual_vec[2] - 1.04425287356321).abs() < tolerance);
+    }
 }
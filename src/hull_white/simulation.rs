@@ -0,0 +1,135 @@
+use super::curve::{match_curve, Curve};
+use super::hw_lib::HullWhite;
+use super::interpolation::CubicSpline;
+use crate::mc::path_generator::PathGenerator;
+use rand_chacha::ChaCha8Rng;
+
+/* 区分的に一定なa(t)、σ(t)のHull-Whiteモデルの短期金利のシミュレーション
+x(t) = z(t) + ψ(t) と分けると dz = -a(t) z dt + σ(t) dW (z(0) = 0) となり、
+z(t + Δt) = E(t) / E(t + Δt) z(t) + √(∫σ^2 (E(u) / E(t + Δt))^2 du) N(0, 1) と遷移分布から厳密にサンプリングできる。
+短期金利の積分 ∫_t^{t+Δt} r du = ln(P(0, t) / P(0, t + Δt)) + ∫ψ du + ∫z du も、∫z du を z(t + Δt) との2変量正規分布から
+同時にサンプリングする(2つ目の正規乱数を使う)。∫ψ du = G ψ(t) + G^2 y(t) / 2 + Var(∫z) / 2 は割引債価格との整合から求まる。
+r(t) = f(0, t) + x(t) のf(0, t)はディスカウントファクターの3次スプラインの微分から求め、
+エンジンの台形公式を使わないため、時間方向のステップが粗くてもディスカウントファクターの期待値は初期カーブに一致する。
+状態変数は[短期金利, x, 直前のステップの短期金利の積分] */
+
+pub struct HullWhiteSimulation {
+    pub hw: HullWhite,
+    curve: CubicSpline, // 初期カーブのディスカウントファクター
+}
+
+impl HullWhiteSimulation {
+    pub fn new(hw: HullWhite, curve: Curve) -> Self {
+        let (dates, dfs) = match_curve(curve);
+        Self {
+            hw,
+            curve: CubicSpline::new(&dates, &dfs),
+        }
+    }
+
+    /// 初期カーブのディスカウントファクター P(0, t) を返します。
+    pub fn initial_df(&self, t: f64) -> f64 {
+        self.curve.value(t)
+    }
+
+    /// 初期カーブの瞬間フォワードレート f(0, t) = -∂P(0, t)/∂t / P(0, t) を返します。
+    pub fn forward_rate(&self, t: f64) -> f64 {
+        -self.curve.derivative(t) / self.curve.value(t)
+    }
+
+    /// 時点tの状態変数xに対する割引債価格 P(t, T) を返します。
+    /// * `t` - 時点
+    /// * `mat` - 割引債の満期
+    /// * `x` - 時点tの状態変数x = r(t) - f(0, t)
+    pub fn bond_price(&self, t: f64, mat: f64, x: f64) -> f64 {
+        let g = self.hw.g(t, mat);
+        self.initial_df(mat) / self.initial_df(t) * (-g * x - 0.5 * g.powi(2) * self.hw.y(t)).exp()
+    }
+}
+
+impl PathGenerator for HullWhiteSimulation {
+    fn num_factors(&self) -> usize {
+        2
+    }
+
+    fn initial_state(&self) -> Vec<f64> {
+        vec![self.forward_rate(0.0), 0.0, 0.0]
+    }
+
+    fn evolve(
+        &self,
+        state: &mut [f64],
+        time: f64,
+        delta_t: f64,
+        normals: &[f64],
+        _: &mut ChaCha8Rng,
+    ) {
+        let next_time = time + delta_t;
+        let (psi, g) = (self.hw.psi(time), self.hw.g(time, next_time));
+        let z = state[1] - psi;
+        let variance = self.hw.variance(time, next_time);
+        let (integral_variance, covariance) = self.hw.integral_covariance(time, next_time);
+        let shock = variance.sqrt() * normals[0];
+        // ∫z du の z(t + Δt) に対する条件付き分布
+        let (slope, residual) = if variance > 0.0 {
+            let slope = covariance / variance;
+            (
+                slope,
+                (integral_variance - slope * covariance).max(0.0).sqrt(),
+            )
+        } else {
+            (0.0, integral_variance.sqrt())
+        };
+        let z_integral = g * z + slope * shock + residual * normals[1];
+        let psi_integral = g * psi + 0.5 * g.powi(2) * self.hw.y(time) + 0.5 * integral_variance;
+        let forward_integral = (self.initial_df(time) / self.initial_df(next_time)).ln();
+        state[2] = forward_integral + psi_integral + z_integral;
+        state[1] = z * self.hw.decay_factor(time, next_time) + shock + self.hw.psi(next_time);
+        state[0] = self.forward_rate(next_time) + state[1];
+    }
+
+    fn short_rate(&self, state: &[f64], _: f64) -> f64 {
+        state[0]
+    }
+
+    fn step_rate_integral(&self, state: &[f64]) -> Option<f64> {
+        Some(state[2])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mc::engine::McEngine;
+    use crate::mc::mc_result::mean_std_error;
+    use crate::mc::test_util::rand_gen;
+
+    #[test]
+    fn test_initial_curve() {
+        // ディスカウントファクターと割引債価格の期待値は初期カーブに一致する。
+        let hw = HullWhite::new(
+            vec![0.03, 0.1],
+            vec![0.0, 1.0],
+            vec![0.01, 0.015],
+            vec![0.0, 2.0],
+        );
+        let simulation = HullWhiteSimulation::new(hw, Curve::Ois);
+        let rand_gen = rand_gen(3);
+        // 短期金利の積分も厳密にサンプリングするため、観測日だけの粗いグリッドでも偏りがない。
+        for time_step in [1, 100] {
+            let engine = McEngine::new(&simulation, &[2.0, 5.0], time_step, &rand_gen);
+            let paths: Vec<_> = (0..20000).map(|i| engine.path(i)).collect();
+            let dfs: Vec<f64> = paths.iter().map(|path| path.fixing_df(1)).collect();
+            let (df, std_error) = mean_std_error(&dfs);
+            assert!((df - simulation.initial_df(5.0)).abs() < 4.0 * std_error + 1e-6);
+            let bonds: Vec<f64> = paths
+                .iter()
+                .map(|path| {
+                    path.fixing_df(0) * simulation.bond_price(2.0, 5.0, path.fixing_state(0)[1])
+                })
+                .collect();
+            let (bond, std_error) = mean_std_error(&bonds);
+            assert!((bond - simulation.initial_df(5.0)).abs() < 4.0 * std_error + 1e-6);
+        }
+    }
+}
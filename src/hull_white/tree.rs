@@ -11,18 +11,15 @@ pub struct Tree {
     pub tree: Vec<Vec<Node>>,       // Treeの本体
 }
 
-// TODO Tree::new でTreeの構築が完了するようにロジックを修正する。
 impl Tree {
     // 初期処理
     // 時間方向のベクトルと金利方向の間隔を設定する。
     pub fn new(hw: HullWhite, time_vec: Vec<f64>) -> Self {
         let time_interval_num = time_vec.len();
-        let mut rate_interval = Vec::with_capacity(time_interval_num);
-        rate_interval[0] = 0.0; // 0番目はTreeのスタートでNodeが1個なのでrate_intervalは使用しないため0としておく。
+        let mut rate_interval = vec![0.0; time_interval_num]; // 0番目はTreeのスタートでNodeが1個なのでrate_intervalは使用しないため0としておく。
         for i in 1..time_interval_num {
             let time_interval = time_vec[i] - time_vec[i - 1];
-            let sigma =
-                Self::get_piecewise_constant_value(&hw.sigma, &hw.sigma_interval, time_vec[i]);
+            let sigma = hw.sigma_at(time_vec[i]);
             rate_interval[i] = Self::calc_rate_interval(sigma, time_interval);
         }
 
@@ -45,64 +42,49 @@ impl Tree {
         let tree_length = self.time_vec.len() - 1; // time_vecの最後の要素の時刻のNodeは不要なため-1する。
         let mut adjusting_params: Vec<f64> = Vec::with_capacity(tree_length);
         let mut tree: Vec<Vec<Node>> = Vec::with_capacity(tree_length);
-        tree[0] = Vec::with_capacity(1);
-        // 最初のNodeは前の時刻が存在しないため、別で計算が必要。
-        let a = self.get_a(self.time_vec[0]);
-        let sigma = self.get_sigma(self.time_vec[0]);
-        let rate_interval = self.rate_interval[0];
-        let next_rate_interval = self.rate_interval[1]; // 次の時刻の金利方向の変動幅
-        let time_interval = self.time_vec[1] - self.time_vec[0];
-        let node = Self::create_node(
-            a,
-            sigma,
-            0,
-            0,
-            rate_interval,
-            next_rate_interval,
-            time_interval,
-            &Vec::new(),
-            0.0,
-            0.0,
-        );
-        tree[0][0] = node;
-        let df = curve::df(Curve::Ois, self.time_vec[1]);
-        adjusting_params[0] = Self::calc_adjusting_param(&tree[0], time_interval, df);
 
-        // ２つめ以降のNode
-        for i in 1..tree_length - 1 {
+        for i in 0..tree_length {
             let a = self.get_a(self.time_vec[i]);
             let sigma = self.get_sigma(self.time_vec[i]);
-
-            // １つ前の時刻のNodeの金利方向のインデックスの最大値
-            let previous_max_rate_index = tree[i - 1].len() - 1;
-            // 今回のループで構築するNodeの金利方向のインデックスの最大値 = 前の時刻のNodeの金利方向の最大のインデックスの遷移(上昇)
-            let max_rate_index = tree[i - 1][previous_max_rate_index].get_transition_index_up();
             let rate_interval = self.rate_interval[i];
             let next_rate_interval = self.rate_interval[i + 1]; // 次の時刻の金利方向の変動幅
             let time_interval = self.time_vec[i + 1] - self.time_vec[i];
-            let node_num = 2 * max_rate_index + 1;
-            tree[i] = Vec::with_capacity(node_num as usize); // node_numは負になることはない。
 
-            for j in 0..2 * max_rate_index + 1 {
-                let p_node = &tree[i - 1];
+            // 最初のNodeは前の時刻が存在しないため、前の時刻のNodeを空とする。
+            let (p_nodes, p_time_interval, p_adjusting_param, max_rate_index) = if i == 0 {
+                (&[][..], 0.0, 0.0, 0)
+            } else {
+                let p_nodes = &tree[i - 1][..];
+                // 今回のループで構築するNodeの金利方向のインデックスの最大値 = 前の時刻のNodeの金利方向の最大のインデックスの遷移(上昇)
+                let max_rate_index = p_nodes[p_nodes.len() - 1].get_transition_index_up();
                 let p_time_interval = self.time_vec[i] - self.time_vec[i - 1];
-                let adjusting_param = adjusting_params[i - 1];
-                let node = Self::create_node(
-                    a,
-                    sigma,
-                    j,
-                    max_rate_index,
-                    rate_interval,
-                    next_rate_interval,
-                    time_interval,
-                    p_node,
+                (
+                    p_nodes,
                     p_time_interval,
-                    adjusting_param,
-                );
-                tree[i][j as usize] = node;
-            }
+                    adjusting_params[i - 1],
+                    max_rate_index,
+                )
+            };
+
+            let nodes: Vec<Node> = (0..2 * max_rate_index + 1)
+                .map(|j| {
+                    Self::create_node(
+                        a,
+                        sigma,
+                        j,
+                        max_rate_index,
+                        rate_interval,
+                        next_rate_interval,
+                        time_interval,
+                        p_nodes,
+                        p_time_interval,
+                        p_adjusting_param,
+                    )
+                })
+                .collect();
             let df = curve::df(Curve::Ois, self.time_vec[i + 1]);
-            adjusting_params[i] = Self::calc_adjusting_param(&tree[i], time_interval, df);
+            adjusting_params.push(Self::calc_adjusting_param(&nodes, time_interval, df));
+            tree.push(nodes);
         }
 
         Tree {
@@ -129,35 +111,13 @@ impl Tree {
     /// 指定したポイントでのpiecewise-constantなaを取得します。
     /// * `target` - 戻り値のポイント
     fn get_a(&self, target: f64) -> f64 {
-        Self::get_piecewise_constant_value(&self.hw.a, &self.hw.a_interval, target)
+        self.hw.a_at(target)
     }
 
     /// 指定したポイントでのpiecewise-constantなsigmaを取得します。
     /// * `target` - 戻り値のポイント
     fn get_sigma(&self, target: f64) -> f64 {
-        Self::get_piecewise_constant_value(&self.hw.sigma, &self.hw.sigma_interval, target)
-    }
-
-    /// piecewise-constantなパラメータの指定したポイントでの値を返します。<br>
-    /// intervalの外側の値については端の値に一致するものとします。
-    /// * `val` - piecewise-constantなパラメータの値のベクタ
-    /// * `interval` - piecewise-constantなパラメータの値に対応する時間間隔のベクタ（左端）
-    /// * `target` - 戻り値のポイント
-    fn get_piecewise_constant_value(val: &Vec<f64>, interval: &Vec<f64>, target: f64) -> f64 {
-        if target < interval[0] {
-            return val[0];
-        }
-        if target > interval[interval.len() - 1] {
-            return val[interval.len() - 1];
-        }
-        let mut val_index = 0;
-        for i in 1..interval.len() {
-            if target < interval[i] {
-                break;
-            }
-            val_index += 1;
-        }
-        val[val_index]
+        self.hw.sigma_at(target)
     }
 
     /// 金利方向のグリッドの間隔を返します。
@@ -186,7 +146,7 @@ impl Tree {
         rate_interval: f64,
         next_rate_interval: f64,
         time_interval: f64,
-        p_nodes: &Vec<Node>,
+        p_nodes: &[Node],
         p_time_interval: f64,
         p_adjusting_param: f64,
     ) -> Node {
@@ -226,7 +186,7 @@ impl Tree {
     /// * `nodes` - Nodeのベクタ
     /// * `time_interval` - 次の時刻までの時間間隔
     /// * `df` - マーケットのレートから計算したディスカウントファクター
-    fn calc_adjusting_param(nodes: &Vec<Node>, time_interval: f64, df: f64) -> f64 {
+    fn calc_adjusting_param(nodes: &[Node], time_interval: f64, df: f64) -> f64 {
         let mut unadjusted_df = 0.0;
         for node in nodes {
             unadjusted_df += node.arrow_debreu * (-node.rate * time_interval).exp()
@@ -241,7 +201,7 @@ impl Tree {
     /// * `p_adjusting_param` - 前の時刻の金利の調整項
     /// * `centering_index` - arrow_debreu を計算するNodeのセンタリングされたインデックス
     fn calc_arrow_debreu(
-        p_nodes: &Vec<Node>,
+        p_nodes: &[Node],
         p_time_interval: f64,
         p_adjusting_param: f64,
         centering_index: isize,
@@ -271,5 +231,34 @@ impl Tree {
         arrow_debreu
     }
 
-    // 各種商品をバックワードでプライシング → ファイル分ける
+    /// i+1番目の時刻の各Nodeの価値から、i番目の時刻の各Nodeの価値をバックワードで計算して返します。<br>
+    /// 各種商品のプライシングはこれを繰り返して行います。
+    /// * `i` - 時間方向のインデックス
+    /// * `next_values` - i+1番目の時刻の各Nodeの価値(最後の時刻は前の時刻の遷移先の数だけ)
+    pub fn rollback(&self, i: usize, next_values: &[f64]) -> Vec<f64> {
+        let time_interval = self.time_vec[i + 1] - self.time_vec[i];
+        // 次の時刻の金利方向のインデックスの最大値
+        let next_max_rate_index = ((next_values.len() - 1) / 2) as isize;
+        let value = |index: isize| next_values[(index + next_max_rate_index) as usize];
+        self.tree[i]
+            .iter()
+            .map(|node| {
+                (node.prob_up * value(node.get_transition_index_up())
+                    + node.prob_mid * value(node.get_transition_index_mid())
+                    + node.prob_down * value(node.get_transition_index_down()))
+                    * (-node.rate * time_interval).exp()
+            })
+            .collect()
+    }
+
+    /// i番目の時刻のNodeの数を返します。最後の時刻は前の時刻の遷移先の数です。
+    /// * `i` - 時間方向のインデックス
+    pub fn node_num(&self, i: usize) -> usize {
+        if i < self.tree.len() {
+            self.tree[i].len()
+        } else {
+            let last = &self.tree[self.tree.len() - 1];
+            (2 * last[last.len() - 1].get_transition_index_up() + 1) as usize
+        }
+    }
 }
@@ -1,4 +1,12 @@
+mod implied_tree;
 mod lattice_crr;
+mod lattice_trinomial;
+pub mod lattice_two_asset;
+mod node;
+
+use lattice_crr::{BarrierType, CashDividend, DividendMethod, ExerciseType, OptionType};
+use lattice_trinomial::TrinomialType;
+use lattice_two_asset::{TwoAssetInput, TwoAssetPayoff};
 
 pub fn run() {
     lattice_crr::crr_euro_call();
@@ -11,4 +19,128 @@ pub fn run() {
         term_annu: 0.5,
     };
     lattice_crr::crr_euro_call_layer(&input);
+
+    for exercise_type in [ExerciseType::European, ExerciseType::American] {
+        let greeks = lattice_crr::crr_greeks(&input, OptionType::Put, exercise_type, 181);
+        // extended treeの価格は通常のTreeの価格と一致する。
+        let price = lattice_crr::crr_price(&input, OptionType::Put, exercise_type, 181);
+        println!(
+            "(lattice {:?} put)price: {} (plain tree {}), delta: {}, gamma: {}, theta: {}, vega: {}, rho: {}",
+            exercise_type,
+            greeks.price,
+            price,
+            greeks.delta,
+            greeks.gamma,
+            greeks.theta,
+            greeks.vega,
+            greeks.rho
+        );
+    }
+
+    let dividends = [CashDividend {
+        time: 0.25,
+        amount: 2.0,
+    }];
+    for method in [DividendMethod::Escrowed, DividendMethod::NonRecombining] {
+        let val = lattice_crr::crr_dividend(
+            &input,
+            OptionType::Call,
+            ExerciseType::American,
+            &dividends,
+            method,
+            181,
+        );
+        println!(
+            "(lattice {:?})price of american call option: {}",
+            method, val
+        );
+    }
+
+    let barrier = 90.0;
+    let grid_num = lattice_crr::boyle_lau_steps(&input, barrier, 181);
+    let val = lattice_crr::crr_barrier(
+        &input,
+        OptionType::Call,
+        ExerciseType::European,
+        BarrierType::DownAndOut,
+        barrier,
+        grid_num,
+        false,
+    );
+    println!(
+        "(lattice boyle-lau {} steps)price of down and out call option: {}",
+        grid_num, val
+    );
+    let val = lattice_crr::crr_barrier(
+        &input,
+        OptionType::Put,
+        ExerciseType::American,
+        BarrierType::UpAndOut,
+        110.0,
+        181,
+        true,
+    );
+    println!(
+        "(lattice derman)price of american up and out put option: {}",
+        val
+    );
+
+    for tri_type in [
+        TrinomialType::Boyle(1.5_f64.sqrt()),
+        TrinomialType::KamradRitchken(1.5_f64.sqrt()),
+    ] {
+        let val = lattice_trinomial::trinomial(
+            &input,
+            OptionType::Call,
+            ExerciseType::European,
+            tri_type,
+            181,
+        );
+        println!(
+            "(trinomial {:?})price of european call option: {}",
+            tri_type, val
+        );
+    }
+
+    // 簡単なスキューのスマイル
+    let smile = |strike: f64, _term: f64| 0.2 - 0.1 * (strike / input.underlying).ln();
+    let implied_tree = implied_tree::ImpliedTree::new(
+        input.underlying,
+        input.zero_rate,
+        input.term_annu,
+        100,
+        &smile,
+    );
+    let val = implied_tree.price(input.strike, OptionType::Put, ExerciseType::American);
+    println!("(implied tree)price of american put option: {}", val);
+
+    let two_asset_input = TwoAssetInput {
+        underlying1: 100.0,
+        underlying2: 95.0,
+        vol1: 0.2,
+        vol2: 0.25,
+        div_yield1: 0.03,
+        div_yield2: 0.0,
+        corr: 0.6,
+        strike: 98.0,
+        zero_rate: 0.02,
+        term_annu: 0.5,
+    };
+    for payoff in [
+        TwoAssetPayoff::CallOnMax,
+        TwoAssetPayoff::PutOnMax,
+        TwoAssetPayoff::CallOnMin,
+        TwoAssetPayoff::PutOnMin,
+        TwoAssetPayoff::Exchange,
+    ] {
+        let euro =
+            lattice_two_asset::beg_two_asset(&two_asset_input, payoff, ExerciseType::European, 100);
+        let amer =
+            lattice_two_asset::beg_two_asset(&two_asset_input, payoff, ExerciseType::American, 100);
+        let analytic = lattice_two_asset::stulz_two_asset(&two_asset_input, payoff);
+        println!(
+            "(two asset lattice {:?})european: {}, american: {}, analytic european: {}",
+            payoff, euro, amer, analytic
+        );
+    }
 }
@@ -0,0 +1,210 @@
+use super::lattice_crr::{intrinsic, ExerciseType, OptionType};
+use super::node::Node;
+use crate::bs::black_scholes::{self, black_scholes};
+
+/* Derman, Kani and Chriss(1996) Implied Trinomial Trees of the Volatility Smile
+状態空間(各ノードの原資産価格)は一定ボラティリティの三項格子で固定し、
+遷移確率を各満期・各ノードの原資産価格をStrikeとするスマイル上のオプション価格に一致させる。 */
+
+#[derive(Clone, Debug)]
+pub struct ImpliedTree {
+    pub zero_rate: f64,       // ゼロレート
+    pub time_vec: Vec<f64>,   // 時間方向のグリッドのベクトル
+    pub tree: Vec<Vec<Node>>, // Treeの本体（各時刻のNodeのインデックス0が最も低い原資産価格）
+}
+
+impl ImpliedTree {
+    /// スマイルにキャリブレーションしたImplied Trinomial Treeを構築します。
+    /// * `underlying` - 原資産価格
+    /// * `zero_rate` - ゼロレート
+    /// * `term_annu` - Treeの満期
+    /// * `grid_num` - 時間方向のグリッド数
+    /// * `smile` - (strike, 満期)に対するインプライドボラティリティ
+    pub fn new(
+        underlying: f64,
+        zero_rate: f64,
+        term_annu: f64,
+        grid_num: usize,
+        smile: &dyn Fn(f64, f64) -> f64,
+    ) -> Self {
+        let delta_t = term_annu / grid_num as f64;
+        let time_vec: Vec<f64> = (0..grid_num + 1).map(|i| i as f64 * delta_t).collect();
+        let df = (-zero_rate * delta_t).exp();
+
+        // 状態空間はATMのボラティリティで u = exp(σ√(2Δt)) の三項格子とする。
+        let atm_vol = smile(underlying, term_annu);
+        let val_up = (atm_vol * (2.0 * delta_t).sqrt()).exp();
+        let layer = |step: usize| -> Vec<f64> {
+            (0..2 * step + 1)
+                .map(|idx| underlying * val_up.powi(idx as i32 - step as i32))
+                .collect()
+        };
+
+        let mut tree: Vec<Vec<Node>> = Vec::with_capacity(grid_num + 1);
+        tree.push(vec![Node::new(underlying, 1.0, 0.0, 0.0, 0.0)]);
+        for step in 0..grid_num {
+            let next_layer = layer(step + 1);
+            let next_time = time_vec[step + 1];
+            let option_price = |strike: f64, option_type: black_scholes::OptionType| -> f64 {
+                let input = black_scholes::CalcInput {
+                    zero_rate,
+                    vol: smile(strike, next_time),
+                    term_annu: next_time,
+                    strike,
+                    underlying,
+                };
+                black_scholes(&input, option_type)
+            };
+
+            let nodes = &mut tree[step];
+            let fwds: Vec<f64> = nodes.iter().map(|node| node.underlying / df).collect();
+            let mut probs = vec![(0.0, 0.0); nodes.len()]; // (up, down)
+
+            // 中心より上のノードはCallの価格から上昇確率を決める。
+            for idx in (step..2 * step + 1).rev() {
+                let strike = next_layer[idx + 1];
+                let call = option_price(strike, black_scholes::OptionType::Call);
+                let upper_sum: f64 = (idx + 1..2 * step + 1)
+                    .map(|k| nodes[k].arrow_debreu * (fwds[k] - strike))
+                    .sum();
+                let prob_up = (call / df - upper_sum)
+                    / (nodes[idx].arrow_debreu * (next_layer[idx + 2] - next_layer[idx + 1]));
+                let prob_down = (fwds[idx]
+                    - prob_up * (next_layer[idx + 2] - next_layer[idx + 1])
+                    - next_layer[idx + 1])
+                    / (next_layer[idx] - next_layer[idx + 1]);
+                probs[idx] = (prob_up, prob_down);
+            }
+            // 中心より下のノードはPutの価格から下落確率を決める。
+            for idx in 0..step {
+                let strike = next_layer[idx + 1];
+                let put = option_price(strike, black_scholes::OptionType::Put);
+                let lower_sum: f64 = (0..idx)
+                    .map(|k| nodes[k].arrow_debreu * (strike - fwds[k]))
+                    .sum();
+                let prob_down = (put / df - lower_sum)
+                    / (nodes[idx].arrow_debreu * (next_layer[idx + 1] - next_layer[idx]));
+                let prob_up = (fwds[idx] + prob_down * (next_layer[idx + 1] - next_layer[idx])
+                    - next_layer[idx + 1])
+                    / (next_layer[idx + 2] - next_layer[idx + 1]);
+                probs[idx] = (prob_up, prob_down);
+            }
+
+            let mut next_nodes: Vec<Node> = next_layer
+                .iter()
+                .map(|und| Node::new(*und, 0.0, 0.0, 0.0, 0.0))
+                .collect();
+            for (idx, node) in nodes.iter_mut().enumerate() {
+                let (mut prob_up, mut prob_down) = probs[idx];
+                if !Self::is_valid_prob(prob_up, prob_down) {
+                    (prob_up, prob_down) =
+                        Self::override_prob(fwds[idx], &next_layer[idx..idx + 3]);
+                }
+                node.prob_up = prob_up;
+                node.prob_down = prob_down;
+                node.prob_mid = 1.0 - prob_up - prob_down;
+
+                next_nodes[idx].arrow_debreu += df * node.arrow_debreu * node.prob_down;
+                next_nodes[idx + 1].arrow_debreu += df * node.arrow_debreu * node.prob_mid;
+                next_nodes[idx + 2].arrow_debreu += df * node.arrow_debreu * node.prob_up;
+            }
+            tree.push(next_nodes);
+        }
+
+        Self {
+            zero_rate,
+            time_vec,
+            tree,
+        }
+    }
+
+    /// 遷移確率が0～1に収まっているかを返します。
+    fn is_valid_prob(prob_up: f64, prob_down: f64) -> bool {
+        let prob_mid = 1.0 - prob_up - prob_down;
+        [prob_up, prob_mid, prob_down]
+            .iter()
+            .all(|prob| prob.is_finite() && (0.0..=1.0).contains(prob))
+    }
+
+    /// スマイルから求めた遷移確率が不正な場合に、フォワードのみを一致させる遷移確率を返します。(DKC 式(4),(5))<br>
+    /// 戻り値：(prob_up, prob_down)
+    /// * `fwd` - 当ノードのフォワード
+    /// * `next` - 遷移先の原資産価格(down, mid, up)
+    fn override_prob(fwd: f64, next: &[f64]) -> (f64, f64) {
+        let (down, mid, up) = (next[0], next[1], next[2]);
+        if fwd > mid {
+            let prob_up = 0.5 * ((fwd - mid) / (up - mid) + (fwd - down) / (up - down));
+            let prob_down = 0.5 * (up - fwd) / (up - down);
+            (prob_up, prob_down)
+        } else {
+            let prob_up = 0.5 * (fwd - down) / (up - down);
+            let prob_down = 0.5 * ((mid - fwd) / (mid - down) + (up - fwd) / (up - down));
+            (prob_up, prob_down)
+        }
+    }
+
+    /// Implied Treeでオプション価格を返します。
+    /// * `strike` - 権利行使価格
+    /// * `option_type` - Call/Put
+    /// * `exercise_type` - European/American
+    pub fn price(&self, strike: f64, option_type: OptionType, exercise_type: ExerciseType) -> f64 {
+        let grid_num = self.tree.len() - 1;
+        let mut vals: Vec<f64> = self.tree[grid_num]
+            .iter()
+            .map(|node| intrinsic(node.underlying, strike, option_type))
+            .collect();
+        for step in (0..grid_num).rev() {
+            let df = (-self.zero_rate * (self.time_vec[step + 1] - self.time_vec[step])).exp();
+            vals = self.tree[step]
+                .iter()
+                .enumerate()
+                .map(|(idx, node)| {
+                    let continuation = df * node.expected_next(&vals, idx);
+                    match exercise_type {
+                        ExerciseType::European => continuation,
+                        ExerciseType::American => {
+                            continuation.max(intrinsic(node.underlying, strike, option_type))
+                        }
+                    }
+                })
+                .collect();
+        }
+        vals[0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_implied_tree_reprices_smile() {
+        let underlying = 100.0;
+        let zero_rate = 0.03;
+        let term_annu = 1.0;
+        let smile = |strike: f64, _term: f64| 0.2 - 0.1 * (strike / underlying).ln();
+        let tree = ImpliedTree::new(underlying, zero_rate, term_annu, 50, &smile);
+
+        // 各時刻でArrow Debreu priceの合計はディスカウントファクターに一致する。
+        for (step, nodes) in tree.tree.iter().enumerate() {
+            let ad_sum: f64 = nodes.iter().map(|node| node.arrow_debreu).sum();
+            assert!((ad_sum - (-zero_rate * tree.time_vec[step]).exp()).abs() < 1e-10);
+        }
+
+        // 満期のノードの原資産価格をStrikeとするEuropean Optionはスマイルの価格を再現する。
+        let last = &tree.tree[tree.tree.len() - 1];
+        for idx in [48, 50, 52] {
+            let strike = last[idx + 1].underlying;
+            let input = black_scholes::CalcInput {
+                zero_rate,
+                vol: smile(strike, term_annu),
+                term_annu,
+                strike,
+                underlying,
+            };
+            let expected = black_scholes(&input, black_scholes::OptionType::Call);
+            let actual = tree.price(strike, OptionType::Call, ExerciseType::European);
+            assert!((actual - expected).abs() < 1e-8);
+        }
+    }
+}
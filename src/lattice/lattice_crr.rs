@@ -1,3 +1,4 @@
+#[derive(Debug, Copy, Clone)]
 pub struct CalcInput {
     pub underlying: f64,
     pub strike: f64,
@@ -6,6 +7,26 @@ pub struct CalcInput {
     pub term_annu: f64,
 }
 
+#[derive(Debug, Copy, Clone)]
+pub enum OptionType {
+    Call,
+    Put,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum ExerciseType {
+    European,
+    American,
+}
+
+// 原資産価格に対する行使価値を返す。
+pub fn intrinsic(underlying: f64, strike: f64, option_type: OptionType) -> f64 {
+    match option_type {
+        OptionType::Call => (underlying - strike).max(0.0),
+        OptionType::Put => (strike - underlying).max(0.0),
+    }
+}
+
 pub fn crr_euro_call() {
     // set parameter
     let underlying = 100.0;
@@ -102,3 +123,637 @@ pub fn crr_euro_call_layer(input: &CalcInput) {
         vals[0]
     }
 }
+
+#[derive(Debug, Copy, Clone)]
+pub struct Greeks {
+    pub price: f64,
+    pub delta: f64,
+    pub gamma: f64,
+    pub theta: f64,
+    pub vega: f64,
+    pub rho: f64,
+}
+
+// return (val_up, val_down, rnp)
+fn crr_params(vol: f64, zero_rate: f64, delta_t: f64) -> (f64, f64, f64) {
+    let delta_t_sqrt = delta_t.sqrt();
+    let val_up = (vol * delta_t_sqrt).exp();
+    let val_down = (-vol * delta_t_sqrt).exp();
+    let rnp = ((zero_rate * delta_t).exp() - val_down) / (val_up - val_down);
+    (val_up, val_down, rnp)
+}
+
+/// CRRの二項格子でbackward inductionを行い、先頭の3時刻分の各ノードのオプション価値を返します。<br>
+/// 戻り値のlayers[i][j]はi番目の時刻の下からj番目のノード(上昇j回)の値です。
+/// * `input` - 計算のインプット
+/// * `option_type` - Call/Put
+/// * `exercise_type` - European/American
+/// * `grid_num` - t=0から満期までの時間方向のグリッド数
+/// * `ext_steps` - t=0より前に延長するステップ数(extended tree)。0なら通常のTree。
+pub fn crr_layers(
+    input: &CalcInput,
+    option_type: OptionType,
+    exercise_type: ExerciseType,
+    grid_num: usize,
+    ext_steps: usize,
+) -> Vec<Vec<f64>> {
+    let CalcInput {
+        underlying,
+        strike,
+        vol,
+        zero_rate,
+        term_annu,
+    } = *input;
+
+    let delta_t = term_annu / grid_num as f64;
+    let df = (-zero_rate * delta_t).exp();
+    let (val_up, val_down, rnp) = crr_params(vol, zero_rate, delta_t);
+    // Treeの根は延長したステップ分だけt=0より前の時刻で、原資産価格はunderlyingとする。
+    let total_steps = grid_num + ext_steps;
+    let und_val = |step: usize, idx: usize| {
+        underlying * val_up.powi(idx as i32) * val_down.powi((step - idx) as i32)
+    };
+
+    let mut vals: Vec<f64> = (0..total_steps + 1)
+        .map(|idx| intrinsic(und_val(total_steps, idx), strike, option_type))
+        .collect();
+    let mut layers: Vec<Vec<f64>> = vec![vec![]; 3];
+    for step in (0..total_steps).rev() {
+        for idx in 0..step + 1 {
+            let continuation = df * (rnp * vals[idx + 1] + (1.0 - rnp) * vals[idx]);
+            vals[idx] = match exercise_type {
+                ExerciseType::European => continuation,
+                ExerciseType::American => {
+                    continuation.max(intrinsic(und_val(step, idx), strike, option_type))
+                }
+            };
+        }
+        if step < 3 {
+            layers[step] = vals[..step + 1].to_vec();
+        }
+    }
+    layers
+}
+
+/// CRRの二項格子でオプション価格を返します。
+pub fn crr_price(
+    input: &CalcInput,
+    option_type: OptionType,
+    exercise_type: ExerciseType,
+    grid_num: usize,
+) -> f64 {
+    crr_layers(input, option_type, exercise_type, grid_num, 0)[0][0]
+}
+
+/// CRRの二項格子からオプション価格とGreeksを返します。<br>
+/// delta、gamma、thetaはt=0より2ステップ前から開始するTree(extended tree)のノードから計算し、
+/// vega、rhoはパラメータをバンプした再計算による中心差分で計算します。
+/// * `input` - 計算のインプット
+/// * `option_type` - Call/Put
+/// * `exercise_type` - European/American
+/// * `grid_num` - t=0から満期までの時間方向のグリッド数
+pub fn crr_greeks(
+    input: &CalcInput,
+    option_type: OptionType,
+    exercise_type: ExerciseType,
+    grid_num: usize,
+) -> Greeks {
+    let delta_t = input.term_annu / grid_num as f64;
+    let (val_up, val_down, _) = crr_params(input.vol, input.zero_rate, delta_t);
+    let layers = crr_layers(input, option_type, exercise_type, grid_num, 2);
+
+    // t=0の3つのノード(下落2回、上昇と下落1回ずつ、上昇2回)
+    let und_low = input.underlying * val_down.powi(2);
+    let und_mid = input.underlying;
+    let und_high = input.underlying * val_up.powi(2);
+    let (val_low, val_mid, val_high) = (layers[2][0], layers[2][1], layers[2][2]);
+
+    let delta = (val_high - val_low) / (und_high - und_low);
+    let gamma = ((val_high - val_mid) / (und_high - und_mid)
+        - (val_mid - val_low) / (und_mid - und_low))
+        / (0.5 * (und_high - und_low));
+    // 根とt=0の中央のノードは原資産価格が同じで、時刻が2ステップ異なる。
+    let theta = (val_mid - layers[0][0]) / (2.0 * delta_t);
+
+    let price_ext = |input: &CalcInput| -> f64 {
+        crr_layers(input, option_type, exercise_type, grid_num, 2)[2][1]
+    };
+    let vol_bump = 0.01;
+    let vega = (price_ext(&CalcInput {
+        vol: input.vol + vol_bump,
+        ..*input
+    }) - price_ext(&CalcInput {
+        vol: input.vol - vol_bump,
+        ..*input
+    })) / (2.0 * vol_bump);
+    let rate_bump = 0.0001;
+    let rho = (price_ext(&CalcInput {
+        zero_rate: input.zero_rate + rate_bump,
+        ..*input
+    }) - price_ext(&CalcInput {
+        zero_rate: input.zero_rate - rate_bump,
+        ..*input
+    })) / (2.0 * rate_bump);
+
+    Greeks {
+        price: val_mid,
+        delta,
+        gamma,
+        theta,
+        vega,
+        rho,
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct CashDividend {
+    pub time: f64,   // 配当落ちの時刻
+    pub amount: f64, // 配当額
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum DividendMethod {
+    Escrowed,       // 原資産価格から配当の現在価値を差し引いた部分を二項格子で表す
+    NonRecombining, // 配当落ちのノードごとに配当額を差し引いた価格から新たなTreeを構築する
+}
+
+/// 離散配当のある原資産に対するオプション価格をCRRの二項格子で返します。
+/// * `input` - 計算のインプット
+/// * `option_type` - Call/Put
+/// * `exercise_type` - European/American
+/// * `dividends` - 配当のベクタ(満期より後の配当は無視する)
+/// * `method` - 配当の扱い方
+/// * `grid_num` - 時間方向のグリッド数
+pub fn crr_dividend(
+    input: &CalcInput,
+    option_type: OptionType,
+    exercise_type: ExerciseType,
+    dividends: &[CashDividend],
+    method: DividendMethod,
+    grid_num: usize,
+) -> f64 {
+    let dividends: Vec<CashDividend> = dividends
+        .iter()
+        .filter(|div| 0.0 < div.time && div.time < input.term_annu)
+        .copied()
+        .collect();
+    match method {
+        // 1ステップのTreeには配当落ちのノードを置けないため、escrowed dividend modelで計算する。
+        DividendMethod::NonRecombining if grid_num < 2 && !dividends.is_empty() => {
+            crr_escrowed(input, option_type, exercise_type, &dividends, grid_num)
+        }
+        DividendMethod::Escrowed => {
+            crr_escrowed(input, option_type, exercise_type, &dividends, grid_num)
+        }
+        DividendMethod::NonRecombining => {
+            let delta_t = input.term_annu / grid_num as f64;
+            // 配当落ちの時刻は最も近いグリッドに丸める。
+            let mut div_steps: Vec<(usize, f64)> = dividends
+                .iter()
+                .map(|div| {
+                    let step = ((div.time / delta_t).round() as usize).clamp(1, grid_num - 1);
+                    (step, div.amount)
+                })
+                .collect();
+            div_steps.sort_by_key(|(step, _)| *step);
+            crr_non_recombining(
+                input,
+                option_type,
+                exercise_type,
+                &div_steps,
+                grid_num,
+                input.underlying,
+                0,
+            )
+        }
+    }
+}
+
+// escrowed dividend model
+// 各ノードの原資産価格 = 二項格子の価格 + 残りの配当の現在価値
+fn crr_escrowed(
+    input: &CalcInput,
+    option_type: OptionType,
+    exercise_type: ExerciseType,
+    dividends: &[CashDividend],
+    grid_num: usize,
+) -> f64 {
+    let CalcInput {
+        underlying,
+        strike,
+        vol,
+        zero_rate,
+        term_annu,
+    } = *input;
+
+    let delta_t = term_annu / grid_num as f64;
+    let df = (-zero_rate * delta_t).exp();
+    let (val_up, val_down, rnp) = crr_params(vol, zero_rate, delta_t);
+
+    // 時刻t以降に支払われる配当のtにおける現在価値(配当落ちの時刻のノードは配当込みとする)
+    let pv_divs = |t: f64| -> f64 {
+        dividends
+            .iter()
+            .filter(|div| div.time >= t)
+            .map(|div| div.amount * (-zero_rate * (div.time - t)).exp())
+            .sum()
+    };
+    let risky_part = underlying - pv_divs(0.0);
+    let und_val = |step: usize, idx: usize| {
+        risky_part * val_up.powi(idx as i32) * val_down.powi((step - idx) as i32)
+            + pv_divs(step as f64 * delta_t)
+    };
+
+    let mut vals: Vec<f64> = (0..grid_num + 1)
+        .map(|idx| intrinsic(und_val(grid_num, idx), strike, option_type))
+        .collect();
+    for step in (0..grid_num).rev() {
+        for idx in 0..step + 1 {
+            let continuation = df * (rnp * vals[idx + 1] + (1.0 - rnp) * vals[idx]);
+            vals[idx] = match exercise_type {
+                ExerciseType::European => continuation,
+                ExerciseType::American => {
+                    continuation.max(intrinsic(und_val(step, idx), strike, option_type))
+                }
+            };
+        }
+    }
+    vals[0]
+}
+
+// non-recombining tree
+// start_stepの原資産価格start_undから次の配当落ち(または満期)までは再結合するTreeで計算し、
+// 配当落ちの各ノードでは配当を差し引いた価格から再帰的に新たなTreeを構築する。
+fn crr_non_recombining(
+    input: &CalcInput,
+    option_type: OptionType,
+    exercise_type: ExerciseType,
+    div_steps: &[(usize, f64)],
+    grid_num: usize,
+    start_und: f64,
+    start_step: usize,
+) -> f64 {
+    let delta_t = input.term_annu / grid_num as f64;
+    let df = (-input.zero_rate * delta_t).exp();
+    let (val_up, val_down, rnp) = crr_params(input.vol, input.zero_rate, delta_t);
+    let und_val = |step: usize, idx: usize| {
+        start_und * val_up.powi(idx as i32) * val_down.powi((step - idx) as i32)
+    };
+    let exercise = |continuation: f64, und: f64| match exercise_type {
+        ExerciseType::European => continuation,
+        ExerciseType::American => continuation.max(intrinsic(und, input.strike, option_type)),
+    };
+
+    let num_steps = match div_steps.first() {
+        Some((div_step, _)) => div_step - start_step,
+        None => grid_num - start_step,
+    };
+    let mut vals: Vec<f64> = match div_steps.first() {
+        Some((div_step, amount)) => (0..num_steps + 1)
+            .map(|idx| {
+                let und = und_val(num_steps, idx);
+                let ex_div = crr_non_recombining(
+                    input,
+                    option_type,
+                    exercise_type,
+                    &div_steps[1..],
+                    grid_num,
+                    (und - amount).max(0.0),
+                    *div_step,
+                );
+                // 配当落ち直前(配当込みの価格)での行使を考慮する。
+                exercise(ex_div, und)
+            })
+            .collect(),
+        None => (0..num_steps + 1)
+            .map(|idx| intrinsic(und_val(num_steps, idx), input.strike, option_type))
+            .collect(),
+    };
+    for step in (0..num_steps).rev() {
+        for idx in 0..step + 1 {
+            let continuation = df * (rnp * vals[idx + 1] + (1.0 - rnp) * vals[idx]);
+            vals[idx] = exercise(continuation, und_val(step, idx));
+        }
+    }
+    vals[0]
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum BarrierType {
+    UpAndOut,
+    DownAndOut,
+}
+
+/// Boyle and Lau(1994) バリアがノードの位置と(ほぼ)一致するステップ数のうち、min_steps以上で最小のものを返します。
+/// * `input` - 計算のインプット
+/// * `barrier` - バリアの水準
+/// * `min_steps` - ステップ数の下限
+pub fn boyle_lau_steps(input: &CalcInput, barrier: f64, min_steps: usize) -> usize {
+    let log_dist = (input.underlying / barrier).ln().powi(2);
+    let mut m = 1;
+    loop {
+        let steps = ((m as f64).powi(2) * input.vol.powi(2) * input.term_annu / log_dist).floor();
+        if steps >= min_steps as f64 {
+            return steps as usize;
+        }
+        m += 1;
+    }
+}
+
+/// ノックアウト型のバリアオプション価格をCRRの二項格子で返します。<br>
+/// バリアは各ノードの時刻で観測し、ノックアウト時のリベートは0とします。
+/// * `input` - 計算のインプット
+/// * `option_type` - Call/Put
+/// * `exercise_type` - European/American
+/// * `barrier_type` - Up and Out/Down and Out
+/// * `barrier` - バリアの水準
+/// * `grid_num` - 時間方向のグリッド数
+/// * `derman_adjust` - trueの場合、Derman et al.(1995)によりバリアの内側で最も近いノードの値を
+///   内側・外側の価格の水準の間のバリアの位置で補間する。
+pub fn crr_barrier(
+    input: &CalcInput,
+    option_type: OptionType,
+    exercise_type: ExerciseType,
+    barrier_type: BarrierType,
+    barrier: f64,
+    grid_num: usize,
+    derman_adjust: bool,
+) -> f64 {
+    let CalcInput {
+        underlying,
+        strike,
+        vol,
+        zero_rate,
+        term_annu,
+    } = *input;
+
+    let delta_t = term_annu / grid_num as f64;
+    let df = (-zero_rate * delta_t).exp();
+    let (val_up, val_down, rnp) = crr_params(vol, zero_rate, delta_t);
+    let und_val = |step: usize, idx: usize| {
+        underlying * val_up.powi(idx as i32) * val_down.powi((step - idx) as i32)
+    };
+    let is_knocked_out = |und: f64| match barrier_type {
+        BarrierType::UpAndOut => und >= barrier,
+        BarrierType::DownAndOut => und <= barrier,
+    };
+    // バリアの内側で最も近い価格の水準のノードの値を、その一つ外側の価格の水準
+    // (CRRでは同一時刻の隣のノードではなく、隣の時刻のノードの水準)との間で補間する。
+    let adjust = |vals: &mut [f64], step: usize| {
+        let (inner, val_outer) = match barrier_type {
+            BarrierType::UpAndOut => (
+                (0..step + 1)
+                    .rev()
+                    .find(|idx| !is_knocked_out(und_val(step, *idx))),
+                val_up,
+            ),
+            BarrierType::DownAndOut => (
+                (0..step + 1).find(|idx| !is_knocked_out(und_val(step, *idx))),
+                val_down,
+            ),
+        };
+        if let Some(idx) = inner {
+            let und_inner = und_val(step, idx);
+            let und_outer = und_inner * val_outer;
+            if is_knocked_out(und_outer) {
+                vals[idx] *= (und_inner - barrier) / (und_inner - und_outer);
+            }
+        }
+    };
+
+    let mut vals: Vec<f64> = (0..grid_num + 1)
+        .map(|idx| {
+            let und = und_val(grid_num, idx);
+            if is_knocked_out(und) {
+                0.0
+            } else {
+                intrinsic(und, strike, option_type)
+            }
+        })
+        .collect();
+    if derman_adjust {
+        adjust(&mut vals, grid_num);
+    }
+    for step in (0..grid_num).rev() {
+        for idx in 0..step + 1 {
+            let und = und_val(step, idx);
+            vals[idx] = if is_knocked_out(und) {
+                0.0
+            } else {
+                let continuation = df * (rnp * vals[idx + 1] + (1.0 - rnp) * vals[idx]);
+                match exercise_type {
+                    ExerciseType::European => continuation,
+                    ExerciseType::American => continuation.max(intrinsic(und, strike, option_type)),
+                }
+            };
+        }
+        if derman_adjust {
+            adjust(&mut vals, step);
+        }
+    }
+    vals[0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bs::black_scholes;
+
+    fn bs_price(underlying: f64, vol: f64, zero_rate: f64, term_annu: f64) -> f64 {
+        let input = black_scholes::CalcInput {
+            zero_rate,
+            vol,
+            term_annu,
+            strike: 100.0,
+            underlying,
+        };
+        black_scholes::black_scholes(&input, black_scholes::OptionType::Call)
+    }
+
+    #[test]
+    fn test_crr_greeks_euro_call() {
+        let input = CalcInput {
+            underlying: 100.0,
+            strike: 100.0,
+            vol: 0.2,
+            zero_rate: 0.05,
+            term_annu: 1.0,
+        };
+        let greeks = crr_greeks(&input, OptionType::Call, ExerciseType::European, 1000);
+
+        // Black-Scholesの価格の差分を参照値とする。
+        let h = 0.01;
+        let price = bs_price(100.0, 0.2, 0.05, 1.0);
+        let delta =
+            (bs_price(100.0 + h, 0.2, 0.05, 1.0) - bs_price(100.0 - h, 0.2, 0.05, 1.0)) / (2.0 * h);
+        let gamma = (bs_price(100.0 + 1.0, 0.2, 0.05, 1.0) - 2.0 * price
+            + bs_price(100.0 - 1.0, 0.2, 0.05, 1.0))
+            / 1.0_f64.powi(2);
+        let theta = -(bs_price(100.0, 0.2, 0.05, 1.0 + h) - bs_price(100.0, 0.2, 0.05, 1.0 - h))
+            / (2.0 * h);
+        let vega =
+            (bs_price(100.0, 0.2 + h, 0.05, 1.0) - bs_price(100.0, 0.2 - h, 0.05, 1.0)) / (2.0 * h);
+        let rho = (bs_price(100.0, 0.2, 0.05 + 1e-4, 1.0) - bs_price(100.0, 0.2, 0.05 - 1e-4, 1.0))
+            / 2e-4;
+
+        assert!((greeks.price - price).abs() < 1e-2);
+        assert!((greeks.delta - delta).abs() < 1e-3);
+        assert!((greeks.gamma - gamma).abs() < 1e-3);
+        assert!((greeks.theta - theta).abs() < 1e-2);
+        assert!((greeks.vega - vega).abs() < 1e-1);
+        assert!((greeks.rho - rho).abs() < 1e-1);
+    }
+
+    #[test]
+    fn test_crr_greeks_american_put() {
+        let input = CalcInput {
+            underlying: 100.0,
+            strike: 100.0,
+            vol: 0.2,
+            zero_rate: 0.05,
+            term_annu: 1.0,
+        };
+        let euro = crr_greeks(&input, OptionType::Put, ExerciseType::European, 1000);
+        let amer = crr_greeks(&input, OptionType::Put, ExerciseType::American, 1000);
+        assert!((amer.price - 6.090).abs() < 1e-2);
+        assert!(
+            (amer.price - crr_price(&input, OptionType::Put, ExerciseType::American, 1000)).abs()
+                < 1e-2
+        );
+        assert!(amer.price > euro.price);
+        assert!(-1.0 < amer.delta && amer.delta < euro.delta);
+        assert!(amer.gamma > 0.0);
+    }
+
+    #[test]
+    fn test_crr_dividend() {
+        let input = CalcInput {
+            underlying: 100.0,
+            strike: 100.0,
+            vol: 0.2,
+            zero_rate: 0.05,
+            term_annu: 1.0,
+        };
+        let dividends = [CashDividend {
+            time: 0.5,
+            amount: 3.0,
+        }];
+
+        // escrowed modelのEuropeanは配当の現在価値を差し引いた原資産のBlack-Scholesに一致する。
+        let pv_div = 3.0 * (-0.05 * 0.5_f64).exp();
+        let expected = bs_price(100.0 - pv_div, 0.2, 0.05, 1.0);
+        let escrowed = crr_dividend(
+            &input,
+            OptionType::Call,
+            ExerciseType::European,
+            &dividends,
+            DividendMethod::Escrowed,
+            1000,
+        );
+        assert!((escrowed - expected).abs() < 1e-2);
+
+        // 配当がなければ通常のTreeに一致する。
+        let no_div = crr_dividend(
+            &input,
+            OptionType::Put,
+            ExerciseType::American,
+            &[],
+            DividendMethod::NonRecombining,
+            200,
+        );
+        let plain = crr_price(&input, OptionType::Put, ExerciseType::American, 200);
+        assert!((no_div - plain).abs() < 1e-12);
+
+        // 配当落ち直前の早期行使があるためAmerican CallはEuropeanより高い。
+        for method in [DividendMethod::Escrowed, DividendMethod::NonRecombining] {
+            let euro = crr_dividend(
+                &input,
+                OptionType::Call,
+                ExerciseType::European,
+                &dividends,
+                method,
+                200,
+            );
+            let amer = crr_dividend(
+                &input,
+                OptionType::Call,
+                ExerciseType::American,
+                &dividends,
+                method,
+                200,
+            );
+            assert!(amer > euro);
+        }
+
+        // 1ステップのTreeでは配当落ちのノードがないのでescrowed modelと同じ
+        let price = |method| {
+            crr_dividend(
+                &input,
+                OptionType::Put,
+                ExerciseType::American,
+                &dividends,
+                method,
+                1,
+            )
+        };
+        assert_eq!(
+            price(DividendMethod::NonRecombining),
+            price(DividendMethod::Escrowed)
+        );
+    }
+
+    // Reiner and Rubinstein(1991) Down and Out Call (barrier <= strike)
+    fn down_and_out_call(input: &CalcInput, barrier: f64) -> f64 {
+        let CalcInput {
+            underlying,
+            strike,
+            vol,
+            zero_rate,
+            term_annu,
+        } = *input;
+        let lambda = (zero_rate + 0.5 * vol.powi(2)) / vol.powi(2);
+        let vol_sqrt_t = vol * term_annu.sqrt();
+        let y = (barrier.powi(2) / (underlying * strike)).ln() / vol_sqrt_t + lambda * vol_sqrt_t;
+        let down_and_in = underlying
+            * (barrier / underlying).powf(2.0 * lambda)
+            * black_scholes::norm_cdf_matic2016(y)
+            - strike
+                * (-zero_rate * term_annu).exp()
+                * (barrier / underlying).powf(2.0 * lambda - 2.0)
+                * black_scholes::norm_cdf_matic2016(y - vol_sqrt_t);
+        bs_price(underlying, vol, zero_rate, term_annu) - down_and_in
+    }
+
+    #[test]
+    fn test_crr_barrier() {
+        let input = CalcInput {
+            underlying: 100.0,
+            strike: 100.0,
+            vol: 0.2,
+            zero_rate: 0.05,
+            term_annu: 1.0,
+        };
+        let barrier = 95.0;
+        let expected = down_and_out_call(&input, barrier);
+        let price = |grid_num: usize, derman_adjust: bool| {
+            crr_barrier(
+                &input,
+                OptionType::Call,
+                ExerciseType::European,
+                BarrierType::DownAndOut,
+                barrier,
+                grid_num,
+                derman_adjust,
+            )
+        };
+
+        let steps = boyle_lau_steps(&input, barrier, 300);
+        assert!(steps >= 300);
+        let boyle_lau = price(steps, false);
+        let unadjusted = price(1000, false);
+        let derman = price(1000, true);
+        assert!((boyle_lau - expected).abs() < 1e-2);
+        assert!((derman - expected).abs() < 5e-2);
+        assert!((derman - expected).abs() < (unadjusted - expected).abs());
+    }
+}
@@ -0,0 +1,168 @@
+use super::lattice_crr::{intrinsic, CalcInput, ExerciseType, OptionType};
+
+// 株式の三項格子
+// λ(stretch parameter)で価格方向のグリッド幅 u = exp(λσ√Δt) を調整する。
+#[derive(Debug, Copy, Clone)]
+pub enum TrinomialType {
+    Boyle(f64),          // Boyle(1988) 平均と分散を厳密に一致させる遷移確率
+    KamradRitchken(f64), // Kamrad and Ritchken(1991) 対数価格の平均と分散を一次近似で一致させる遷移確率
+}
+
+/// 三項格子の上昇幅と遷移確率をtupleで返します。<br>
+/// 戻り値：(val_up, prob_up, prob_mid, prob_down)
+/// * `tri_type` - 三項格子の種類
+/// * `vol` - ボラティリティ
+/// * `zero_rate` - ゼロレート
+/// * `delta_t` - 時間方向のグリッドの間隔
+pub fn trinomial_params(
+    tri_type: TrinomialType,
+    vol: f64,
+    zero_rate: f64,
+    delta_t: f64,
+) -> (f64, f64, f64, f64) {
+    match tri_type {
+        TrinomialType::Boyle(lambda) => {
+            let val_up = (lambda * vol * delta_t.sqrt()).exp();
+            let mean = (zero_rate * delta_t).exp();
+            let var = mean.powi(2) * ((vol.powi(2) * delta_t).exp() - 1.0);
+            let denom = (val_up - 1.0) * (val_up.powi(2) - 1.0);
+            let prob_up = ((var + mean.powi(2) - mean) * val_up - (mean - 1.0)) / denom;
+            let prob_down = ((var + mean.powi(2) - mean) * val_up.powi(2)
+                - (mean - 1.0) * val_up.powi(3))
+                / denom;
+            (val_up, prob_up, 1.0 - prob_up - prob_down, prob_down)
+        }
+        TrinomialType::KamradRitchken(lambda) => {
+            let val_up = (lambda * vol * delta_t.sqrt()).exp();
+            let drift = (zero_rate - 0.5 * vol.powi(2)) * delta_t.sqrt() / (2.0 * lambda * vol);
+            let prob_up = 1.0 / (2.0 * lambda.powi(2)) + drift;
+            let prob_down = 1.0 / (2.0 * lambda.powi(2)) - drift;
+            (val_up, prob_up, 1.0 - 1.0 / lambda.powi(2), prob_down)
+        }
+    }
+}
+
+/// 三項格子でオプション価格を返します。
+/// * `input` - 計算のインプット
+/// * `option_type` - Call/Put
+/// * `exercise_type` - European/American
+/// * `tri_type` - 三項格子の種類
+/// * `grid_num` - 時間方向のグリッド数
+pub fn trinomial(
+    input: &CalcInput,
+    option_type: OptionType,
+    exercise_type: ExerciseType,
+    tri_type: TrinomialType,
+    grid_num: usize,
+) -> f64 {
+    let CalcInput {
+        underlying,
+        strike,
+        vol,
+        zero_rate,
+        term_annu,
+    } = *input;
+
+    let delta_t = term_annu / grid_num as f64;
+    let df = (-zero_rate * delta_t).exp();
+    let (val_up, prob_up, prob_mid, prob_down) =
+        trinomial_params(tri_type, vol, zero_rate, delta_t);
+
+    // 満期の各ノードの原資産価格(インデックス0が最も低い)
+    let und_val = |step: usize, idx: usize| underlying * val_up.powi(idx as i32 - step as i32);
+    let mut vals: Vec<f64> = (0..2 * grid_num + 1)
+        .map(|idx| intrinsic(und_val(grid_num, idx), strike, option_type))
+        .collect();
+
+    // backward induction
+    for step in (0..grid_num).rev() {
+        for idx in 0..2 * step + 1 {
+            let continuation =
+                df * (prob_up * vals[idx + 2] + prob_mid * vals[idx + 1] + prob_down * vals[idx]);
+            vals[idx] = match exercise_type {
+                ExerciseType::European => continuation,
+                ExerciseType::American => {
+                    continuation.max(intrinsic(und_val(step, idx), strike, option_type))
+                }
+            };
+        }
+    }
+    vals[0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bs::black_scholes;
+
+    #[test]
+    fn test_trinomial_euro() {
+        let input = CalcInput {
+            underlying: 100.0,
+            strike: 98.0,
+            vol: 0.2,
+            zero_rate: 0.02,
+            term_annu: 0.5,
+        };
+        let bs_input = black_scholes::CalcInput {
+            underlying: 100.0,
+            strike: 98.0,
+            vol: 0.2,
+            zero_rate: 0.02,
+            term_annu: 0.5,
+        };
+        let tolerance = 1e-2;
+        for tri_type in [
+            TrinomialType::Boyle(1.2),
+            TrinomialType::KamradRitchken(1.5_f64.sqrt()),
+        ] {
+            let call = trinomial(
+                &input,
+                OptionType::Call,
+                ExerciseType::European,
+                tri_type,
+                500,
+            );
+            let put = trinomial(
+                &input,
+                OptionType::Put,
+                ExerciseType::European,
+                tri_type,
+                500,
+            );
+            let bs_call = black_scholes::black_scholes(&bs_input, black_scholes::OptionType::Call);
+            let bs_put = black_scholes::black_scholes(&bs_input, black_scholes::OptionType::Put);
+            assert!((call - bs_call).abs() < tolerance);
+            assert!((put - bs_put).abs() < tolerance);
+        }
+    }
+
+    #[test]
+    fn test_trinomial_american_put() {
+        let input = CalcInput {
+            underlying: 100.0,
+            strike: 100.0,
+            vol: 0.2,
+            zero_rate: 0.05,
+            term_annu: 1.0,
+        };
+        let tri_type = TrinomialType::KamradRitchken(1.5_f64.sqrt());
+        let euro = trinomial(
+            &input,
+            OptionType::Put,
+            ExerciseType::European,
+            tri_type,
+            500,
+        );
+        let amer = trinomial(
+            &input,
+            OptionType::Put,
+            ExerciseType::American,
+            tri_type,
+            500,
+        );
+        // American put の参照値(二項格子 10000 step)は約 6.090
+        assert!(amer > euro);
+        assert!((amer - 6.090).abs() < 1e-2);
+    }
+}
@@ -0,0 +1,242 @@
+use super::lattice_crr::ExerciseType;
+use crate::hull_white::math::{bivariate_std_normal_cdf, std_normal_cdf};
+
+#[derive(Debug, Copy, Clone)]
+pub struct TwoAssetInput {
+    pub underlying1: f64,
+    pub underlying2: f64,
+    pub vol1: f64,
+    pub vol2: f64,
+    pub div_yield1: f64, // 連続配当利回り
+    pub div_yield2: f64, // 連続配当利回り
+    pub corr: f64,       // 2資産の相関
+    pub strike: f64,     // Exchangeでは使用しない
+    pub zero_rate: f64,
+    pub term_annu: f64,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum TwoAssetPayoff {
+    CallOnMax, // max(max(S1, S2) - K, 0)
+    PutOnMax,  // max(K - max(S1, S2), 0)
+    CallOnMin, // max(min(S1, S2) - K, 0)
+    PutOnMin,  // max(K - min(S1, S2), 0)
+    Exchange,  // max(S1 - S2, 0)
+}
+
+// 2資産の原資産価格に対する行使価値を返す。
+fn two_asset_intrinsic(und1: f64, und2: f64, strike: f64, payoff: TwoAssetPayoff) -> f64 {
+    match payoff {
+        TwoAssetPayoff::CallOnMax => (und1.max(und2) - strike).max(0.0),
+        TwoAssetPayoff::PutOnMax => (strike - und1.max(und2)).max(0.0),
+        TwoAssetPayoff::CallOnMin => (und1.min(und2) - strike).max(0.0),
+        TwoAssetPayoff::PutOnMin => (strike - und1.min(und2)).max(0.0),
+        TwoAssetPayoff::Exchange => (und1 - und2).max(0.0),
+    }
+}
+
+/// Boyle, Evnine and Gibbs(1989) の2資産の二項格子でオプション価格を返します。<br>
+/// 各ノードから(上昇, 上昇)、(上昇, 下落)、(下落, 上昇)、(下落, 下落)の4方向に遷移します。
+/// * `input` - 計算のインプット
+/// * `payoff` - ペイオフの種類
+/// * `exercise_type` - European/American
+/// * `grid_num` - 時間方向のグリッド数
+pub fn beg_two_asset(
+    input: &TwoAssetInput,
+    payoff: TwoAssetPayoff,
+    exercise_type: ExerciseType,
+    grid_num: usize,
+) -> f64 {
+    let TwoAssetInput {
+        underlying1,
+        underlying2,
+        vol1,
+        vol2,
+        div_yield1,
+        div_yield2,
+        corr,
+        strike,
+        zero_rate,
+        term_annu,
+    } = *input;
+
+    let delta_t = term_annu / grid_num as f64;
+    let delta_t_sqrt = delta_t.sqrt();
+    let df = (-zero_rate * delta_t).exp();
+    let (val_up1, val_up2) = ((vol1 * delta_t_sqrt).exp(), (vol2 * delta_t_sqrt).exp());
+    // 対数価格のドリフトをボラティリティで割ったもの
+    let drift1 = (zero_rate - div_yield1 - 0.5 * vol1.powi(2)) / vol1 * delta_t_sqrt;
+    let drift2 = (zero_rate - div_yield2 - 0.5 * vol2.powi(2)) / vol2 * delta_t_sqrt;
+    let prob_uu = 0.25 * (1.0 + corr + drift1 + drift2);
+    let prob_ud = 0.25 * (1.0 - corr + drift1 - drift2);
+    let prob_du = 0.25 * (1.0 - corr - drift1 + drift2);
+    let prob_dd = 0.25 * (1.0 + corr - drift1 - drift2);
+
+    // (step, 資産1の上昇回数, 資産2の上昇回数)のノードの原資産価格
+    let und_val = |step: usize, idx1: usize, idx2: usize| -> (f64, f64) {
+        (
+            underlying1 * val_up1.powi(2 * idx1 as i32 - step as i32),
+            underlying2 * val_up2.powi(2 * idx2 as i32 - step as i32),
+        )
+    };
+
+    let mut vals: Vec<Vec<f64>> = (0..grid_num + 1)
+        .map(|idx1| {
+            (0..grid_num + 1)
+                .map(|idx2| {
+                    let (und1, und2) = und_val(grid_num, idx1, idx2);
+                    two_asset_intrinsic(und1, und2, strike, payoff)
+                })
+                .collect()
+        })
+        .collect();
+
+    // backward induction
+    for step in (0..grid_num).rev() {
+        for idx1 in 0..step + 1 {
+            for idx2 in 0..step + 1 {
+                let continuation = df
+                    * (prob_uu * vals[idx1 + 1][idx2 + 1]
+                        + prob_ud * vals[idx1 + 1][idx2]
+                        + prob_du * vals[idx1][idx2 + 1]
+                        + prob_dd * vals[idx1][idx2]);
+                vals[idx1][idx2] = match exercise_type {
+                    ExerciseType::European => continuation,
+                    ExerciseType::American => {
+                        let (und1, und2) = und_val(step, idx1, idx2);
+                        continuation.max(two_asset_intrinsic(und1, und2, strike, payoff))
+                    }
+                };
+            }
+        }
+    }
+    vals[0][0]
+}
+
+/// Stulz(1982) 2資産の最大値・最小値に対するEuropean Optionの理論価格を返します。(Exchangeの場合はMargrabe(1978))
+/// * `input` - 計算のインプット
+/// * `payoff` - ペイオフの種類
+pub fn stulz_two_asset(input: &TwoAssetInput, payoff: TwoAssetPayoff) -> f64 {
+    let TwoAssetInput {
+        underlying1,
+        underlying2,
+        vol1,
+        vol2,
+        div_yield1,
+        div_yield2,
+        corr,
+        strike,
+        zero_rate,
+        term_annu,
+    } = *input;
+
+    let t_sqrt = term_annu.sqrt();
+    let vol = (vol1.powi(2) + vol2.powi(2) - 2.0 * corr * vol1 * vol2).sqrt();
+    let d = ((underlying1 / underlying2).ln()
+        + (div_yield2 - div_yield1 + 0.5 * vol.powi(2)) * term_annu)
+        / (vol * t_sqrt);
+    let fwd1 = underlying1 * (-div_yield1 * term_annu).exp();
+    let fwd2 = underlying2 * (-div_yield2 * term_annu).exp();
+    let df = (-zero_rate * term_annu).exp();
+
+    // Margrabe
+    let exchange = fwd1 * std_normal_cdf(d) - fwd2 * std_normal_cdf(d - vol * t_sqrt);
+    // strikeが0のときのcall on max/min (資産の最大値・最小値そのものの価値)
+    let max_asset = fwd1 * std_normal_cdf(d) + fwd2 * std_normal_cdf(-d + vol * t_sqrt);
+    let min_asset = fwd1 + fwd2 - max_asset;
+
+    let y1 = ((underlying1 / strike).ln()
+        + (zero_rate - div_yield1 + 0.5 * vol1.powi(2)) * term_annu)
+        / (vol1 * t_sqrt);
+    let y2 = ((underlying2 / strike).ln()
+        + (zero_rate - div_yield2 + 0.5 * vol2.powi(2)) * term_annu)
+        / (vol2 * t_sqrt);
+    let rho1 = (vol1 - corr * vol2) / vol;
+    let rho2 = (vol2 - corr * vol1) / vol;
+    let call_on_max = || -> f64 {
+        fwd1 * bivariate_std_normal_cdf(y1, d, rho1)
+            + fwd2 * bivariate_std_normal_cdf(y2, -d + vol * t_sqrt, rho2)
+            - strike
+                * df
+                * (1.0 - bivariate_std_normal_cdf(-y1 + vol1 * t_sqrt, -y2 + vol2 * t_sqrt, corr))
+    };
+    let call_on_min = || -> f64 {
+        fwd1 * bivariate_std_normal_cdf(y1, -d, -rho1)
+            + fwd2 * bivariate_std_normal_cdf(y2, d - vol * t_sqrt, -rho2)
+            - strike * df * bivariate_std_normal_cdf(y1 - vol1 * t_sqrt, y2 - vol2 * t_sqrt, corr)
+    };
+
+    match payoff {
+        TwoAssetPayoff::CallOnMax => call_on_max(),
+        TwoAssetPayoff::CallOnMin => call_on_min(),
+        // put-call parity
+        TwoAssetPayoff::PutOnMax => strike * df - max_asset + call_on_max(),
+        TwoAssetPayoff::PutOnMin => strike * df - min_asset + call_on_min(),
+        TwoAssetPayoff::Exchange => exchange,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_input() -> TwoAssetInput {
+        TwoAssetInput {
+            underlying1: 100.0,
+            underlying2: 105.0,
+            vol1: 0.2,
+            vol2: 0.3,
+            div_yield1: 0.02,
+            div_yield2: 0.01,
+            corr: 0.5,
+            strike: 100.0,
+            zero_rate: 0.05,
+            term_annu: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_beg_two_asset_euro() {
+        let input = test_input();
+        let tolerance = 5e-2;
+        for payoff in [
+            TwoAssetPayoff::CallOnMax,
+            TwoAssetPayoff::PutOnMax,
+            TwoAssetPayoff::CallOnMin,
+            TwoAssetPayoff::PutOnMin,
+            TwoAssetPayoff::Exchange,
+        ] {
+            let lattice = beg_two_asset(&input, payoff, ExerciseType::European, 300);
+            let analytic = stulz_two_asset(&input, payoff);
+            assert!((lattice - analytic).abs() < tolerance);
+        }
+    }
+
+    #[test]
+    fn test_beg_two_asset_american() {
+        let input = test_input();
+        for payoff in [TwoAssetPayoff::CallOnMax, TwoAssetPayoff::PutOnMin] {
+            let euro = beg_two_asset(&input, payoff, ExerciseType::European, 200);
+            let amer = beg_two_asset(&input, payoff, ExerciseType::American, 200);
+            assert!(amer > euro);
+        }
+        // 受け取る資産(資産1)に配当がなければ、Exchange Optionの早期行使は最適でない。
+        let input = TwoAssetInput {
+            div_yield1: 0.0,
+            ..test_input()
+        };
+        let euro = beg_two_asset(
+            &input,
+            TwoAssetPayoff::Exchange,
+            ExerciseType::European,
+            200,
+        );
+        let amer = beg_two_asset(
+            &input,
+            TwoAssetPayoff::Exchange,
+            ExerciseType::American,
+            200,
+        );
+        assert!((amer - euro).abs() < 1e-10);
+    }
+}
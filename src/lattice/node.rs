@@ -0,0 +1,35 @@
+#[derive(Clone, Copy, Debug)]
+pub struct Node {
+    pub underlying: f64,   // 当ノードの原資産価格
+    pub arrow_debreu: f64, // Arrow Debreu price
+    pub prob_up: f64,      // 遷移確率(上昇)
+    pub prob_mid: f64,     // 遷移確率(中間)
+    pub prob_down: f64,    // 遷移確率(下落)
+}
+
+impl Node {
+    pub fn new(
+        underlying: f64,
+        arrow_debreu: f64,
+        prob_up: f64,
+        prob_mid: f64,
+        prob_down: f64,
+    ) -> Self {
+        Self {
+            underlying,
+            arrow_debreu,
+            prob_up,
+            prob_mid,
+            prob_down,
+        }
+    }
+
+    /// 次の時刻の値（原資産価格、オプション価値など）の期待値を返します。
+    /// * `next_layer` - 次の時刻の各ノードの値のベクタ
+    /// * `index` - 当ノードの同一時刻内のインデックス（遷移先は index, index + 1, index + 2）
+    pub fn expected_next(&self, next_layer: &[f64], index: usize) -> f64 {
+        self.prob_down * next_layer[index]
+            + self.prob_mid * next_layer[index + 1]
+            + self.prob_up * next_layer[index + 2]
+    }
+}
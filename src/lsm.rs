@@ -1,7 +1,21 @@
+pub mod bermudan;
+pub mod bounds;
 mod least_square_monte_carlo;
+pub mod regression;
 
-use least_square_monte_carlo::{longstaff_schwartz_american_put, CalcInput};
-use std::time::Instant;
+use crate::mc::multi_asset::MultiGbm;
+use crate::mc::rand_num::{InverseCdf, RandGen, RandType};
+use bermudan::{lsm_price, BermudanProduct, ExerciseRight};
+use bounds::{lsm_price_bounds, BoundsConfig};
+use least_square_monte_carlo::{
+    american_put, longstaff_schwartz_american_put, longstaff_schwartz_american_put_until, lsm_gbm,
+    CalcInput,
+};
+use ndarray::arr2;
+use regression::{BasisType, Regression, Solver};
+
+// 乱数のシード
+const SEED: u64 = 1234;
 
 pub fn run() {
     let input = CalcInput {
@@ -11,9 +25,154 @@ pub fn run() {
         zero_rate: 0.05,
         term_annu: 1.0,
     };
-    let start = Instant::now();
-    let opt_price = longstaff_schwartz_american_put(&input, 100, 10000);
-    let end = start.elapsed();
-    println!("(lsm) time:{}s", end.as_secs_f64());
-    println!("(lsm) american option price: {}", opt_price);
+    let rand_gen = RandGen {
+        rand_type: RandType::Pseudo,
+        seed: SEED,
+        brownian_bridge: false,
+        inverse_cdf: InverseCdf::Moro,
+    };
+    let result = longstaff_schwartz_american_put(&input, 100, 10000, &rand_gen).result;
+    println!("(lsm) time:{}s", result.time_sec);
+    println!("(lsm) american option price: {:?}", result);
+
+    // 標準誤差が0.02を下回るまで10000パスずつ追加する。
+    let result =
+        longstaff_schwartz_american_put_until(&input, 100, 0.02, 10000, 1000000, &rand_gen);
+    println!(
+        "(lsm) american option price (target std error 0.02): {:?}",
+        result
+    );
+
+    // Sobol列の方向数は21次元までなので、行使日は20日とする。
+    let sobol_gen = RandGen {
+        rand_type: RandType::Sobol,
+        brownian_bridge: true,
+        ..rand_gen
+    };
+    let result = longstaff_schwartz_american_put(&input, 20, 8192, &sobol_gen).result;
+    println!(
+        "(lsm) american option price (sobol + brownian bridge, 20 exercise dates): {}",
+        result.price
+    );
+
+    // 2資産のBermudan max-call(年3回行使、3年)
+    let strike = 100.0;
+    let model = MultiGbm::new(
+        vec![100.0, 100.0],
+        vec![0.1, 0.1],
+        vec![0.2, 0.2],
+        0.05,
+        &arr2(&[[1.0, 0.0], [0.0, 1.0]]),
+    );
+    let max_call = BermudanProduct {
+        exercise_times: (1..10).map(|i| i as f64 / 3.0).collect(),
+        exercise_value: Box::new(move |s, _| (s[0].max(s[1]) - strike).max(0.0)),
+        coupon: None,
+        explanatory: Box::new(move |s, _| vec![s[0].max(s[1]) / strike, s[0].min(s[1]) / strike]),
+        exercise_right: ExerciseRight::Holder,
+        regression: Regression::default(),
+    };
+    let result = lsm_price(&model, &max_call, 9, 100000, &sobol_gen).result;
+    println!("(lsm) bermudan max-call price: {:?}", result);
+
+    // 基底関数と解き方による価格と回帰の条件数の違い(American Put、説明変数は原資産価格そのもの)
+    let gbm = lsm_gbm(&input);
+    for (basis_type, standardize, solver) in [
+        (BasisType::Monomial, false, Solver::NormalEquations),
+        (BasisType::Monomial, false, Solver::Svd),
+        (BasisType::Laguerre, true, Solver::Qr),
+        (BasisType::Hermite, true, Solver::Qr),
+        (BasisType::Chebyshev, true, Solver::Svd),
+    ] {
+        let put = BermudanProduct {
+            explanatory: Box::new(|s, _| vec![s[0]]),
+            regression: Regression {
+                basis_type,
+                standardize,
+                solver,
+                ..Regression::default()
+            },
+            ..american_put(&input, 50)
+        };
+        let result = lsm_price(&gbm, &put, 50, 50000, &rand_gen);
+        let max_cond = result
+            .condition_numbers
+            .iter()
+            .flatten()
+            .cloned()
+            .fold(0.0, f64::max);
+        println!(
+            "(lsm) american put ({:?}, standardize:{}, {:?}): {} (max condition number {:e})",
+            basis_type, standardize, solver, result.result.price, max_cond
+        );
+    }
+
+    // 説明変数が重複すると計画行列の階数が落ち、打ち切りSVDによる擬似逆行列で解く。
+    let explanatory: Vec<Vec<f64>> = (0..100)
+        .map(|i| {
+            let x = 0.8 + 0.004 * i as f64;
+            vec![x, 2.0 * x]
+        })
+        .collect();
+    let targets: Vec<f64> = explanatory.iter().map(|x| (1.0 - x[0]).max(0.0)).collect();
+    let regression = Regression {
+        solver: Solver::Svd,
+        ..Regression::default()
+    };
+    let fit = regression.fit(&explanatory, &targets);
+    println!(
+        "(lsm) rank of the design matrix with duplicated explanatory variables: {:?} (basis functions: {})",
+        fit.rank,
+        regression.num_basis(2)
+    );
+
+    // 満期にS_Tを支払い、発行体が四半期ごとに110で償還できる債券(発行体が価値を最小化するように行使する)
+    let call_price = 110.0;
+    let callable_note = BermudanProduct {
+        exercise_times: vec![0.25, 0.5, 0.75, 1.0],
+        exercise_value: Box::new(move |s, k| if k < 3 { call_price } else { s[0] }),
+        coupon: None,
+        explanatory: Box::new(move |s, _| vec![s[0] / call_price]),
+        exercise_right: ExerciseRight::Issuer,
+        regression: Regression::default(),
+    };
+    let result = lsm_price(&gbm, &callable_note, 4, 100000, &rand_gen).result;
+    println!("(lsm) issuer callable note price: {:?}", result);
+
+    // 年10回行使のBermudan Putの価格の区間(独立なパスでの下限とAndersen-Broadieの上限)
+    let bermudan_put = american_put(&input, 10);
+    let config = BoundsConfig {
+        num_train: 50000,
+        num_path: 100000,
+        num_outer: 1000,
+        num_inner: 500,
+    };
+    let bounds = lsm_price_bounds(&gbm, &bermudan_put, 10, &config, &rand_gen);
+    println!(
+        "(lsm) bermudan put bounds: in-sample {} lower {} ± {} upper {} ± {} ({}s)",
+        bounds.in_sample.price,
+        bounds.lower.price,
+        bounds.lower.std_error,
+        bounds.upper.price,
+        bounds.upper.std_error,
+        bounds.upper.time_sec
+    );
+    println!(
+        "(lsm) bermudan put exercise probabilities of the training paths: {:?}",
+        bounds.policy.exercise_probabilities
+    );
+
+    // 行使境界と行使確率(行使日ごとの回帰係数とあわせてCSVに書き出す)
+    let report = longstaff_schwartz_american_put(&input, 20, 50000, &rand_gen);
+    let csv_path = std::env::temp_dir().join("lsm_exercise_boundary.csv");
+    match report.write_csv(csv_path.to_str().unwrap()) {
+        Ok(()) => println!("(lsm) exercise boundary csv: {}", csv_path.display()),
+        Err(e) => println!("(lsm) failed to write exercise boundary csv: {}", e),
+    }
+    for k in (0..report.times.len()).step_by(4) {
+        println!(
+            "(lsm) exercise boundary t={:.2}: {:?} (exercise probability {})",
+            report.times[k], report.boundary[k], report.exercise_probabilities[k]
+        );
+    }
 }
@@ -0,0 +1,391 @@
+use super::regression::{Regression, RegressionModel};
+use crate::mc::engine::{McEngine, Path};
+use crate::mc::mc_result::{run_until_std_error, McResult};
+use crate::mc::path_generator::PathGenerator;
+use crate::mc::rand_num::RandGen;
+use rayon::prelude::*;
+use std::ops::Range;
+use std::time::Instant;
+
+// 時刻0とみなす行使日(回帰せずに継続価値を平均とする)
+const TIME_TOLERANCE: f64 = 1e-10;
+
+/* 任意のパスの生成モデルとBermudan型の商品に対するLongstaff-Schwartz
+行使日を後ろから順に、行使しなかった場合の(各行使日まで割り引いた)将来のキャッシュフローを説明変数の基底関数に回帰して継続価値を推定し、
+行使価値と比べて行使するかを決める。割引はパスのディスカウントファクターを使うため、確率金利のモデルにも使える。
+保有者が行使する商品(Bermudan Call/Putなど)は行使価値が正のパスだけで回帰し、継続価値より大きければ行使する。
+発行体が行使する商品(コーラブル債など)は全てのパスで回帰し、継続価値より小さければ行使する。 */
+
+/// 行使するのは保有者(価値を最大化)か発行体(価値を最小化)か
+#[derive(Debug, Copy, Clone)]
+pub enum ExerciseRight {
+    Holder,
+    Issuer,
+}
+
+/// (状態変数, 行使日のインデックス)に対する金額
+pub type ExerciseFn<'a> = Box<dyn Fn(&[f64], usize) -> f64 + Sync + 'a>;
+
+/// (状態変数, 行使日のインデックス)から回帰の説明変数を作る関数
+pub type ExplanatoryFn<'a> = Box<dyn Fn(&[f64], usize) -> Vec<f64> + Sync + 'a>;
+
+/// Bermudan型の商品です。状態変数はパスの生成モデルのものを使います。
+pub struct BermudanProduct<'a> {
+    pub exercise_times: Vec<f64>, // 行使日(昇順、最後が満期)
+    // (状態変数, 行使日のインデックス)に対する行使価値。満期では行使しなかった場合の受取額とする。
+    pub exercise_value: ExerciseFn<'a>,
+    // (状態変数, 行使日のインデックス)に対する、その行使日まで生存していれば受け取るキャッシュフロー(クーポンなど)
+    pub coupon: Option<ExerciseFn<'a>>,
+    // 回帰の説明変数(状態変数と行使日のインデックスから作る)
+    pub explanatory: ExplanatoryFn<'a>,
+    pub exercise_right: ExerciseRight,
+    pub regression: Regression, // 継続価値の回帰の基底関数と解き方
+}
+
+/// 回帰で推定した行使戦略です。<br>
+/// 回帰に使ったパスと独立なパスに適用すると、保有者が行使する商品なら下限、発行体が行使する商品なら上限となります。
+#[derive(Debug, Clone)]
+pub struct ExercisePolicy {
+    // 行使日ごとの継続価値(行使日まで割り引いた値)のモデル。満期と、回帰するパスがなかった行使日はNone
+    pub continuation: Vec<Option<RegressionModel>>,
+    // 行使日ごとの回帰の計画行列の条件数(回帰しなかった行使日はNone)
+    pub condition_numbers: Vec<Option<f64>>,
+    // 回帰に使ったパスの行使日ごとの行使確率(保有者の満期は行使価値が正の確率、発行体の満期は満期まで行使しない確率)
+    pub exercise_probabilities: Vec<f64>,
+}
+
+impl ExercisePolicy {
+    /// 行使日に行使するかを返します。満期では常に行使します。
+    /// * `product` - Bermudan型の商品
+    /// * `state` - 行使日の状態変数
+    /// * `k` - 行使日のインデックス
+    pub fn exercises(&self, product: &BermudanProduct, state: &[f64], k: usize) -> bool {
+        if k == product.exercise_times.len() - 1 {
+            return true;
+        }
+        let exercise = (product.exercise_value)(state, k);
+        match (&self.continuation[k], product.exercise_right) {
+            // 回帰するパスがなかった(行使価値が正のパスがなかった)行使日は行使しない。
+            (None, _) => false,
+            (Some(model), ExerciseRight::Holder) => {
+                exercise > 0.0 && exercise > model.predict(&(product.explanatory)(state, k))
+            }
+            (Some(model), ExerciseRight::Issuer) => {
+                exercise < model.predict(&(product.explanatory)(state, k))
+            }
+        }
+    }
+
+    /// start番目以降の行使日に行使戦略を適用して、パスのディスカウントファクターで割り引いたペイオフを返します。
+    /// * `product` - Bermudan型の商品
+    /// * `path` - 行使日を観測日とするパス
+    /// * `start` - 最初の行使日のインデックス
+    pub fn path_value(&self, product: &BermudanProduct, path: &Path, start: usize) -> f64 {
+        let mut value = 0.0;
+        for k in start..product.exercise_times.len() {
+            let (state, df) = (path.fixing_state(k), path.fixing_df(k));
+            value += coupon(product, state, k) * df;
+            if self.exercises(product, state, k) {
+                value += (product.exercise_value)(state, k) * df;
+                break;
+            }
+        }
+        value
+    }
+}
+
+/// Longstaff-Schwartzの計算結果です。
+#[derive(Debug, Clone)]
+pub struct LsmResult {
+    pub result: McResult,
+    // 行使日ごとの回帰の計画行列の条件数(回帰しなかった行使日はNone)
+    pub condition_numbers: Vec<Option<f64>>,
+    // 推定した行使戦略(行使日ごとの継続価値のモデルと行使確率)
+    pub policy: ExercisePolicy,
+}
+
+/// Longstaff-SchwartzでBermudan型の商品の価格を返します。<br>
+/// 標準誤差はパスごとのペイオフから求めたもので、回帰の誤差は含みません。
+/// * `generator` - パスの生成モデル
+/// * `product` - Bermudan型の商品
+/// * `time_step` - 時間方向のステップ数(行使日を除く)
+/// * `num_path` - パス数
+/// * `rand_gen` - 乱数の設定
+pub fn lsm_price(
+    generator: &dyn PathGenerator,
+    product: &BermudanProduct,
+    time_step: usize,
+    num_path: usize,
+    rand_gen: &RandGen,
+) -> LsmResult {
+    let start = Instant::now();
+    let engine = McEngine::new(generator, &product.exercise_times, time_step, rand_gen);
+    let (payoffs, policy) = lsm_policy(product, &engine, 0..num_path);
+    LsmResult {
+        result: McResult::from_samples(&payoffs, start),
+        condition_numbers: policy.condition_numbers.clone(),
+        policy,
+    }
+}
+
+/// 標準誤差が目標値を下回るまでパスを追加して、Longstaff-SchwartzでBermudan型の商品の価格を返します。<br>
+/// 回帰はバッチごとに独立に行います。
+/// * `generator` - パスの生成モデル
+/// * `product` - Bermudan型の商品
+/// * `time_step` - 時間方向のステップ数(行使日を除く)
+/// * `target_std_error` - 目標の標準誤差
+/// * `batch_size` - 1回に追加するパス数(回帰に使うパス数)
+/// * `max_path` - パス数の上限
+/// * `rand_gen` - 乱数の設定
+pub fn lsm_price_until(
+    generator: &dyn PathGenerator,
+    product: &BermudanProduct,
+    time_step: usize,
+    target_std_error: f64,
+    batch_size: usize,
+    max_path: usize,
+    rand_gen: &RandGen,
+) -> McResult {
+    let engine = McEngine::new(generator, &product.exercise_times, time_step, rand_gen);
+    run_until_std_error(target_std_error, batch_size, max_path, |paths| {
+        lsm_payoffs(product, &engine, paths)
+    })
+}
+
+/// パスのインデックスの範囲で回帰を行い、パスごとの割引後のペイオフを返します。
+/// * `product` - Bermudan型の商品
+/// * `engine` - 行使日を観測日とするエンジン
+/// * `paths` - パスのインデックスの範囲
+pub fn lsm_payoffs(product: &BermudanProduct, engine: &McEngine, paths: Range<usize>) -> Vec<f64> {
+    lsm_policy(product, engine, paths).0
+}
+
+/// パスのインデックスの範囲で回帰を行い、パスごとの割引後のペイオフと推定した行使戦略を返します。
+/// * `product` - Bermudan型の商品
+/// * `engine` - 行使日を観測日とするエンジン
+/// * `paths` - パスのインデックスの範囲
+pub fn lsm_policy(
+    product: &BermudanProduct,
+    engine: &McEngine,
+    paths: Range<usize>,
+) -> (Vec<f64>, ExercisePolicy) {
+    let last = product.exercise_times.len() - 1;
+    // パスのインデックスを乱数のストリーム番号とし、スレッド数によらず同じパスとなるようにする。
+    // 行使日ごとの(状態変数, ディスカウントファクター)
+    let fixings: Vec<Vec<(Vec<f64>, f64)>> = paths
+        .into_par_iter()
+        .map(|path_idx| {
+            let path = engine.path(path_idx);
+            (0..last + 1)
+                .map(|k| (path.fixing_state(k).to_vec(), path.fixing_df(k)))
+                .collect()
+        })
+        .collect();
+    let num_path = fixings.len();
+    let coupon = |state: &[f64], k: usize| coupon(product, state, k);
+
+    // 時刻0まで割り引いたペイオフ
+    let mut payoffs: Vec<f64> = fixings
+        .par_iter()
+        .map(|f| {
+            let (state, df) = (&f[last].0, f[last].1);
+            ((product.exercise_value)(state, last) + coupon(state, last)) * df
+        })
+        .collect();
+    let mut policy = ExercisePolicy {
+        continuation: vec![None; last + 1],
+        condition_numbers: vec![None; last + 1],
+        exercise_probabilities: vec![0.0; last + 1],
+    };
+    // パスごとの行使日のインデックス(行使しなければNone)
+    let mut stopping: Vec<Option<usize>> = fixings
+        .iter()
+        .map(|f| match product.exercise_right {
+            ExerciseRight::Holder => {
+                ((product.exercise_value)(&f[last].0, last) > 0.0).then_some(last)
+            }
+            ExerciseRight::Issuer => Some(last),
+        })
+        .collect();
+
+    for k in (0..last).rev() {
+        let exercise: Vec<f64> = fixings
+            .iter()
+            .map(|f| (product.exercise_value)(&f[k].0, k))
+            .collect();
+        // 行使日まで割り引いた将来のキャッシュフロー
+        let future: Vec<f64> = (0..num_path)
+            .map(|p| payoffs[p] / fixings[p][k].1)
+            .collect();
+        let candidates: Vec<usize> = match product.exercise_right {
+            ExerciseRight::Holder => (0..num_path).filter(|p| exercise[*p] > 0.0).collect(),
+            ExerciseRight::Issuer => (0..num_path).collect(),
+        };
+        let continuation: Vec<f64> = if product.exercise_times[k] < TIME_TOLERANCE {
+            let mean = future.iter().sum::<f64>() / num_path as f64;
+            let dim = (product.explanatory)(&fixings[0][k].0, k).len();
+            policy.continuation[k] = Some(product.regression.constant_model(dim, mean));
+            vec![mean; candidates.len()]
+        } else {
+            let explanatory: Vec<Vec<f64>> = candidates
+                .iter()
+                .map(|p| (product.explanatory)(&fixings[*p][k].0, k))
+                .collect();
+            let targets: Vec<f64> = candidates.iter().map(|p| future[*p]).collect();
+            let fit = product.regression.fit(&explanatory, &targets);
+            policy.condition_numbers[k] = fit.condition_number;
+            policy.continuation[k] = fit.model;
+            fit.fitted
+        };
+        for (i, p) in candidates.iter().enumerate() {
+            let exercised = match product.exercise_right {
+                ExerciseRight::Holder => exercise[*p] > continuation[i],
+                ExerciseRight::Issuer => exercise[*p] < continuation[i],
+            };
+            if exercised {
+                payoffs[*p] = exercise[*p] * fixings[*p][k].1;
+                stopping[*p] = Some(k);
+            }
+        }
+        for p in 0..num_path {
+            payoffs[p] += coupon(&fixings[p][k].0, k) * fixings[p][k].1;
+        }
+    }
+    for k in stopping.iter().flatten() {
+        policy.exercise_probabilities[*k] += 1.0;
+    }
+    for prob in policy.exercise_probabilities.iter_mut() {
+        *prob /= num_path as f64;
+    }
+    (payoffs, policy)
+}
+
+// その行使日まで生存していれば受け取るキャッシュフロー
+fn coupon(product: &BermudanProduct, state: &[f64], k: usize) -> f64 {
+    product.coupon.as_ref().map_or(0.0, |c| c(state, k))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bs::black_scholes::{self, black_scholes};
+    use crate::lsm::regression::{BasisType, Solver};
+    use crate::mc::multi_asset::MultiGbm;
+    use crate::mc::test_util::{gbm, rand_gen};
+    use ndarray::arr2;
+
+    const SEED: u64 = 31;
+
+    fn bs_call(strike: f64, term: f64) -> f64 {
+        let input = black_scholes::CalcInput {
+            zero_rate: 0.05,
+            vol: 0.2,
+            term_annu: term,
+            strike,
+            underlying: 100.0,
+        };
+        black_scholes(&input, black_scholes::OptionType::Call)
+    }
+
+    #[test]
+    fn test_bermudan_call_without_dividend() {
+        // 配当がなければ早期行使は最適でなく、Bermudan CallはEuropean Callと一致する。
+        let strike = 100.0;
+        let call = BermudanProduct {
+            exercise_times: (1..5).map(|i| i as f64 * 0.25).collect(),
+            exercise_value: Box::new(move |s, _| (s[0] - strike).max(0.0)),
+            coupon: None,
+            explanatory: Box::new(move |s, _| vec![s[0] / strike]),
+            exercise_right: ExerciseRight::Holder,
+            regression: Regression::default(),
+        };
+        let result = lsm_price(&gbm(0.05, 0.0, 0.2), &call, 4, 50000, &rand_gen(SEED)).result;
+        assert!((result.price - bs_call(strike, 1.0)).abs() < 4.0 * result.std_error + 0.05);
+    }
+
+    #[test]
+    fn test_bermudan_max_call() {
+        // Andersen-Broadie(2004)の2資産のBermudan max-call(S_0 = 100、年3回で3年)。参照値は約13.90
+        let strike = 100.0;
+        let model = MultiGbm::new(
+            vec![100.0, 100.0],
+            vec![0.1, 0.1],
+            vec![0.2, 0.2],
+            0.05,
+            &arr2(&[[1.0, 0.0], [0.0, 1.0]]),
+        );
+        let max_call = BermudanProduct {
+            exercise_times: (1..10).map(|i| i as f64 / 3.0).collect(),
+            exercise_value: Box::new(move |s, _| (s[0].max(s[1]) - strike).max(0.0)),
+            coupon: None,
+            explanatory: Box::new(move |s, _| {
+                vec![s[0].max(s[1]) / strike, s[0].min(s[1]) / strike]
+            }),
+            exercise_right: ExerciseRight::Holder,
+            regression: Regression::default(),
+        };
+        let result = lsm_price(&model, &max_call, 9, 50000, &rand_gen(SEED)).result;
+        assert!((result.price - 13.90).abs() < 4.0 * result.std_error + 0.15);
+    }
+
+    #[test]
+    fn test_issuer_callable_note() {
+        // 満期にS_Tを支払い、発行体が行使日にKで償還できる債券は S_0 - (最後の行使日満期のCall) に等しい。
+        // (配当がなければ発行体のCallは最後の行使日まで行使しないのが最適)
+        let strike = 110.0;
+        let call_dates = [0.25, 0.5, 0.75];
+        let note = BermudanProduct {
+            exercise_times: vec![0.25, 0.5, 0.75, 1.0],
+            exercise_value: Box::new(move |s, k| if k < call_dates.len() { strike } else { s[0] }),
+            coupon: None,
+            explanatory: Box::new(move |s, _| vec![s[0] / strike]),
+            exercise_right: ExerciseRight::Issuer,
+            regression: Regression::default(),
+        };
+        let result = lsm_price(&gbm(0.05, 0.0, 0.2), &note, 4, 50000, &rand_gen(SEED)).result;
+        let expected = 100.0 - bs_call(strike, 0.75);
+        assert!((result.price - expected).abs() < 4.0 * result.std_error + 0.05);
+    }
+
+    #[test]
+    fn test_regression_bases() {
+        let (gbm, rand_gen) = (gbm(0.05, 0.0, 0.2), rand_gen(SEED));
+        // 説明変数を原資産価格そのものとしても、標準化した基底ならどれも同じ程度の価格となり、条件数も小さい。
+        let strike = 100.0;
+        let put = |regression: Regression| BermudanProduct {
+            exercise_times: (1..11).map(|i| i as f64 * 0.1).collect(),
+            exercise_value: Box::new(move |s, _| (strike - s[0]).max(0.0)),
+            coupon: None,
+            explanatory: Box::new(|s, _| vec![s[0]]),
+            exercise_right: ExerciseRight::Holder,
+            regression,
+        };
+        let reference = lsm_price(&gbm, &put(Regression::default()), 10, 20000, &rand_gen);
+        for basis_type in [
+            BasisType::Hermite,
+            BasisType::Monomial,
+            BasisType::Chebyshev,
+        ] {
+            for solver in [Solver::Qr, Solver::Svd] {
+                let regression = Regression {
+                    basis_type,
+                    solver,
+                    ..Regression::default()
+                };
+                let result = lsm_price(&gbm, &put(regression), 10, 20000, &rand_gen);
+                assert!((result.result.price - reference.result.price).abs() < 0.03);
+                let conds: Vec<f64> = result.condition_numbers.iter().flatten().cloned().collect();
+                assert_eq!(conds.len(), 9);
+                assert!(conds.iter().all(|c| *c < 1e3));
+            }
+        }
+        // 標準化しない単項式では条件数が非常に大きい。
+        let raw = Regression {
+            basis_type: BasisType::Monomial,
+            standardize: false,
+            ..Regression::default()
+        };
+        let result = lsm_price(&gbm, &put(raw), 10, 20000, &rand_gen);
+        assert!(result.condition_numbers[5].unwrap() > 1e6);
+    }
+}
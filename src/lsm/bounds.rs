@@ -0,0 +1,221 @@
+use super::bermudan::{lsm_policy, BermudanProduct, ExercisePolicy, ExerciseRight};
+use crate::mc::engine::McEngine;
+use crate::mc::mc_result::McResult;
+use crate::mc::path_generator::PathGenerator;
+use crate::mc::rand_num::RandGen;
+use rayon::prelude::*;
+use std::time::Instant;
+
+/* Bermudan型の商品の価格の区間
+主問題: 回帰に使ったパスと独立なパスで推定した行使戦略τを適用すると、E[Z_τ]は(最適でない戦略なので)保有者なら価格の下限となる。
+        Z_kはk番目の行使日に行使した場合の時刻0まで割り引いたキャッシュフロー(それまでのクーポンを含む)である。
+双対問題(Andersen-Broadie 2004): 任意のマルチンゲールM(M_0 = 0)に対して 価格 <= E[max_k (Z_k - M_k)] となる。
+        行使戦略に従った場合の価値 L_k から M_{k+1} = M_k + L_{k+1} - E_k[L_{k+1}] とし、条件付き期待値 E_k[L_{k+1}] は
+        k番目の行使日の状態変数から始めた入れ子のシミュレーションで(k+1番目以降に行使戦略を適用して)推定する。
+        行使戦略が最適ならMはスネル包絡線のDoob-Meyer分解のマルチンゲールとなり、上限は価格に一致する。
+        最初の行使日までの増分 L_{t_1} - E[L_{t_1}] もマルチンゲールに含め、E[L_{t_1}]を主問題の推定値で置き換えると
+        上限 = 主問題の推定値 + E[max_k (Z_k - M_k) - L_{t_1}](双対ギャップ)となり、ギャップの分散は小さい。
+発行体が行使する商品は価値を最小化するため、主問題が上限、双対問題(E[min_k (Z_k - M_k)])が下限となる。
+入れ子のシミュレーションの誤差は上限を上方(発行体なら下限を下方)にずらすだけなので、区間は保守的になる。 */
+
+/// 価格の区間の計算の設定です。
+#[derive(Debug, Copy, Clone)]
+pub struct BoundsConfig {
+    pub num_train: usize, // 行使戦略の回帰に使うパス数
+    pub num_path: usize,  // 行使戦略を適用する独立なパス数
+    pub num_outer: usize, // 双対問題の外側のパス数
+    pub num_inner: usize, // 双対問題の行使日ごとの入れ子のパス数
+}
+
+/// Bermudan型の商品の価格の区間です。
+#[derive(Debug, Clone)]
+pub struct PriceBounds {
+    pub in_sample: McResult, // 回帰に使ったパスでの価格(通常のLongstaff-Schwartz)
+    pub lower: McResult,
+    pub upper: McResult,
+    pub policy: ExercisePolicy,
+}
+
+/// 独立なパスに行使戦略を適用した価格と、Andersen-Broadieの双対による価格から、Bermudan型の商品の価格の区間を返します。
+/// * `generator` - パスの生成モデル
+/// * `product` - Bermudan型の商品
+/// * `time_step` - 時間方向のステップ数(行使日を除く)
+/// * `config` - 区間の計算の設定
+/// * `rand_gen` - 乱数の設定
+pub fn lsm_price_bounds(
+    generator: &dyn PathGenerator,
+    product: &BermudanProduct,
+    time_step: usize,
+    config: &BoundsConfig,
+    rand_gen: &RandGen,
+) -> PriceBounds {
+    let start = Instant::now();
+    let engine = McEngine::new(generator, &product.exercise_times, time_step, rand_gen);
+    let (payoffs, policy) = lsm_policy(product, &engine, 0..config.num_train);
+    let in_sample = McResult::from_samples(&payoffs, start);
+
+    // パスのインデックスを回帰に使ったパスの後ろから取り、独立なパスとする。
+    let start = Instant::now();
+    let offset = config.num_train;
+    let values: Vec<f64> = (offset..offset + config.num_path)
+        .into_par_iter()
+        .map(|path_idx| policy.path_value(product, &engine.path(path_idx), 0))
+        .collect();
+    let primal = McResult::from_samples(&values, start);
+
+    let start = Instant::now();
+    let offset = offset + config.num_path;
+    let values: Vec<f64> = (0..config.num_outer)
+        .into_par_iter()
+        .map(|outer| {
+            dual_value(
+                product,
+                &policy,
+                &engine,
+                offset + outer,
+                outer,
+                config.num_inner,
+            )
+        })
+        .collect();
+    let gap = McResult::from_samples(&values, start);
+    let dual = McResult::new(
+        primal.price + gap.price,
+        (primal.std_error.powi(2) + gap.std_error.powi(2)).sqrt(),
+        config.num_outer,
+        start,
+    );
+
+    let (lower, upper) = match product.exercise_right {
+        ExerciseRight::Holder => (primal, dual),
+        ExerciseRight::Issuer => (dual, primal),
+    };
+    PriceBounds {
+        in_sample,
+        lower,
+        upper,
+        policy,
+    }
+}
+
+// 外側のパス1本の max_k (Z_k - M_k) - L_{t_1}(発行体ならmin)
+fn dual_value(
+    product: &BermudanProduct,
+    policy: &ExercisePolicy,
+    engine: &McEngine,
+    path_idx: usize,
+    outer: usize,
+    num_inner: usize,
+) -> f64 {
+    let last = product.exercise_times.len() - 1;
+    let path = engine.path(path_idx);
+    let coupon = |state: &[f64], k: usize| product.coupon.as_ref().map_or(0.0, |c| c(state, k));
+    let mut coupons = 0.0; // k番目の行使日までのクーポンの割引価値
+    let mut martingale = 0.0;
+    let mut prev_continuation = 0.0; // E_{k-1}[L_k]
+    let mut initial_value = 0.0; // L_{t_1}
+    let mut bound = match product.exercise_right {
+        ExerciseRight::Holder => f64::NEG_INFINITY,
+        ExerciseRight::Issuer => f64::INFINITY,
+    };
+    for k in 0..last + 1 {
+        let (state, df) = (path.fixing_state(k), path.fixing_df(k));
+        coupons += coupon(state, k) * df;
+        let exercise = coupons + (product.exercise_value)(state, k) * df;
+        // E_k[L_{k+1}]: k+1番目以降に行使戦略を適用した入れ子のシミュレーション
+        let continuation = if k < last {
+            let sum: f64 = (0..num_inner)
+                .map(|j| {
+                    let stream = (outer * last + k) * num_inner + j;
+                    let inner = engine.path_from(path.fixing_index(k), state, stream);
+                    policy.path_value(product, &inner, k + 1)
+                })
+                .sum();
+            coupons + df * sum / num_inner as f64
+        } else {
+            0.0
+        };
+        let value = if policy.exercises(product, state, k) {
+            exercise
+        } else {
+            continuation
+        };
+        if k > 0 {
+            martingale += value - prev_continuation;
+        } else {
+            initial_value = value;
+        }
+        bound = match product.exercise_right {
+            ExerciseRight::Holder => bound.max(exercise - martingale),
+            ExerciseRight::Issuer => bound.min(exercise - martingale),
+        };
+        prev_continuation = continuation;
+    }
+    bound - initial_value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bs::black_scholes::{self, black_scholes};
+    use crate::lsm::regression::Regression;
+    use crate::mc::test_util::{gbm, rand_gen};
+
+    const SEED: u64 = 37;
+
+    fn bermudan(strike: f64, is_call: bool) -> BermudanProduct<'static> {
+        BermudanProduct {
+            exercise_times: (1..11).map(|i| i as f64 * 0.1).collect(),
+            exercise_value: Box::new(move |s, _| {
+                let intrinsic = if is_call {
+                    s[0] - strike
+                } else {
+                    strike - s[0]
+                };
+                intrinsic.max(0.0)
+            }),
+            coupon: None,
+            explanatory: Box::new(move |s, _| vec![s[0] / strike]),
+            exercise_right: ExerciseRight::Holder,
+            regression: Regression::default(),
+        }
+    }
+
+    const CONFIG: BoundsConfig = BoundsConfig {
+        num_train: 20000,
+        num_path: 50000,
+        num_outer: 300,
+        num_inner: 300,
+    };
+
+    #[test]
+    fn test_bounds_bermudan_call() {
+        let (gbm, rand_gen) = (gbm(0.05, 0.0, 0.2), rand_gen(SEED));
+        // 配当がなければ価格はEuropean Callで、行使戦略はほぼ最適なので区間は狭い。
+        let bounds = lsm_price_bounds(&gbm, &bermudan(100.0, true), 10, &CONFIG, &rand_gen);
+        let input = black_scholes::CalcInput {
+            zero_rate: 0.05,
+            vol: 0.2,
+            term_annu: 1.0,
+            strike: 100.0,
+            underlying: 100.0,
+        };
+        let european = black_scholes(&input, black_scholes::OptionType::Call);
+        assert!(bounds.lower.price < european + 4.0 * bounds.lower.std_error);
+        assert!(bounds.upper.price > european - 4.0 * bounds.upper.std_error);
+        assert!(bounds.upper.price - european < 0.1);
+    }
+
+    #[test]
+    fn test_bounds_bermudan_put() {
+        let (gbm, rand_gen) = (gbm(0.05, 0.0, 0.2), rand_gen(SEED));
+        // 区間は American Put(約6.09)以下で、LSMの行使戦略の準最適性による幅は小さい。
+        let bounds = lsm_price_bounds(&gbm, &bermudan(100.0, false), 10, &CONFIG, &rand_gen);
+        let gap = bounds.upper.price - bounds.lower.price;
+        let noise = 4.0 * (bounds.lower.std_error + bounds.upper.std_error);
+        assert!(gap > -noise);
+        assert!(gap < 0.1 + noise);
+        assert!(bounds.upper.price < 6.09 + noise);
+        assert!(bounds.policy.continuation[9].is_none());
+    }
+}
@@ -1,8 +1,19 @@
-use ndarray::{Array1, Array2};
-use ndarray_linalg::Inverse;
-use rand::thread_rng;
-use rand_distr::{Distribution, StandardNormal};
-use rayon::prelude::*;
+use super::bermudan::{lsm_price, lsm_price_until, BermudanProduct, ExercisePolicy, ExerciseRight};
+use super::regression::Regression;
+use crate::mc::mc_result::McResult;
+use crate::mc::path_generator::Gbm;
+use crate::mc::rand_num::RandGen;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+// 行使境界を探す刻み(対数の原資産価格の標準偏差に対する比)
+const SCAN_STEP: f64 = 0.1;
+
+// 行使境界の二分法の反復回数
+const BISECTION_ITERATION: usize = 50;
+
+// 行使境界を探す下端(対数の原資産価格の期待値から何標準偏差下か)。これより下には回帰に使ったパスがほぼない
+const LOWER_STD_DEV: f64 = 4.0;
 
 #[derive(Debug, Copy, Clone)]
 pub struct CalcInput {
@@ -13,125 +24,223 @@ pub struct CalcInput {
     pub underlying: f64,
 }
 
+/// 標準誤差が目標値を下回るまでパスを追加して、Longstaff-SchwartzでAmerican Putの価格を返します。<br>
+/// 回帰はバッチごとに独立に行います。
+/// * `input` - 計算のインプット
+/// * `time_step` - 時間方向のステップ数
+/// * `target_std_error` - 目標の標準誤差
+/// * `batch_size` - 1回に追加するパス数(回帰に使うパス数)
+/// * `max_path` - パス数の上限
+/// * `rand_gen` - 乱数の設定
+pub fn longstaff_schwartz_american_put_until(
+    input: &CalcInput,
+    time_step: usize,
+    target_std_error: f64,
+    batch_size: usize,
+    max_path: usize,
+    rand_gen: &RandGen,
+) -> McResult {
+    let gbm = lsm_gbm(input);
+    let put = american_put(input, time_step);
+    lsm_price_until(
+        &gbm,
+        &put,
+        time_step,
+        target_std_error,
+        batch_size,
+        max_path,
+        rand_gen,
+    )
+}
+
+/// American Putの行使境界と行使の統計です。
+#[derive(Debug, Clone)]
+pub struct ExerciseBoundary {
+    pub result: McResult,
+    pub times: Vec<f64>, // 行使日
+    // 行使日ごとの行使境界(原資産価格がこれ以下なら行使する)。行使しない行使日はNone
+    pub boundary: Vec<Option<f64>>,
+    // 行使日ごとの行使確率(満期はITMの確率)
+    pub exercise_probabilities: Vec<f64>,
+    // 行使日ごとの継続価値の回帰係数(基底関数はRegression::default、説明変数は原資産価格 / 行使価格)
+    pub coefficients: Vec<Option<Vec<f64>>>,
+}
+
+impl ExerciseBoundary {
+    /// 行使日ごとに 時刻, 行使境界, 行使確率, 回帰係数 をCSVに書き出します。
+    /// * `path` - ファイルのパス
+    pub fn write_csv(&self, path: &str) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let num_coeffs = self.coefficients.iter().flatten().map(|c| c.len()).max();
+        let coeff_header: Vec<String> = (0..num_coeffs.unwrap_or(0))
+            .map(|i| format!(",coeff{}", i))
+            .collect();
+        writeln!(
+            writer,
+            "time,boundary,exercise_probability{}",
+            coeff_header.concat()
+        )?;
+        for k in 0..self.times.len() {
+            let boundary = self.boundary[k].map_or(String::new(), |b| b.to_string());
+            let coeffs: Vec<String> = self.coefficients[k]
+                .iter()
+                .flatten()
+                .map(|c| format!(",{}", c))
+                .collect();
+            writeln!(
+                writer,
+                "{},{},{}{}",
+                self.times[k],
+                boundary,
+                self.exercise_probabilities[k],
+                coeffs.concat()
+            )?;
+        }
+        writer.flush()
+    }
+}
+
+/// Longstaff-SchwartzでAmerican Putの価格と、回帰から推定した行使境界、行使確率、回帰係数を返します。<br>
+/// 標準誤差はパスごとのペイオフから求めたもので、回帰の誤差は含みません。
+/// * `input` - 計算のインプット
+/// * `time_step` - 時間方向のステップ数
+/// * `num_path` - パス数
+/// * `rand_gen` - 乱数の設定
 pub fn longstaff_schwartz_american_put(
     input: &CalcInput,
     time_step: usize,
     num_path: usize,
-) -> f64 {
-    let CalcInput {
-        zero_rate,
-        vol,
-        term_annu,
-        strike,
-        underlying,
-    } = *input;
+    rand_gen: &RandGen,
+) -> ExerciseBoundary {
     println!("longstaff_schwartz_american_put");
     println!("number of paths:{}", num_path);
-
-    let delta_t = term_annu / time_step as f64;
-    let df_one_step = (-zero_rate * delta_t).exp();
-
-    // 2次元Vecで並列にパスを生成。いくつか試した中ではこれが一番速い。
-    let und_paths: Vec<Vec<f64>> = vec![vec![0.0; time_step + 1]; num_path];
-    let und_paths: Vec<Vec<f64>> = und_paths
-        .into_par_iter()
-        .map(|mut und_path| -> Vec<f64> {
-            und_path[0] = underlying;
-            for i in 0..time_step {
-                let norm_rand: f64 = StandardNormal.sample(&mut thread_rng());
-                und_path[i + 1] = und_path[i]
-                    * ((zero_rate - 0.5 * vol.powi(2)) * delta_t
-                        + vol * delta_t.sqrt() * norm_rand)
-                        .exp();
+    let gbm = lsm_gbm(input);
+    let put = american_put(input, time_step);
+    let lsm = lsm_price(&gbm, &put, time_step, num_path, rand_gen);
+    let last = put.exercise_times.len() - 1;
+    let boundary: Vec<Option<f64>> = (0..last + 1)
+        .map(|k| {
+            if k == last {
+                Some(input.strike)
+            } else {
+                put_boundary(input, &put, &lsm.policy, k)
             }
-            und_path
         })
         .collect();
+    ExerciseBoundary {
+        result: lsm.result,
+        times: put.exercise_times.clone(),
+        boundary,
+        exercise_probabilities: lsm.policy.exercise_probabilities.clone(),
+        coefficients: lsm
+            .policy
+            .continuation
+            .iter()
+            .map(|m| m.as_ref().map(|m| m.coefficients.clone()))
+            .collect(),
+    }
+}
 
-    // Array1を使ってVecを要素としてpar_map_inplaceで並列にパスを生成。
-    // let mut und_paths: Array1<Vec<f64>> = Array1::from_elem(num_path, vec![0.0; time_step + 1]);
-    // und_paths.par_map_inplace(|und_path: &mut Vec<f64>| {
-    //     und_path[0] = underlying;
-    //     for i in 0..time_step {
-    //         let norm_rand: f64 = StandardNormal.sample(&mut thread_rng());
-    //         und_path[i + 1] = und_path[i]
-    //             * ((zero_rate - 0.5 * vol.powi(2)) * delta_t + vol * delta_t.sqrt() * norm_rand)
-    //                 .exp();
-    //     }
-    // });
-
-    let payoffs: Vec<f64> = vec![0.0; num_path];
-    // for i in 0..num_path {
-    //     payoffs[i] = (strike - und_paths[i][time_step]).max(0.0);
-    // }
-    let mut payoffs: Vec<f64> = payoffs
-        .par_iter()
-        .enumerate()
-        .map(|(idx, _)| (strike - &und_paths[idx][time_step]).max(0.0))
-        .collect();
-
-    for step in (1..time_step).rev() {
-        let mut itm_paths: Vec<usize> = Vec::new();
-        for path in 0..num_path {
-            if strike - und_paths[path][step] > 0.0 {
-                itm_paths.push(path);
-            }
-        }
-        // 基底関数はラゲール多項式の0～3項を使用する。
-        let laguerre: Array2<f64> = Array2::from_shape_fn((itm_paths.len(), 4), |(i, j)| {
-            laguerre_polynomial(und_paths[itm_paths[i]][step])[j]
-        });
-
-        let explained: Array1<f64> =
-            Array1::from_shape_fn(itm_paths.len(), |i| df_one_step * &payoffs[itm_paths[i]]);
-        let reg_coeffs: Array1<f64> =
-            ((((laguerre.t()).dot(&laguerre)).inv().unwrap()).dot(&laguerre.t())).dot(&explained);
-
-        let mut not_excercise = vec![true; num_path];
-        let continuation_vals = laguerre.dot(&reg_coeffs);
-        for (itm_path_idx, path) in itm_paths.iter().enumerate() {
-            // itm_pathsはITMのみのため、0との比較は不要
-            let intrinsic_val = strike - und_paths[*path][step];
-            if intrinsic_val > continuation_vals[itm_path_idx] {
-                payoffs[*path] = intrinsic_val;
-                not_excercise[*path] = false;
-            }
-        }
-
-        for i in 0..num_path {
-            if not_excercise[i] {
-                payoffs[i] = df_one_step * payoffs[i];
-            }
+// 行使価格から対数の原資産価格の標準偏差の刻みで下に探し、最初に行使する価格と行使しない価格の間を二分法で求める。
+// 回帰の外挿でパスの少ない低い原資産価格にも行使する区間ができることがあるため、行使価格に最も近い境界を探す。
+fn put_boundary(
+    input: &CalcInput,
+    put: &BermudanProduct,
+    policy: &ExercisePolicy,
+    k: usize,
+) -> Option<f64> {
+    let exercises = |s: f64| policy.exercises(put, &[s], k);
+    let time = put.exercise_times[k];
+    let std_dev = input.vol * time.sqrt();
+    // パスが分布する範囲の下端の対数の原資産価格 / 行使価格
+    let log_lower = (input.underlying / input.strike).ln()
+        + (input.zero_rate - 0.5 * input.vol.powi(2)) * time
+        - LOWER_STD_DEV * std_dev;
+    let step = SCAN_STEP * std_dev;
+    let num_grid = (-log_lower / step).ceil().max(1.0) as usize;
+    let grid = |i: usize| input.strike * (-(i as f64) * step).exp();
+    let i = (1..num_grid + 1).find(|i| exercises(grid(*i)))?;
+    let (mut lower, mut upper) = (grid(i), grid(i - 1));
+    for _ in 0..BISECTION_ITERATION {
+        let mid = 0.5 * (lower + upper);
+        if exercises(mid) {
+            lower = mid;
+        } else {
+            upper = mid;
         }
+    }
+    Some(lower)
+}
 
-        // 回帰をITMに絞らないパターン。ITMのパスのインデックスを抽出する必要がなくなるが、パスが多いため遅くなる。
-        // let laguerre: Array2<f64> = Array2::from_shape_fn((num_path, 4), |(i, j)| {
-        //     laguerre_polynomial(und_paths[i][step])[j]
-        // });
+pub fn lsm_gbm(input: &CalcInput) -> Gbm {
+    Gbm {
+        underlying: input.underlying,
+        zero_rate: input.zero_rate,
+        div_yield: 0.0,
+        vol: input.vol,
+    }
+}
 
-        // let explained: Array1<f64> = Array1::from_shape_fn(num_path, |i| df_one_step * &payoffs[i]);
-        // let reg_coeffs: Array1<f64> =
-        //     ((((laguerre.t()).dot(&laguerre)).inv().unwrap()).dot(&laguerre.t())).dot(&explained);
+// 行使日 t_i = iΔt (i = 1, ..., time_step) のPut。回帰の説明変数は行使価格で割った原資産価格
+pub fn american_put(input: &CalcInput, time_step: usize) -> BermudanProduct<'static> {
+    let delta_t = input.term_annu / time_step as f64;
+    let strike = input.strike;
+    BermudanProduct {
+        exercise_times: (1..time_step + 1).map(|i| i as f64 * delta_t).collect(),
+        exercise_value: Box::new(move |s, _| (strike - s[0]).max(0.0)),
+        coupon: None,
+        explanatory: Box::new(move |s, _| vec![s[0] / strike]),
+        exercise_right: ExerciseRight::Holder,
+        regression: Regression::default(),
+    }
+}
 
-        // let conti_vals = laguerre.dot(&reg_coeffs);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mc::test_util::rand_gen;
 
-        // for i in 0..num_path {
-        //     let intrinsic_val = strike - und_paths[i][step];
-        //     if intrinsic_val > conti_vals[i] {
-        //         payoffs[i] = intrinsic_val;
-        //     } else {
-        //         payoffs[i] = df_one_step * payoffs[i];
-        //     }
-        // }
+    #[test]
+    fn test_longstaff_schwartz_american_put() {
+        let input = CalcInput {
+            underlying: 100.0,
+            strike: 100.0,
+            vol: 0.2,
+            zero_rate: 0.05,
+            term_annu: 1.0,
+        };
+        let rand_gen = rand_gen(5);
+        let result =
+            longstaff_schwartz_american_put_until(&input, 50, 0.03, 5000, 100000, &rand_gen);
+        assert!(result.std_error < 0.03);
+        // American put の参照値(二項格子)は約 6.090。LSMは下方バイアスを持つ。
+        assert!((result.price - 6.090).abs() < 4.0 * result.std_error + 0.05);
     }
-    payoffs.par_iter().sum::<f64>() / num_path as f64 * df_one_step
-}
 
-// ラゲール多項式の0〜3までの項を返す
-fn laguerre_polynomial(x: f64) -> [f64; 4] {
-    let lag0 = 1.0;
-    let lag1 = 1.0 - x;
-    let lag2 = 1.0 - 2.0 * x + 0.5 * x.powi(2);
-    let lag3 = 1.0 - 3.0 * x + 1.5 * x.powi(2) - x.powi(3) / 6.0;
-    [lag0, lag1, lag2, lag3]
+    #[test]
+    fn test_exercise_boundary() {
+        let input = CalcInput {
+            underlying: 100.0,
+            strike: 100.0,
+            vol: 0.2,
+            zero_rate: 0.05,
+            term_annu: 1.0,
+        };
+        let rand_gen = rand_gen(7);
+        let path = std::env::temp_dir().join("lsm_exercise_boundary_test.csv");
+        let report = longstaff_schwartz_american_put(&input, 20, 50000, &rand_gen);
+        report.write_csv(path.to_str().unwrap()).unwrap();
+        // 行使境界は行使価格より下で、満期に向かって行使価格に近づく。
+        // (行使するパスが少ない最初の方の行使日は回帰の誤差が大きい)
+        let boundary: Vec<f64> = report.boundary.iter().map(|b| b.unwrap()).collect();
+        assert!(boundary[..19].iter().all(|b| *b > 70.0 && *b < 100.0));
+        assert!(boundary[10..20].windows(2).all(|w| w[0] < w[1]));
+        let total: f64 = report.exercise_probabilities.iter().sum();
+        assert!(total > 0.3 && total <= 1.0);
+        assert!(report.coefficients[19].is_none());
+        let csv = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(csv.lines().count(), 21);
+        assert!(csv.starts_with("time,boundary,exercise_probability,coeff0"));
+    }
 }
@@ -0,0 +1,489 @@
+use ndarray::{Array1, Array2};
+use ndarray_linalg::{Diag, Inverse, LeastSquaresSvd, SolveTriangular, QR, SVD, UPLO};
+
+// 最大の特異値に対する比がこれより小さい特異値は0とみなす(条件数がこの逆数を超えたら階数落ちとして扱う)
+const RANK_TOLERANCE: f64 = 1e-10;
+
+/* Longstaff-Schwartzの継続価値の回帰
+説明変数ごとに1変数の多項式 φ_0 = 1, φ_1, ..., φ_n を作り、多変数の基底関数は積 Π_i φ_{a_i}(x_i) とする。
+cross_termsがtrueなら次数の合計 Σ a_i が degree 以下の全ての積、falseなら定数項と説明変数ごとの φ_1, ..., φ_degree を使う。
+standardizeがtrueなら、基底関数を計算する前に説明変数を回帰に使うサンプルで標準化する(アフィン変換なので多項式の張る空間は変わらない)。
+  ラゲール: (x - 最小値) / 標準偏差(定義域[0, ∞)に入れ、広がりを1程度にする)
+  エルミート、単項式: (x - 平均) / 標準偏差
+  チェビシェフ: [最小値, 最大値] を [-1, 1] に写す
+スポット100程度の説明変数をそのまま単項式にすると計画行列の条件数は10^10程度となり、正規方程式ではその2乗で精度が落ちる。
+QR分解やSVDは計画行列を直接分解するため、条件数の2乗の影響を受けない。
+定数の説明変数、ITMのパスが全て同じ状態にある場合などは計画行列の階数が落ちる。
+条件数が 1 / RANK_TOLERANCE を超えたら、どの解き方でも X = QR の R を特異値分解し、
+小さな特異値を除いた擬似逆行列で最小ノルムの解を求める。 */
+
+/// 1変数の多項式の種類
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BasisType {
+    Laguerre,  // 重み付きラゲール多項式 e^{-x/2} L_n(x)
+    Hermite,   // エルミート多項式(確率論者の定義 He_n)
+    Monomial,  // 単項式 x^n
+    Chebyshev, // 第1種チェビシェフ多項式 T_n
+}
+
+/// 最小二乗法の解き方
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Solver {
+    NormalEquations, // (X^T X)^{-1} X^T y
+    Qr,
+    Svd,
+}
+
+/// 継続価値の回帰の設定です。
+#[derive(Debug, Copy, Clone)]
+pub struct Regression {
+    pub basis_type: BasisType,
+    pub degree: usize,
+    pub cross_terms: bool,
+    pub standardize: bool,
+    pub solver: Solver,
+}
+
+impl Default for Regression {
+    fn default() -> Self {
+        Regression {
+            basis_type: BasisType::Laguerre,
+            degree: 3,
+            cross_terms: true,
+            standardize: true,
+            solver: Solver::Qr,
+        }
+    }
+}
+
+/// 回帰の結果です。
+#[derive(Debug, Clone)]
+pub struct RegressionFit {
+    pub fitted: Vec<f64>,               // サンプルごとの回帰値
+    pub condition_number: Option<f64>,  // 計画行列の条件数(回帰しなかった場合はNone)
+    pub rank: Option<usize>, // 擬似逆行列で使った計画行列の階数(階数が落ちていなければNone)
+    pub model: Option<RegressionModel>, // サンプルがなければNone
+}
+
+/// 回帰で推定したモデルです。回帰に使わなかった説明変数に対しても回帰値を返します。
+#[derive(Debug, Clone)]
+pub struct RegressionModel {
+    regression: Regression,
+    indices: Vec<Vec<usize>>,
+    scales: Vec<(f64, f64)>,    // 説明変数ごとの標準化の (シフト, スケール)
+    pub coefficients: Vec<f64>, // 基底関数の係数(先頭が定数項)
+}
+
+impl RegressionModel {
+    /// 説明変数に対する回帰値を返します。
+    /// * `x` - 説明変数
+    pub fn predict(&self, x: &[f64]) -> f64 {
+        self.regression
+            .basis(x, &self.indices, &self.scales)
+            .iter()
+            .zip(self.coefficients.iter())
+            .map(|(b, c)| b * c)
+            .sum()
+    }
+}
+
+impl Regression {
+    /// 説明変数の数に対する基底関数の数を返します。
+    /// * `dim` - 説明変数の数
+    pub fn num_basis(&self, dim: usize) -> usize {
+        self.multi_indices(dim).len()
+    }
+
+    /// 定数を返すモデル(定数項の係数だけを持つ)を返します。
+    /// * `dim` - 説明変数の数
+    /// * `value` - 定数
+    pub fn constant_model(&self, dim: usize, value: f64) -> RegressionModel {
+        let indices = self.multi_indices(dim);
+        let mut coefficients = vec![0.0; indices.len()];
+        coefficients[0] = value;
+        RegressionModel {
+            regression: *self,
+            indices,
+            scales: vec![(0.0, 1.0); dim],
+            coefficients,
+        }
+    }
+
+    /// 最小二乗法で回帰し、サンプルごとの回帰値を返します。<br>
+    /// サンプル数が基底関数の数以下なら回帰せず平均を使います。
+    /// * `explanatory` - サンプルごとの説明変数
+    /// * `targets` - サンプルごとの被説明変数
+    pub fn fit(&self, explanatory: &[Vec<f64>], targets: &[f64]) -> RegressionFit {
+        let num = targets.len();
+        if num == 0 {
+            return RegressionFit {
+                fitted: Vec::new(),
+                condition_number: None,
+                rank: None,
+                model: None,
+            };
+        }
+        let dim = explanatory[0].len();
+        let indices = self.multi_indices(dim);
+        if num <= indices.len() {
+            let mean = targets.iter().sum::<f64>() / num as f64;
+            return RegressionFit {
+                fitted: vec![mean; num],
+                condition_number: None,
+                rank: None,
+                model: Some(self.constant_model(dim, mean)),
+            };
+        }
+        let scales: Vec<(f64, f64)> = (0..dim)
+            .map(|i| {
+                let column: Vec<f64> = explanatory.iter().map(|x| x[i]).collect();
+                self.scale(&column)
+            })
+            .collect();
+        let mut design: Array2<f64> = Array2::zeros((num, indices.len()));
+        for (row, x) in explanatory.iter().enumerate() {
+            for (col, value) in self.basis(x, &indices, &scales).into_iter().enumerate() {
+                design[[row, col]] = value;
+            }
+        }
+        let explained: Array1<f64> = Array1::from(targets.to_vec());
+        let is_full_rank = |cond: f64| cond.is_finite() && cond * RANK_TOLERANCE < 1.0;
+        let solution = match self.solver {
+            Solver::NormalEquations => {
+                let gram = design.t().dot(&design);
+                // cond(X^T X) = cond(X)^2
+                let (_, sigma, _) = gram.svd(false, false).unwrap();
+                let cond = condition(&sigma).sqrt();
+                match gram.inv() {
+                    Ok(inv) if is_full_rank(cond) => {
+                        Some((inv.dot(&design.t().dot(&explained)), cond))
+                    }
+                    _ => None,
+                }
+            }
+            Solver::Qr => {
+                let (q, r) = design.qr().unwrap();
+                let (_, sigma, _) = r.svd(false, false).unwrap();
+                let cond = condition(&sigma);
+                if is_full_rank(cond) {
+                    r.solve_triangular(UPLO::Upper, Diag::NonUnit, &q.t().dot(&explained))
+                        .ok()
+                        .map(|coeffs| (coeffs, cond))
+                } else {
+                    None
+                }
+            }
+            Solver::Svd => design.least_squares(&explained).ok().and_then(|result| {
+                let cond = condition(&result.singular_values);
+                if is_full_rank(cond) {
+                    Some((result.solution, cond))
+                } else {
+                    None
+                }
+            }),
+        };
+        let (reg_coeffs, condition_number, rank) = match solution {
+            Some((coeffs, cond)) => (coeffs, cond, None),
+            None => {
+                let (coeffs, cond, rank) = truncated_svd_solve(&design, &explained);
+                (coeffs, cond, Some(rank))
+            }
+        };
+        RegressionFit {
+            fitted: design.dot(&reg_coeffs).to_vec(),
+            condition_number: Some(condition_number),
+            rank,
+            model: Some(RegressionModel {
+                regression: *self,
+                indices,
+                scales,
+                coefficients: reg_coeffs.to_vec(),
+            }),
+        }
+    }
+
+    // 説明変数に対する基底関数の値
+    fn basis(&self, x: &[f64], indices: &[Vec<usize>], scales: &[(f64, f64)]) -> Vec<f64> {
+        let polys: Vec<Vec<f64>> = x
+            .iter()
+            .zip(scales.iter())
+            .map(|(xi, (shift, scale))| self.polynomials((xi - shift) / scale))
+            .collect();
+        indices
+            .iter()
+            .map(|index| index.iter().zip(polys.iter()).map(|(a, p)| p[*a]).product())
+            .collect()
+    }
+
+    // 定数項を先頭とする多重指数(説明変数ごとの多項式の次数)
+    fn multi_indices(&self, dim: usize) -> Vec<Vec<usize>> {
+        let mut indices: Vec<Vec<usize>> = vec![vec![0; dim]];
+        if self.cross_terms {
+            for total in 1..self.degree + 1 {
+                let mut index = vec![0; dim];
+                push_compositions(&mut indices, &mut index, 0, total);
+            }
+        } else {
+            for i in 0..dim {
+                for n in 1..self.degree + 1 {
+                    let mut index = vec![0; dim];
+                    index[i] = n;
+                    indices.push(index);
+                }
+            }
+        }
+        indices
+    }
+
+    // 標準化の (シフト, スケール)
+    fn scale(&self, column: &[f64]) -> (f64, f64) {
+        if !self.standardize {
+            return (0.0, 1.0);
+        }
+        let num = column.len() as f64;
+        let non_zero = |s: f64| if s > 0.0 { s } else { 1.0 };
+        match self.basis_type {
+            BasisType::Laguerre | BasisType::Hermite | BasisType::Monomial => {
+                let mean = column.iter().sum::<f64>() / num;
+                let var = column.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / num;
+                if self.basis_type == BasisType::Laguerre {
+                    let min = column.iter().cloned().fold(f64::INFINITY, f64::min);
+                    (min, non_zero(var.sqrt()))
+                } else {
+                    (mean, non_zero(var.sqrt()))
+                }
+            }
+            BasisType::Chebyshev => {
+                let min = column.iter().cloned().fold(f64::INFINITY, f64::min);
+                let max = column.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+                (0.5 * (max + min), non_zero(0.5 * (max - min)))
+            }
+        }
+    }
+
+    // 0〜degreeまでの1変数の多項式(0次は重みを付けず1とする)
+    fn polynomials(&self, x: f64) -> Vec<f64> {
+        let mut p = vec![1.0; self.degree + 1];
+        if self.degree == 0 {
+            return p;
+        }
+        match self.basis_type {
+            BasisType::Laguerre => {
+                // (n + 1) L_{n+1} = (2n + 1 - x) L_n - n L_{n-1}
+                p[1] = 1.0 - x;
+                for n in 1..self.degree {
+                    let n_f = n as f64;
+                    p[n + 1] = ((2.0 * n_f + 1.0 - x) * p[n] - n_f * p[n - 1]) / (n_f + 1.0);
+                }
+                let weight = (-0.5 * x).exp();
+                for value in p.iter_mut().skip(1) {
+                    *value *= weight;
+                }
+            }
+            BasisType::Hermite => {
+                // He_{n+1} = x He_n - n He_{n-1}
+                p[1] = x;
+                for n in 1..self.degree {
+                    p[n + 1] = x * p[n] - n as f64 * p[n - 1];
+                }
+            }
+            BasisType::Monomial => {
+                for n in 1..self.degree + 1 {
+                    p[n] = p[n - 1] * x;
+                }
+            }
+            BasisType::Chebyshev => {
+                // T_{n+1} = 2x T_n - T_{n-1}
+                p[1] = x;
+                for n in 1..self.degree {
+                    p[n + 1] = 2.0 * x * p[n] - p[n - 1];
+                }
+            }
+        }
+        p
+    }
+}
+
+// 次数の合計がtotalとなる多重指数を全て加える
+fn push_compositions(
+    indices: &mut Vec<Vec<usize>>,
+    index: &mut Vec<usize>,
+    i: usize,
+    total: usize,
+) {
+    if i == index.len() - 1 {
+        index[i] = total;
+        indices.push(index.clone());
+        return;
+    }
+    for n in (0..total + 1).rev() {
+        index[i] = n;
+        push_compositions(indices, index, i + 1, total - n);
+    }
+    index[i] = 0;
+}
+
+// X = QR の R の特異値分解から、RANK_TOLERANCE より小さな特異値を除いた擬似逆行列による
+// 最小ノルムの最小二乗解と (解, 条件数, 階数) を返す
+fn truncated_svd_solve(design: &Array2<f64>, explained: &Array1<f64>) -> (Array1<f64>, f64, usize) {
+    let (q, r) = design.qr().unwrap();
+    let (u, sigma, vt) = r.svd(true, true).unwrap();
+    let (u, vt) = (u.unwrap(), vt.unwrap());
+    let qty = q.t().dot(explained);
+    let max = sigma.iter().cloned().fold(0.0, f64::max);
+    let mut coeffs: Array1<f64> = Array1::zeros(r.ncols());
+    let mut rank = 0;
+    for (j, s) in sigma.iter().enumerate() {
+        if *s > RANK_TOLERANCE * max {
+            let weight = u.column(j).dot(&qty) / s;
+            coeffs.scaled_add(weight, &vt.row(j));
+            rank += 1;
+        }
+    }
+    (coeffs, condition(&sigma), rank)
+}
+
+// 特異値から求めた条件数
+fn condition(sigma: &Array1<f64>) -> f64 {
+    let max = sigma.iter().cloned().fold(0.0, f64::max);
+    let min = sigma.iter().cloned().fold(f64::INFINITY, f64::min);
+    max / min
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_fit_and_conditioning() {
+        // 3次式は多項式の基底(重み付きラゲールを除く)ならどの解き方でも厳密に再現される。
+        let explanatory: Vec<Vec<f64>> = (0..200)
+            .map(|i| vec![60.0 + 0.4 * i as f64, 0.5 + 0.1 * (i % 7) as f64])
+            .collect();
+        let targets: Vec<f64> = explanatory
+            .iter()
+            .map(|x| 2.0 - 0.5 * x[0] + 0.01 * x[0] * x[1] + 1e-4 * x[0].powi(3))
+            .collect();
+        for basis_type in [
+            BasisType::Hermite,
+            BasisType::Monomial,
+            BasisType::Chebyshev,
+        ] {
+            for solver in [Solver::NormalEquations, Solver::Qr, Solver::Svd] {
+                let regression = Regression {
+                    basis_type,
+                    solver,
+                    ..Regression::default()
+                };
+                let fit = regression.fit(&explanatory, &targets);
+                let model = fit.model.unwrap();
+                for ((fitted, target), x) in fit
+                    .fitted
+                    .iter()
+                    .zip(targets.iter())
+                    .zip(explanatory.iter())
+                {
+                    assert!((fitted - target).abs() < 1e-6);
+                    assert!((model.predict(x) - fitted).abs() < 1e-9);
+                }
+            }
+        }
+        assert_eq!(Regression::default().num_basis(2), 10);
+        let univariate = Regression {
+            cross_terms: false,
+            ..Regression::default()
+        };
+        assert_eq!(univariate.num_basis(2), 7);
+
+        // 標準化しない単項式の条件数は非常に大きく、標準化すると小さくなる。
+        let raw = Regression {
+            basis_type: BasisType::Monomial,
+            standardize: false,
+            ..Regression::default()
+        };
+        let raw_cond = raw.fit(&explanatory, &targets).condition_number.unwrap();
+        assert!(raw_cond > 1e7);
+        for basis_type in [
+            BasisType::Laguerre,
+            BasisType::Hermite,
+            BasisType::Chebyshev,
+        ] {
+            let regression = Regression {
+                basis_type,
+                ..Regression::default()
+            };
+            let cond = regression
+                .fit(&explanatory, &targets)
+                .condition_number
+                .unwrap();
+            assert!(cond < 1e4);
+        }
+        // 3つの解き方の条件数は一致する。
+        let conds: Vec<f64> = [Solver::NormalEquations, Solver::Qr, Solver::Svd]
+            .iter()
+            .map(|solver| {
+                let regression = Regression {
+                    solver: *solver,
+                    ..Regression::default()
+                };
+                regression
+                    .fit(&explanatory, &targets)
+                    .condition_number
+                    .unwrap()
+            })
+            .collect();
+        assert!((conds[0] / conds[2] - 1.0).abs() < 1e-4);
+        assert!((conds[1] / conds[2] - 1.0).abs() < 1e-8);
+    }
+    #[test]
+    fn test_rank_deficient_design() {
+        // 2つ目の説明変数が1つ目の定数倍、3つ目が定数なら計画行列の階数が落ちる。
+        // どの解き方でもpanicせず、擬似逆行列で1つ目の説明変数の3次式を再現する。
+        let explanatory: Vec<Vec<f64>> = (0..100)
+            .map(|i| {
+                let x = 80.0 + 0.4 * i as f64;
+                vec![x, 0.01 * x, 1.0]
+            })
+            .collect();
+        let targets: Vec<f64> = explanatory
+            .iter()
+            .map(|x| 5.0 - 0.1 * x[0] + 1e-4 * x[0].powi(3))
+            .collect();
+        for basis_type in [
+            BasisType::Laguerre,
+            BasisType::Hermite,
+            BasisType::Monomial,
+            BasisType::Chebyshev,
+        ] {
+            for solver in [Solver::NormalEquations, Solver::Qr, Solver::Svd] {
+                let regression = Regression {
+                    basis_type,
+                    solver,
+                    ..Regression::default()
+                };
+                let fit = regression.fit(&explanatory, &targets);
+                // 多項式の基底は1つ目の説明変数の3次以下の多項式が張る空間(次元4)に落ちる。
+                if basis_type == BasisType::Laguerre {
+                    assert!(fit.rank.unwrap() < regression.num_basis(3));
+                } else {
+                    assert_eq!(fit.rank, Some(4));
+                }
+                let model = fit.model.unwrap();
+                for ((fitted, target), x) in fit
+                    .fitted
+                    .iter()
+                    .zip(targets.iter())
+                    .zip(explanatory.iter())
+                {
+                    if basis_type != BasisType::Laguerre {
+                        assert!((fitted - target).abs() < 1e-6);
+                    }
+                    assert!((model.predict(x) - fitted).abs() < 1e-8);
+                }
+            }
+        }
+        // 階数が落ちていなければ擬似逆行列は使わない。
+        let full_rank: Vec<Vec<f64>> = explanatory.iter().map(|x| vec![x[0]]).collect();
+        assert_eq!(Regression::default().fit(&full_rank, &targets).rank, None);
+    }
+}
@@ -1,5 +1,6 @@
 use std::env;
 
+mod ad;
 mod bs;
 mod fdm;
 mod hull_white;
@@ -12,6 +13,7 @@ fn main() {
     let args: Vec<String> = env::args().collect();
     let module = args[1].as_str();
     match module {
+        "ad" => ad::run(),
         "bs" => bs::run(),
         "fdm" => fdm::run(),
         "hull_white" => hull_white::run(),
@@ -1,11 +1,50 @@
+pub mod autocallable;
+pub mod barrier;
+pub mod brownian_bridge;
+pub mod cliquet;
+pub mod engine;
+pub mod greeks;
+pub mod halton;
+pub mod mc_result;
+pub mod mlmc;
 mod monte_carlo;
-use monte_carlo::{mc_bs_asian_call, CalcInput};
-use std::time::Instant;
+pub mod multi_asset;
+pub mod path_generator;
+pub mod payoff;
+pub mod rand_num;
+pub mod sobol;
+pub mod stochastic_vol;
+#[cfg(test)]
+pub mod test_util;
+mod variance_reduction;
+pub mod variance_swap;
+use crate::bs::black_scholes::{self, implied_vol};
+use crate::bs::exotic::{barrier_option, forward_start_option, lookback_option};
+use crate::sabr::sabr_lognormal::sabr_lognormal;
+use autocallable::{mc_autocallable, Autocallable};
+use barrier::{Barrier, BarrierType, Lookback, LookbackStrike};
+use cliquet::{Cliquet, ForwardStart};
+use engine::mc_price;
+use greeks::{mc_greeks, GreekMethod};
+use mlmc::{mlmc_price, ContinuousAsian, MlmcConfig, Scheme};
+use monte_carlo::{
+    mc_bs_asian_call, mc_bs_asian_call_greeks, mc_bs_asian_call_mlmc, mc_bs_asian_call_rqmc,
+    mc_bs_asian_call_sensitivities, mc_bs_asian_call_until, CalcInput,
+};
+use multi_asset::{Basket, Himalaya, MultiGbm, Rainbow, RainbowType, Spread};
+use ndarray::arr2;
+use path_generator::{Gbm, Heston, LocalVol, MertonJump};
+use payoff::{Digital, European, FloatingLookback, OptionType};
+use rand_num::{InverseCdf, RandGen, RandType};
+use stochastic_vol::{heston_call, HestonQe, Sabr, SabrScheme};
+use variance_reduction::{mc_bs_asian_call_vr, VarianceReduction};
+use variance_swap::{VarianceSwap, VolatilitySwap};
 
-// mod rand_num;
+// 乱数のシード。同じシードであれば並列実行のスレッド数によらず同じ結果となる。
+const SEED: u64 = 1234;
 
 pub fn run() {
-    // rand_num::normal_rand();
+    rand_num::normal_rand(SEED);
     let input = CalcInput {
         underlying: 100.0,
         strike: 100.0,
@@ -13,9 +52,455 @@ pub fn run() {
         zero_rate: 0.05,
         term_annu: 1.0,
     };
-    let start = Instant::now();
-    let opt_price = mc_bs_asian_call(&input, 250, 10000);
-    let end = start.elapsed();
-    println!("(monte_carlo) time:{}s", end.as_secs_f64());
-    println!("(monte_carlo) mc_bs_asian_call: {}", opt_price);
+    let rand_gen = RandGen {
+        rand_type: RandType::Pseudo,
+        seed: SEED,
+        brownian_bridge: false,
+        inverse_cdf: InverseCdf::Moro,
+    };
+    let result = mc_bs_asian_call(&input, 250, 10000, &rand_gen);
+    println!("(monte_carlo) time:{}s", result.time_sec);
+    println!("(monte_carlo) mc_bs_asian_call: {:?}", result);
+    let greeks = mc_bs_asian_call_greeks(&input, 250, 10000, &rand_gen, GreekMethod::Pathwise);
+    println!("(monte_carlo) asian call delta: {:?}", greeks.delta);
+    println!("(monte_carlo) asian call gamma: {:?}", greeks.gamma);
+    println!("(monte_carlo) asian call vega: {:?}", greeks.vega);
+    // デジタルCallのGreeks(ペイオフが微分できないため、尤度比法か共通乱数による差分を使う)
+    let gbm = Gbm {
+        underlying: 100.0,
+        zero_rate: 0.05,
+        div_yield: 0.0,
+        vol: 0.2,
+    };
+    let digital = Digital {
+        option_type: OptionType::Call,
+        strike: 100.0,
+        maturity: 1.0,
+    };
+    for method in [GreekMethod::LikelihoodRatio, GreekMethod::BumpAndRevalue] {
+        let greeks = mc_greeks(&gbm, &digital, 1, 100000, &rand_gen, method);
+        println!(
+            "(monte_carlo) digital call {:?}: price {} delta {} gamma {} vega {}",
+            method, greeks.price.price, greeks.delta.price, greeks.gamma.price, greeks.vega.price
+        );
+    }
+    let (_, sensitivities) = mc_bs_asian_call_sensitivities(&input, 250, 10000, &rand_gen);
+    let sensitivities: Vec<f64> = sensitivities.iter().map(|s| s.price).collect();
+    println!(
+        "(monte_carlo) asian call dual [delta, vega, rho, dterm, dstrike]: {:?}",
+        sensitivities
+    );
+
+    // 標準誤差が0.01を下回るまでパスを追加する。
+    let result = mc_bs_asian_call_until(&input, 250, 0.01, 10000, 10000000, &rand_gen);
+    println!(
+        "(monte_carlo) mc_bs_asian_call_until: {} (95% confidence interval: {:?}, paths: {})",
+        result.price, result.conf_interval, result.num_path
+    );
+
+    // MLMCで連続観測のAsian Callを求める(RMSE 0.01)。
+    let mlmc_config = MlmcConfig {
+        scheme: Scheme::Milstein,
+        base_step: 4,
+        refinement: 2,
+        initial_path: 10000,
+        min_level: 2,
+        max_level: 10,
+    };
+    let result = mc_bs_asian_call_mlmc(&input, 0.01, &mlmc_config, &rand_gen);
+    println!("(monte_carlo) mc_bs_asian_call_mlmc: {:?}", result.result);
+    for (level, stat) in result.levels.iter().enumerate() {
+        println!(
+            "(monte_carlo) mlmc level:{} paths:{} mean:{} variance:{} cost:{}",
+            level, stat.num_path, stat.mean, stat.variance, stat.cost
+        );
+    }
+
+    // seedを変えた10回の推定値から標準誤差を求める。
+    for rand_type in [RandType::Pseudo, RandType::Sobol, RandType::Halton] {
+        let rand_gen = RandGen {
+            rand_type,
+            brownian_bridge: true,
+            ..rand_gen
+        };
+        // Sobol列の方向数は21次元までなので、観測日は20日とする。
+        let result = mc_bs_asian_call_rqmc(&input, 20, 1024, &rand_gen, 10);
+        println!(
+            "(monte_carlo) {:?} + brownian bridge: {} (std error: {})",
+            rand_type, result.price, result.std_error
+        );
+    }
+    // 一様乱数を正規乱数に変換する逆関数をWichura(AS241)にする。
+    let wichura_gen = RandGen {
+        rand_type: RandType::Sobol,
+        brownian_bridge: true,
+        inverse_cdf: InverseCdf::Wichura,
+        ..rand_gen
+    };
+    let result = mc_bs_asian_call_rqmc(&input, 20, 1024, &wichura_gen, 10);
+    println!(
+        "(monte_carlo) Sobol + brownian bridge (wichura): {} (std error: {})",
+        result.price, result.std_error
+    );
+
+    // 分散減少法の組み合わせ
+    let rand_gen = RandGen {
+        brownian_bridge: false,
+        ..rand_gen
+    };
+    let vr = VarianceReduction {
+        antithetic: true,
+        geometric_control: true,
+        stock_control: true,
+        moment_matching: false,
+        drift_shift: 0.0,
+    };
+    let result = mc_bs_asian_call_vr(&input, 250, 10000, &rand_gen, &vr);
+    println!(
+        "(monte_carlo) variance reduction: {} (std error: {}, plain std error: {}, variance reduction factor: {})",
+        result.result.price,
+        result.result.std_error,
+        result.plain_std_error,
+        result.variance_reduction_factor
+    );
+
+    // 共通のエンジンでモデルとペイオフを組み合わせる。
+    let heston = Heston {
+        underlying: 100.0,
+        zero_rate: 0.05,
+        div_yield: 0.0,
+        var0: 0.04,
+        kappa: 1.5,
+        theta: 0.04,
+        vol_of_var: 0.5,
+        corr: -0.7,
+    };
+    let lookback = FloatingLookback {
+        option_type: OptionType::Call,
+        observation_times: (0..53).map(|i| i as f64 / 52.0).collect(),
+    };
+    let result = mc_price(&heston, &lookback, 52, 100000, &rand_gen);
+    println!("(monte_carlo) heston weekly lookback call: {:?}", result);
+    // ジャンプのあるモデル(Merton)のEuropean Call
+    let merton = MertonJump {
+        underlying: 100.0,
+        zero_rate: 0.05,
+        div_yield: 0.0,
+        vol: 0.2,
+        intensity: 0.5,
+        jump_mean: -0.1,
+        jump_vol: 0.15,
+    };
+    let call = European {
+        option_type: OptionType::Call,
+        strike: 100.0,
+        maturity: 1.0,
+    };
+    let result = mc_price(&merton, &call, 52, 100000, &rand_gen);
+    println!(
+        "(monte_carlo) merton jump diffusion call: {} (std error: {})",
+        result.price, result.std_error
+    );
+
+    // 連続観測のダウン・アンド・アウトCallとルックバックをブラウン橋で補正する。
+    let gbm = Gbm {
+        underlying: 100.0,
+        zero_rate: 0.05,
+        div_yield: 0.0,
+        vol: 0.2,
+    };
+    let bs_input = black_scholes::CalcInput {
+        underlying: 100.0,
+        strike: 100.0,
+        vol: 0.2,
+        zero_rate: 0.05,
+        term_annu: 1.0,
+    };
+    for continuous in [false, true] {
+        let down_out = Barrier {
+            option_type: OptionType::Call,
+            barrier_type: BarrierType::DownOut,
+            strike: 100.0,
+            barrier: 90.0,
+            maturity: 1.0,
+            continuous,
+        };
+        let result = mc_price(&gbm, &down_out, 52, 100000, &rand_gen);
+        println!(
+            "(monte_carlo) down-and-out call (continuous: {}): {} (std error: {})",
+            continuous, result.price, result.std_error
+        );
+        let lookback = Lookback {
+            option_type: OptionType::Call,
+            strike: LookbackStrike::Floating,
+            maturity: 1.0,
+            continuous,
+        };
+        let result = mc_price(&gbm, &lookback, 52, 100000, &rand_gen);
+        println!(
+            "(monte_carlo) floating lookback call (continuous: {}): {} (std error: {})",
+            continuous, result.price, result.std_error
+        );
+    }
+    let down_out = Barrier {
+        option_type: OptionType::Call,
+        barrier_type: BarrierType::DownOut,
+        strike: 100.0,
+        barrier: 90.0,
+        maturity: 1.0,
+        continuous: true,
+    };
+    let result = mlmc_price(&gbm, &down_out, 0.02, &mlmc_config, &rand_gen);
+    println!("(monte_carlo) down-and-out call mlmc: {:?}", result.result);
+    println!(
+        "(monte_carlo) down-and-out call closed form: {}",
+        barrier_option(
+            &bs_input,
+            90.0,
+            BarrierType::DownOut,
+            black_scholes::OptionType::Call
+        )
+    );
+    println!(
+        "(monte_carlo) floating lookback call closed form: {}",
+        lookback_option(&bs_input, false, black_scholes::OptionType::Call)
+    );
+    // 4種類のバリアのCallと固定ストライクのルックバックCallを閉形式と比べる(連続観測)。
+    for (barrier_type, barrier) in [
+        (BarrierType::DownOut, 90.0),
+        (BarrierType::DownIn, 90.0),
+        (BarrierType::UpOut, 120.0),
+        (BarrierType::UpIn, 120.0),
+    ] {
+        let payoff = Barrier {
+            option_type: OptionType::Call,
+            barrier_type,
+            strike: 100.0,
+            barrier,
+            maturity: 1.0,
+            continuous: true,
+        };
+        let result = mc_price(&gbm, &payoff, 52, 100000, &rand_gen);
+        println!(
+            "(monte_carlo) {:?} call: {} (std error: {}, closed form: {})",
+            barrier_type,
+            result.price,
+            result.std_error,
+            barrier_option(
+                &bs_input,
+                barrier,
+                barrier_type,
+                black_scholes::OptionType::Call
+            )
+        );
+    }
+    let fixed_lookback = Lookback {
+        option_type: OptionType::Call,
+        strike: LookbackStrike::Fixed(100.0),
+        maturity: 1.0,
+        continuous: true,
+    };
+    let result = mc_price(&gbm, &fixed_lookback, 52, 100000, &rand_gen);
+    println!(
+        "(monte_carlo) fixed lookback call: {} (std error: {}, closed form: {})",
+        result.price,
+        result.std_error,
+        lookback_option(&bs_input, true, black_scholes::OptionType::Call)
+    );
+
+    // スキューのあるローカルボラティリティで、MLMCのEuler法とMilstein法のレベル数と計算量を比べる。
+    let local_vol = LocalVol {
+        underlying: 100.0,
+        zero_rate: 0.05,
+        div_yield: 0.0,
+        local_vol: Box::new(|s, _| 0.2 * (100.0 / s.max(1.0)).powf(0.5)),
+    };
+    let asian = ContinuousAsian {
+        option_type: OptionType::Call,
+        strike: 100.0,
+        maturity: 1.0,
+    };
+    for scheme in [Scheme::Euler, Scheme::Milstein] {
+        let config = MlmcConfig {
+            scheme,
+            ..mlmc_config
+        };
+        let result = mlmc_price(&local_vol, &asian, 0.02, &config, &rand_gen);
+        let cost: f64 = result
+            .levels
+            .iter()
+            .map(|l| l.num_path as f64 * l.cost)
+            .sum();
+        println!(
+            "(monte_carlo) local vol asian call mlmc ({:?}): {} (std error: {}, levels: {}, cost: {}, converged: {})",
+            scheme,
+            result.result.price,
+            result.result.std_error,
+            result.levels.len(),
+            cost,
+            result.converged
+        );
+    }
+
+    // 半年後に行使価格を決めるATMの前進開始Call
+    let forward_start = ForwardStart {
+        option_type: OptionType::Call,
+        start: 0.5,
+        maturity: 1.0,
+        moneyness: 1.0,
+    };
+    let result = mc_price(&gbm, &forward_start, 2, 100000, &rand_gen);
+    println!(
+        "(monte_carlo) forward start call: {} (std error: {}, closed form: {})",
+        result.price,
+        result.std_error,
+        forward_start_option(&bs_input, 0.5, 1.0, black_scholes::OptionType::Call)
+    );
+
+    // Hestonモデルの四半期リセットのクリケと日次観測のバリアンス・スワップ、ボラティリティ・スワップ
+    let cliquet = Cliquet {
+        reset_times: (0..5).map(|i| i as f64 * 0.25).collect(),
+        local_floor: 0.0,
+        local_cap: 0.05,
+        global_floor: 0.02,
+        global_cap: f64::INFINITY,
+    };
+    let result = mc_price(&heston, &cliquet, 52, 100000, &rand_gen);
+    println!("(monte_carlo) heston cliquet: {:?}", result);
+    let var_swap = VarianceSwap {
+        observation_times: (0..253).map(|i| i as f64 / 252.0).collect(),
+        strike: 0.0,
+    };
+    let result = mc_price(&heston, &var_swap, 252, 20000, &rand_gen);
+    println!(
+        "(monte_carlo) heston variance swap fair strike: {} (std error: {})",
+        result.price / (-heston.zero_rate * var_swap.observation_times[252]).exp(),
+        result.std_error
+    );
+    let vol_swap = VolatilitySwap {
+        observation_times: var_swap.observation_times.clone(),
+        strike: 0.0,
+    };
+    let result = mc_price(&heston, &vol_swap, 252, 20000, &rand_gen);
+    println!(
+        "(monte_carlo) heston volatility swap fair strike: {} (std error: {})",
+        result.price / (-heston.zero_rate * vol_swap.observation_times[252]).exp(),
+        result.std_error
+    );
+
+    // 3資産のWorst-of Put(相関行列は正定値でないため補正される)
+    let gbm = MultiGbm::new(
+        vec![100.0, 50.0, 200.0],
+        vec![0.02, 0.0, 0.01],
+        vec![0.2, 0.3, 0.25],
+        0.05,
+        &arr2(&[[1.0, 0.9, 0.7], [0.9, 1.0, 0.3], [0.7, 0.3, 1.0]]),
+    );
+    let worst_of = Rainbow {
+        rainbow_type: RainbowType::WorstOf,
+        option_type: OptionType::Put,
+        strike: 1.0,
+        maturity: 1.0,
+    };
+    let result = mc_price(&gbm, &worst_of, 1, 100000, &rand_gen);
+    println!("(monte_carlo) worst-of put: {:?}", result);
+    let best_of = Rainbow {
+        rainbow_type: RainbowType::BestOf,
+        option_type: OptionType::Call,
+        ..worst_of
+    };
+    let result = mc_price(&gbm, &best_of, 1, 100000, &rand_gen);
+    println!("(monte_carlo) best-of call: {:?}", result.price);
+    // 初期値が100となるウェイトのバスケットCall、1資産目と2資産目のスプレッドCall
+    let basket = Basket {
+        option_type: OptionType::Call,
+        strike: 100.0,
+        weights: vec![1.0 / 3.0, 2.0 / 3.0, 1.0 / 6.0],
+        maturity: 1.0,
+    };
+    let result = mc_price(&gbm, &basket, 1, 100000, &rand_gen);
+    println!("(monte_carlo) basket call: {:?}", result.price);
+    let spread = Spread {
+        option_type: OptionType::Call,
+        strike: 50.0,
+        maturity: 1.0,
+    };
+    let result = mc_price(&gbm, &spread, 1, 100000, &rand_gen);
+    println!("(monte_carlo) spread call: {:?}", result.price);
+    // 年1回観測の3年のHimalaya
+    let himalaya = Himalaya {
+        strike: 1.0,
+        observation_times: vec![1.0, 2.0, 3.0],
+    };
+    let result = mc_price(&gbm, &himalaya, 3, 100000, &rand_gen);
+    println!("(monte_carlo) himalaya call: {:?}", result.price);
+
+    // 3資産のWorst-of Phoenix(四半期観測、メモリー付きクーポン、満期観測のノックイン・プット)
+    let schedule: Vec<(f64, f64, f64)> = (1..9).map(|i| (i as f64 * 0.25, 1.0, 0.02)).collect();
+    let phoenix = Autocallable::new(&schedule, 0.7, true, 0.6, 1.0, 3);
+    let result = mc_autocallable(&gbm, &phoenix, 8, 100000, &rand_gen);
+    println!("(monte_carlo) worst-of phoenix: {:?}", result.price);
+    println!(
+        "(monte_carlo) worst-of phoenix redemption probabilities: {:?} (knock-in: {}, expected life: {})",
+        result.redemption_probabilities, result.knock_in_probability, result.expected_life
+    );
+
+    // HestonモデルのQEスキームとセミ解析解
+    let heston_qe = HestonQe {
+        underlying: 100.0,
+        zero_rate: 0.05,
+        div_yield: 0.0,
+        var0: 0.04,
+        kappa: 0.5,
+        theta: 0.04,
+        vol_of_var: 1.0,
+        corr: -0.9,
+        martingale_correction: true,
+    };
+    let call = European {
+        option_type: OptionType::Call,
+        strike: 100.0,
+        maturity: 5.0,
+    };
+    let result = mc_price(&heston_qe, &call, 20, 100000, &rand_gen);
+    println!("(monte_carlo) heston qe call: {:?}", result);
+    println!(
+        "(monte_carlo) heston semi-analytic call: {}",
+        heston_call(&heston_qe, 100.0, 5.0)
+    );
+
+    // SABRモデルの対数Euler法・低バイアススキームとHaganの近似式のインプライド・ボラティリティ
+    let (fwd, beta, alpha, rho, nu) = (0.04, 0.5, 0.04, -0.3, 0.4);
+    for (scheme, time_step) in [(SabrScheme::LogEuler, 50), (SabrScheme::LowBias, 10)] {
+        let sabr = Sabr {
+            fwd,
+            beta,
+            alpha,
+            rho,
+            nu,
+            scheme,
+        };
+        for strike in [0.03, 0.04, 0.05] {
+            let call = European {
+                option_type: OptionType::Call,
+                strike,
+                maturity: 1.0,
+            };
+            let result = mc_price(&sabr, &call, time_step, 100000, &rand_gen);
+            let input = black_scholes::CalcInput {
+                zero_rate: 0.0,
+                vol: 0.0,
+                term_annu: 1.0,
+                strike,
+                underlying: fwd,
+            };
+            println!(
+                "(monte_carlo) sabr {:?} strike:{} mc vol:{} hagan vol:{}",
+                scheme,
+                strike,
+                implied_vol(result.price, &input, black_scholes::OptionType::Call),
+                sabr_lognormal(strike, fwd, 1.0, beta, alpha, rho, nu)
+            );
+        }
+    }
 }
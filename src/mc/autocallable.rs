@@ -0,0 +1,276 @@
+use super::engine::{McEngine, Path};
+use super::mc_result::McResult;
+use super::path_generator::PathGenerator;
+use super::payoff::Payoff;
+use super::rand_num::RandGen;
+use rayon::prelude::*;
+use std::time::Instant;
+
+/* オートコーラブル債とPhoenix債(額面1)
+各観測日に参照資産のパフォーマンス(Worst-ofでは最小値)を観測する。
+クーポン: パフォーマンスがクーポンバリア以上ならその観測日のクーポンを支払う。memoryがtrueなら、支払われなかったクーポンを
+          次にクーポンバリアを超えた観測日にまとめて支払う。
+早期償還: 最後以外の観測日でパフォーマンスが早期償還バリア以上なら額面を支払って終了する。
+満期償還: パフォーマンスがノックイン・バリアを下回ると、額面から行使価格のPutの行使価値 max(K - パフォーマンス, 0) を差し引く。
+クーポンバリアを早期償還バリアと同じにしてmemoryをtrueとすると、償還時に累積クーポンを支払う通常のオートコーラブルとなる。 */
+
+// オートコーラブル債(パフォーマンスは状態変数の先頭num_underlying個の、時刻0に対する比の最小値)
+// 観測日、早期償還バリア、クーポンの数が揃うようにnewで作る
+#[derive(Debug, Clone)]
+pub struct Autocallable {
+    pub observation_times: Vec<f64>, // 観測日(最後が満期、支払日は観測日と同じ)
+    pub autocall_barriers: Vec<f64>, // 観測日ごとの早期償還バリア(満期の値は使わない)
+    pub coupon_barrier: f64,
+    pub coupons: Vec<f64>, // 観測日ごとのクーポン(額面に対する割合)
+    pub memory: bool,
+    pub knock_in_barrier: f64, // 満期に観測する
+    pub put_strike: f64,
+    pub num_underlying: usize, // 1ならシングル、2以上ならWorst-of
+}
+
+// 1本のパスの評価結果
+struct Redemption {
+    cashflows: Vec<(usize, f64)>,
+    observation: usize, // 償還した観測日
+    knocked_in: bool,
+}
+
+impl Autocallable {
+    /// 観測日ごとの(観測日, 早期償還バリア, クーポン)のスケジュールからオートコーラブル債を作ります。<br>
+    /// スケジュールが空の場合はpanicします。
+    /// * `schedule` - 観測日ごとの(観測日, 早期償還バリア, クーポン)。最後が満期で、満期の早期償還バリアは使わない
+    /// * `coupon_barrier` - クーポンバリア
+    /// * `memory` - 支払われなかったクーポンを後で支払うか
+    /// * `knock_in_barrier` - ノックイン・バリア(満期に観測する)
+    /// * `put_strike` - ノックインした場合のPutの行使価格
+    /// * `num_underlying` - 参照資産の数(2以上ならWorst-of)
+    pub fn new(
+        schedule: &[(f64, f64, f64)],
+        coupon_barrier: f64,
+        memory: bool,
+        knock_in_barrier: f64,
+        put_strike: f64,
+        num_underlying: usize,
+    ) -> Self {
+        if schedule.is_empty() {
+            panic!("観測日がありません");
+        }
+        Self {
+            observation_times: schedule.iter().map(|s| s.0).collect(),
+            autocall_barriers: schedule.iter().map(|s| s.1).collect(),
+            coupon_barrier,
+            coupons: schedule.iter().map(|s| s.2).collect(),
+            memory,
+            knock_in_barrier,
+            put_strike,
+            num_underlying,
+        }
+    }
+
+    // k番目の観測日のWorst-ofのパフォーマンス
+    fn performance(&self, path: &Path, k: usize) -> f64 {
+        path.fixing_state(k)[..self.num_underlying]
+            .iter()
+            .zip(path.states[0].iter())
+            .map(|(und, init)| und / init)
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    fn redemption(&self, path: &Path) -> Redemption {
+        let last = self.observation_times.len() - 1;
+        let mut cashflows: Vec<(usize, f64)> = Vec::new();
+        let mut unpaid = 0.0;
+        for k in 0..last + 1 {
+            let perf = self.performance(path, k);
+            if perf >= self.coupon_barrier {
+                cashflows.push((k, self.coupons[k] + unpaid));
+                unpaid = 0.0;
+            } else if self.memory {
+                unpaid += self.coupons[k];
+            }
+            if k < last && perf >= self.autocall_barriers[k] {
+                cashflows.push((k, 1.0));
+                return Redemption {
+                    cashflows,
+                    observation: k,
+                    knocked_in: false,
+                };
+            }
+            if k == last {
+                let knocked_in = perf < self.knock_in_barrier;
+                let loss = if knocked_in {
+                    (self.put_strike - perf).max(0.0)
+                } else {
+                    0.0
+                };
+                cashflows.push((k, 1.0 - loss));
+                return Redemption {
+                    cashflows,
+                    observation: k,
+                    knocked_in,
+                };
+            }
+        }
+        unreachable!()
+    }
+}
+
+impl Payoff for Autocallable {
+    fn fixing_times(&self) -> Vec<f64> {
+        self.observation_times.clone()
+    }
+
+    fn cashflows(&self, path: &Path) -> Vec<(usize, f64)> {
+        self.redemption(path).cashflows
+    }
+}
+
+/// オートコーラブル債の計算結果です。
+#[derive(Debug, Clone)]
+pub struct AutocallableResult {
+    pub price: McResult,
+    pub redemption_probabilities: Vec<f64>, // 観測日ごとの償還確率(最後は満期償還)
+    pub knock_in_probability: f64,
+    pub expected_life: f64, // 償還までの期待年数
+}
+
+/// オートコーラブル債の価格と、観測日ごとの償還確率を返します。
+/// * `generator` - パスの生成モデル
+/// * `note` - オートコーラブル債
+/// * `time_step` - 時間方向のステップ数(観測日を除く)
+/// * `num_path` - パス数
+/// * `rand_gen` - 乱数の設定
+pub fn mc_autocallable(
+    generator: &dyn PathGenerator,
+    note: &Autocallable,
+    time_step: usize,
+    num_path: usize,
+    rand_gen: &RandGen,
+) -> AutocallableResult {
+    let start = Instant::now();
+    let engine = McEngine::new(generator, &note.fixing_times(), time_step, rand_gen);
+    let results: Vec<(f64, usize, bool)> = (0..num_path)
+        .into_par_iter()
+        .map(|path_idx| {
+            let path = engine.path(path_idx);
+            let redemption = note.redemption(&path);
+            let value = redemption
+                .cashflows
+                .iter()
+                .map(|(k, amount)| amount * path.fixing_df(*k))
+                .sum();
+            (value, redemption.observation, redemption.knocked_in)
+        })
+        .collect();
+    let values: Vec<f64> = results.iter().map(|r| r.0).collect();
+    let mut counts = vec![0usize; note.observation_times.len()];
+    for (_, k, _) in results.iter() {
+        counts[*k] += 1;
+    }
+    let redemption_probabilities: Vec<f64> =
+        counts.iter().map(|c| *c as f64 / num_path as f64).collect();
+    let knock_in_probability = results.iter().filter(|r| r.2).count() as f64 / num_path as f64;
+    let expected_life = redemption_probabilities
+        .iter()
+        .zip(note.observation_times.iter())
+        .map(|(p, t)| p * t)
+        .sum();
+    AutocallableResult {
+        price: McResult::from_samples(&values, start),
+        redemption_probabilities,
+        knock_in_probability,
+        expected_life,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hull_white::math::std_normal_cdf;
+    use crate::mc::multi_asset::MultiGbm;
+    use crate::mc::test_util::{gbm, rand_gen};
+    use ndarray::arr2;
+
+    const SEED: u64 = 21;
+
+    fn note(autocall: f64, coupon_barrier: f64, knock_in: f64) -> Autocallable {
+        let schedule: Vec<(f64, f64, f64)> =
+            (1..5).map(|i| (i as f64 * 0.5, autocall, 0.03)).collect();
+        Autocallable::new(&schedule, coupon_barrier, true, knock_in, 1.0, 1)
+    }
+
+    #[test]
+    #[should_panic(expected = "観測日がありません")]
+    fn test_empty_schedule() {
+        Autocallable::new(&[], 0.7, true, 0.6, 1.0, 1);
+    }
+
+    #[test]
+    fn test_deterministic_limits() {
+        // 早期償還せず、クーポンは必ず支払われ、ノックインしなければ債券と同じ。
+        let bond = note(f64::INFINITY, 0.0, 0.0);
+        let result = mc_autocallable(&gbm(0.03, 0.0, 0.25), &bond, 4, 1000, &rand_gen(SEED));
+        let expected: f64 = bond
+            .observation_times
+            .iter()
+            .map(|t| 0.03 * (-0.03 * t).exp())
+            .sum::<f64>()
+            + (-0.03 * 2.0_f64).exp();
+        assert!((result.price.price - expected).abs() < 1e-12);
+        assert_eq!(result.redemption_probabilities, vec![0.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_knock_in_put_and_autocall_probability() {
+        let model = gbm(0.03, 0.0, 0.25);
+        let (vol, rate) = (model.vol, model.zero_rate);
+        // 早期償還・クーポンなし、ノックイン・バリアが行使価格と同じなら 額面の割引 - ATMのPut
+        let mut no_coupon = note(f64::INFINITY, f64::INFINITY, 1.0);
+        no_coupon.coupons = vec![0.0; 4];
+        let result = mc_autocallable(&model, &no_coupon, 4, 100000, &rand_gen(SEED));
+        let term = 2.0_f64;
+        let d1 = (rate + 0.5 * vol.powi(2)) * term / (vol * term.sqrt());
+        let d2 = d1 - vol * term.sqrt();
+        let put = (-rate * term).exp() * std_normal_cdf(-d2) - std_normal_cdf(-d1);
+        let expected = (-rate * term).exp() - put;
+        assert!((result.price.price - expected).abs() < 4.0 * result.price.std_error);
+        assert!((result.knock_in_probability - std_normal_cdf(-d2)).abs() < 0.01);
+
+        // 最初の観測日の早期償還確率は P(S(t_1) >= B S(0)) = N(d2)
+        let barrier = 1.05_f64;
+        let phoenix = note(barrier, 0.7, 0.6);
+        let result = mc_autocallable(&model, &phoenix, 4, 100000, &rand_gen(SEED));
+        let t1 = 0.5_f64;
+        let d2 = ((1.0 / barrier).ln() + (rate - 0.5 * vol.powi(2)) * t1) / (vol * t1.sqrt());
+        let p = std_normal_cdf(d2);
+        let binomial_error = (p * (1.0 - p) / 100000.0).sqrt();
+        assert!((result.redemption_probabilities[0] - p).abs() < 4.0 * binomial_error);
+        let total: f64 = result.redemption_probabilities.iter().sum();
+        assert!((total - 1.0).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_worst_of() {
+        // 完全相関で同じ資産ならWorst-ofは1資産と(相関行列の補正の誤差を除いて)一致し、相関が低いほど価格が下がる。
+        let worst_of = |corr: f64, num_underlying: usize| {
+            let model = MultiGbm::new(
+                vec![100.0, 100.0],
+                vec![0.0, 0.0],
+                vec![0.25, 0.25],
+                0.03,
+                &arr2(&[[1.0, corr], [corr, 1.0]]),
+            );
+            let note = Autocallable {
+                num_underlying,
+                ..note(1.0, 0.7, 0.6)
+            };
+            mc_autocallable(&model, &note, 4, 20000, &rand_gen(SEED))
+        };
+        let single = worst_of(1.0, 1);
+        let perfect = worst_of(1.0, 2);
+        assert!((perfect.price.price - single.price.price).abs() < 1e-3);
+        let low = worst_of(0.3, 2);
+        assert!(low.price.price < perfect.price.price - 4.0 * low.price.std_error);
+        assert!(low.knock_in_probability > perfect.knock_in_probability);
+    }
+}
@@ -0,0 +1,294 @@
+use super::engine::Path;
+use super::payoff::{intrinsic, OptionType, Payoff};
+pub use crate::bs::exotic::BarrierType;
+
+/* 連続観測のバリアオプションとルックバックオプション
+時間グリッドの各点だけで観測すると、点の間でバリアに触れたパスや最大値・最小値を見逃して価格が偏る。
+continuousがtrueのとき、各ステップで対数価格を両端で条件付けたブラウン橋とみなして補正する
+(ボラティリティはPathGenerator::log_volのステップ開始時の値)。
+バリア: ステップ内でバリアに触れる確率 p = exp(-2 ln(H/S_j) ln(H/S_{j+1}) / (σ^2 Δt)) から生存確率 Π(1 - p) を求め、
+        ペイオフに掛ける(ノックインは 1 - Π(1 - p))。乱数を使わない条件付き期待値のため分散も増えない。
+ルックバック: ステップ内の最大値・最小値を一様乱数Uから
+        ln M = (x_j + x_{j+1} + √((x_{j+1} - x_j)^2 - 2σ^2 Δt ln U)) / 2 (最小値は平方根の符号が逆)でサンプリングする。 */
+
+/// ブラウン橋でステップの両端がバリアの内側にあるときに、ステップ内でバリアに触れる確率を返します。
+/// * `start` - ステップの開始時点の値
+/// * `end` - ステップの終了時点の値
+/// * `barrier` - バリア
+/// * `variance` - ステップの対数の分散 σ^2 Δt
+pub fn crossing_probability(start: f64, end: f64, barrier: f64, variance: f64) -> f64 {
+    if variance <= 0.0 {
+        return 0.0;
+    }
+    (-2.0 * (barrier / start).ln() * (barrier / end).ln() / variance).exp()
+}
+
+/// ブラウン橋でステップ内の最大値(is_max = false なら最小値)をサンプリングします。
+/// * `start` - ステップの開始時点の値
+/// * `end` - ステップの終了時点の値
+/// * `variance` - ステップの対数の分散 σ^2 Δt
+/// * `uniform` - (0, 1]の一様乱数
+/// * `is_max` - 最大値ならtrue
+pub fn sample_extreme(start: f64, end: f64, variance: f64, uniform: f64, is_max: bool) -> f64 {
+    let (x0, x1) = (start.ln(), end.ln());
+    let root = ((x1 - x0).powi(2) - 2.0 * variance * uniform.ln()).sqrt();
+    let sign = if is_max { 1.0 } else { -1.0 };
+    (0.5 * (x0 + x1 + sign * root)).exp()
+}
+
+// ステップjの対数の分散(log_volがないモデルは0とし、補正しない)
+fn step_variance(path: &Path, j: usize) -> f64 {
+    let delta_t = path.times[j + 1] - path.times[j];
+    path.log_vols[j].map_or(0.0, |vol| vol.powi(2) * delta_t)
+}
+
+// バリアオプション(満期までの時間グリッドの全点で観測する)
+#[derive(Debug, Copy, Clone)]
+pub struct Barrier {
+    pub option_type: OptionType,
+    pub barrier_type: BarrierType,
+    pub strike: f64,
+    pub barrier: f64,
+    pub maturity: f64,
+    pub continuous: bool, // ブラウン橋で連続観測に補正する
+}
+
+impl Barrier {
+    /// バリアに触れずに満期まで生存する確率(離散観測では0か1)
+    /// * `path` - パス
+    pub fn survival_probability(&self, path: &Path) -> f64 {
+        let last = path.fixing_index(0);
+        let is_up = self.barrier_type.is_up();
+        let breached = |s: f64| {
+            if is_up {
+                s >= self.barrier
+            } else {
+                s <= self.barrier
+            }
+        };
+        let mut survival = 1.0;
+        for j in 0..last + 1 {
+            if breached(path.states[j][0]) {
+                return 0.0;
+            }
+            if self.continuous && j < last {
+                let p = crossing_probability(
+                    path.states[j][0],
+                    path.states[j + 1][0],
+                    self.barrier,
+                    step_variance(path, j),
+                );
+                survival *= 1.0 - p;
+            }
+        }
+        survival
+    }
+}
+
+impl Payoff for Barrier {
+    fn fixing_times(&self) -> Vec<f64> {
+        vec![self.maturity]
+    }
+
+    fn cashflows(&self, path: &Path) -> Vec<(usize, f64)> {
+        let survival = self.survival_probability(path);
+        let weight = if self.barrier_type.is_out() {
+            survival
+        } else {
+            1.0 - survival
+        };
+        vec![(
+            0,
+            weight * intrinsic(path.fixing(0), self.strike, self.option_type),
+        )]
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum LookbackStrike {
+    Floating,   // Call: S_T - min S, Put: max S - S_T
+    Fixed(f64), // Call: max(max S - K, 0), Put: max(K - min S, 0)
+}
+
+// ルックバックオプション(満期までの時間グリッドの全点で観測する)
+#[derive(Debug, Copy, Clone)]
+pub struct Lookback {
+    pub option_type: OptionType,
+    pub strike: LookbackStrike,
+    pub maturity: f64,
+    pub continuous: bool, // ブラウン橋で最大値・最小値をサンプリングする
+}
+
+impl Lookback {
+    /// 満期までの最大値(is_max = false なら最小値)
+    /// * `path` - パス
+    /// * `is_max` - 最大値ならtrue
+    pub fn extreme(&self, path: &Path, is_max: bool) -> f64 {
+        let last = path.fixing_index(0);
+        let better = |a: f64, b: f64| if is_max { a.max(b) } else { a.min(b) };
+        let mut extreme = path.states[0][0];
+        if !self.continuous {
+            return (1..last + 1).fold(path.states[0][0], |e, j| better(e, path.states[j][0]));
+        }
+        let uniforms = path.bridge_uniforms();
+        for (j, uniform) in uniforms.iter().enumerate().take(last) {
+            let (start, end) = (path.states[j][0], path.states[j + 1][0]);
+            let step_extreme = sample_extreme(start, end, step_variance(path, j), *uniform, is_max);
+            extreme = better(extreme, step_extreme);
+        }
+        extreme
+    }
+}
+
+impl Payoff for Lookback {
+    fn fixing_times(&self) -> Vec<f64> {
+        vec![self.maturity]
+    }
+
+    fn cashflows(&self, path: &Path) -> Vec<(usize, f64)> {
+        let spot = path.fixing(0);
+        let payoff = match (self.strike, self.option_type) {
+            (LookbackStrike::Floating, OptionType::Call) => spot - self.extreme(path, false),
+            (LookbackStrike::Floating, OptionType::Put) => self.extreme(path, true) - spot,
+            (LookbackStrike::Fixed(strike), OptionType::Call) => {
+                intrinsic(self.extreme(path, true), strike, OptionType::Call)
+            }
+            (LookbackStrike::Fixed(strike), OptionType::Put) => {
+                intrinsic(self.extreme(path, false), strike, OptionType::Put)
+            }
+        };
+        vec![(0, payoff)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bs::black_scholes::{self, CalcInput};
+    use crate::bs::exotic::{barrier_option, lookback_option};
+    use crate::mc::engine::mc_price;
+    use crate::mc::test_util::{gbm, rand_gen};
+
+    const SEED: u64 = 5;
+
+    const TIME_STEP: usize = 50;
+    const NUM_PATH: usize = 40000;
+
+    fn input(strike: f64) -> CalcInput {
+        CalcInput {
+            zero_rate: 0.05,
+            vol: 0.3,
+            term_annu: 1.0,
+            strike,
+            underlying: 100.0,
+        }
+    }
+
+    fn bs_type(option_type: OptionType) -> black_scholes::OptionType {
+        match option_type {
+            OptionType::Call => black_scholes::OptionType::Call,
+            OptionType::Put => black_scholes::OptionType::Put,
+        }
+    }
+
+    #[test]
+    fn test_barrier_against_closed_form() {
+        let (gbm, rand_gen) = (gbm(0.05, 0.0, 0.3), rand_gen(SEED));
+        let cases = [
+            (OptionType::Call, BarrierType::DownOut, 100.0, 90.0),
+            (OptionType::Call, BarrierType::UpOut, 100.0, 130.0),
+            (OptionType::Call, BarrierType::DownIn, 100.0, 90.0),
+            (OptionType::Call, BarrierType::UpIn, 100.0, 130.0),
+            (OptionType::Put, BarrierType::UpOut, 100.0, 115.0),
+            (OptionType::Put, BarrierType::DownOut, 100.0, 85.0),
+        ];
+        for (option_type, barrier_type, strike, barrier) in cases {
+            let expected =
+                barrier_option(&input(strike), barrier, barrier_type, bs_type(option_type));
+            let payoff = |continuous| Barrier {
+                option_type,
+                barrier_type,
+                strike,
+                barrier,
+                maturity: 1.0,
+                continuous,
+            };
+            let continuous = mc_price(&gbm, &payoff(true), TIME_STEP, NUM_PATH, &rand_gen);
+            let discrete = mc_price(&gbm, &payoff(false), TIME_STEP, NUM_PATH, &rand_gen);
+            assert!((continuous.price - expected).abs() < 4.0 * continuous.std_error);
+            // 離散観測ではバリアに触れたパスを見逃すため、ノックアウトは高く、ノックインは低くなる。
+            let bias = if barrier_type.is_out() {
+                discrete.price - expected
+            } else {
+                expected - discrete.price
+            };
+            assert!(bias > 4.0 * discrete.std_error);
+        }
+    }
+
+    #[test]
+    fn test_lookback_against_closed_form() {
+        let (gbm, rand_gen) = (gbm(0.05, 0.0, 0.3), rand_gen(SEED));
+        let cases = [
+            (OptionType::Call, LookbackStrike::Floating),
+            (OptionType::Put, LookbackStrike::Floating),
+            (OptionType::Call, LookbackStrike::Fixed(110.0)),
+            (OptionType::Put, LookbackStrike::Fixed(95.0)),
+        ];
+        for (option_type, strike) in cases {
+            let expected = match strike {
+                LookbackStrike::Floating => {
+                    lookback_option(&input(0.0), false, bs_type(option_type))
+                }
+                LookbackStrike::Fixed(k) => lookback_option(&input(k), true, bs_type(option_type)),
+            };
+            let payoff = |continuous| Lookback {
+                option_type,
+                strike,
+                maturity: 1.0,
+                continuous,
+            };
+            let continuous = mc_price(&gbm, &payoff(true), TIME_STEP, NUM_PATH, &rand_gen);
+            let discrete = mc_price(&gbm, &payoff(false), TIME_STEP, NUM_PATH, &rand_gen);
+            assert!((continuous.price - expected).abs() < 4.0 * continuous.std_error);
+            // 離散観測では最大値・最小値が内側に偏るため価格が低くなる。
+            assert!(expected - discrete.price > 4.0 * discrete.std_error);
+        }
+    }
+
+    #[test]
+    fn test_lookback_zero_rate() {
+        // 金利0では r -> 0 の極限の式を使い、小さな金利の価格と連続で、モンテカルロと一致する。
+        let (gbm, rand_gen) = (gbm(0.0, 0.0, 0.3), rand_gen(SEED));
+        let cases = [
+            (OptionType::Call, LookbackStrike::Floating),
+            (OptionType::Put, LookbackStrike::Floating),
+            (OptionType::Call, LookbackStrike::Fixed(110.0)),
+            (OptionType::Put, LookbackStrike::Fixed(95.0)),
+        ];
+        for (option_type, strike) in cases {
+            let (k, fixed_strike) = match strike {
+                LookbackStrike::Floating => (0.0, false),
+                LookbackStrike::Fixed(k) => (k, true),
+            };
+            let price = |zero_rate: f64| {
+                let input = CalcInput {
+                    zero_rate,
+                    ..input(k)
+                };
+                lookback_option(&input, fixed_strike, bs_type(option_type))
+            };
+            let expected = price(0.0);
+            assert!(expected.is_finite());
+            assert!((price(1e-6) - expected).abs() < 1e-4);
+            let payoff = Lookback {
+                option_type,
+                strike,
+                maturity: 1.0,
+                continuous: true,
+            };
+            let mc = mc_price(&gbm, &payoff, TIME_STEP, NUM_PATH, &rand_gen);
+            assert!((mc.price - expected).abs() < 4.0 * mc.std_error);
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::VecDeque;
+
+/* Brownian bridge
+最初の乱数で満期のW(T)を決め、以降は区間の中点を両端の値で条件付けて埋めていく。
+W(t_m) = ((t_r - t_m) W(t_l) + (t_m - t_l) W(t_r)) / (t_r - t_l) + √((t_m - t_l)(t_r - t_m) / (t_r - t_l)) Z
+パスの大まかな形が先頭の乱数で決まるため、低食い違い量列の先頭の(一様性の良い)次元が有効に使われる。 */
+
+#[derive(Clone, Debug)]
+struct BridgeStep {
+    target: usize,
+    left: Option<usize>, // Noneは時刻0(W = 0)
+    right: Option<usize>,
+    left_weight: f64,
+    right_weight: f64,
+    std_dev: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct BrownianBridge {
+    times: Vec<f64>,
+    steps: Vec<BridgeStep>, // 構築順
+}
+
+impl BrownianBridge {
+    /// 時間グリッドに対するBrownian bridgeを構築します。
+    /// * `times` - 時間グリッド(時刻0を含まない、昇順)
+    pub fn new(times: &[f64]) -> Self {
+        let num = times.len();
+        let mut steps: Vec<BridgeStep> = Vec::with_capacity(num);
+        if num > 0 {
+            steps.push(BridgeStep {
+                target: num - 1,
+                left: None,
+                right: None,
+                left_weight: 0.0,
+                right_weight: 0.0,
+                std_dev: times[num - 1].sqrt(),
+            });
+        }
+        // (左端, 右端)で未確定の区間を幅優先で分割する。
+        let mut intervals: VecDeque<(Option<usize>, usize)> = VecDeque::new();
+        if num > 1 {
+            intervals.push_back((None, num - 1));
+        }
+        while let Some((left, right)) = intervals.pop_front() {
+            let lower = left.map_or(0, |l| l + 1);
+            let mid = lower + (right - 1 - lower) / 2;
+            let t_left = left.map_or(0.0, |l| times[l]);
+            let (t_mid, t_right) = (times[mid], times[right]);
+            steps.push(BridgeStep {
+                target: mid,
+                left,
+                right: Some(right),
+                left_weight: (t_right - t_mid) / (t_right - t_left),
+                right_weight: (t_mid - t_left) / (t_right - t_left),
+                std_dev: ((t_mid - t_left) * (t_right - t_mid) / (t_right - t_left)).sqrt(),
+            });
+            if mid > lower {
+                intervals.push_back((left, mid));
+            }
+            if right > mid + 1 {
+                intervals.push_back((Some(mid), right));
+            }
+        }
+        Self {
+            times: times.to_vec(),
+            steps,
+        }
+    }
+
+    /// 構築順の標準正規乱数から、各期間のブラウン運動の増分を√Δtで割った標準正規乱数を返します。
+    /// * `normals` - 標準正規乱数(時間グリッドと同じ数)
+    pub fn transform(&self, normals: &[f64]) -> Vec<f64> {
+        let mut path = vec![0.0; self.times.len()];
+        for (step, z) in self.steps.iter().zip(normals.iter()) {
+            let left = step.left.map_or(0.0, |l| path[l]);
+            let right = step.right.map_or(0.0, |r| path[r]);
+            path[step.target] =
+                step.left_weight * left + step.right_weight * right + step.std_dev * z;
+        }
+        let mut prev = (0.0, 0.0); // (時刻, W)
+        path.iter()
+            .zip(self.times.iter())
+            .map(|(w, t)| {
+                let z = (w - prev.1) / (t - prev.0).sqrt();
+                prev = (*t, *w);
+                z
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_brownian_bridge() {
+        let times = [0.1, 0.25, 0.3, 0.5, 0.8, 0.9, 1.0];
+        let bridge = BrownianBridge::new(&times);
+        let mut targets: Vec<usize> = bridge.steps.iter().map(|step| step.target).collect();
+        targets.sort_unstable();
+        assert_eq!(targets, (0..times.len()).collect::<Vec<usize>>());
+
+        let normals = [0.3, -1.2, 0.7, 1.5, -0.4, 0.1, -2.0];
+        let increments = bridge.transform(&normals);
+        // 満期の値は先頭の乱数のみで決まる。
+        let w_end: f64 = increments
+            .iter()
+            .zip(times.iter())
+            .scan(0.0, |t_prev, (z, t)| {
+                let dw = z * (t - *t_prev).sqrt();
+                *t_prev = *t;
+                Some(dw)
+            })
+            .sum();
+        assert!((w_end - normals[0]).abs() < 1e-12);
+        // 独立な標準正規乱数から独立な標準正規乱数への線形変換(直交変換)のため、ノルムが保たれる。
+        let norm = |x: &[f64]| x.iter().map(|z| z * z).sum::<f64>();
+        assert!((norm(&increments) - norm(&normals)).abs() < 1e-12);
+    }
+}
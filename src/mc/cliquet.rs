@@ -0,0 +1,130 @@
+use super::engine::Path;
+use super::payoff::{intrinsic, OptionType, Payoff};
+
+// 前進開始オプション。時刻startに行使価格を moneyness * S(start) と決め、満期に支払う。
+#[derive(Debug, Copy, Clone)]
+pub struct ForwardStart {
+    pub option_type: OptionType,
+    pub start: f64,
+    pub maturity: f64,
+    pub moneyness: f64,
+}
+
+impl Payoff for ForwardStart {
+    fn fixing_times(&self) -> Vec<f64> {
+        vec![self.start, self.maturity]
+    }
+
+    fn cashflows(&self, path: &Path) -> Vec<(usize, f64)> {
+        let strike = self.moneyness * path.fixing(0);
+        vec![(1, intrinsic(path.fixing(1), strike, self.option_type))]
+    }
+}
+
+// クリケ(額面1)。リセット日の間のリターン R_i = S(t_i) / S(t_{i-1}) - 1 を
+// [local_floor, local_cap]で切り取って合計し、合計を[global_floor, global_cap]で切り取って最後のリセット日に支払う。
+// 上限・下限がない場合は±f64::INFINITYとする。
+#[derive(Debug, Clone)]
+pub struct Cliquet {
+    pub reset_times: Vec<f64>, // 最初の期間の開始日を含む
+    pub local_floor: f64,
+    pub local_cap: f64,
+    pub global_floor: f64,
+    pub global_cap: f64,
+}
+
+impl Payoff for Cliquet {
+    fn fixing_times(&self) -> Vec<f64> {
+        self.reset_times.clone()
+    }
+
+    fn cashflows(&self, path: &Path) -> Vec<(usize, f64)> {
+        let last = self.reset_times.len() - 1;
+        let sum: f64 = (1..last + 1)
+            .map(|i| {
+                let ret = path.fixing(i) / path.fixing(i - 1) - 1.0;
+                ret.max(self.local_floor).min(self.local_cap)
+            })
+            .sum();
+        vec![(last, sum.max(self.global_floor).min(self.global_cap))]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bs::black_scholes::{self, black_scholes, CalcInput};
+    use crate::bs::exotic::forward_start_option;
+    use crate::mc::engine::mc_price;
+    use crate::mc::test_util::{gbm, rand_gen};
+
+    const SEED: u64 = 13;
+
+    const RATE: f64 = 0.03;
+    const VOL: f64 = 0.2;
+
+    #[test]
+    fn test_forward_start_against_rubinstein() {
+        let input = CalcInput {
+            zero_rate: RATE,
+            vol: VOL,
+            term_annu: 1.5,
+            strike: 0.0,
+            underlying: 100.0,
+        };
+        for (option_type, bs_type, moneyness) in [
+            (OptionType::Call, black_scholes::OptionType::Call, 1.05),
+            (OptionType::Put, black_scholes::OptionType::Put, 0.9),
+        ] {
+            let payoff = ForwardStart {
+                option_type,
+                start: 0.5,
+                maturity: 1.5,
+                moneyness,
+            };
+            let result = mc_price(&gbm(RATE, 0.0, VOL), &payoff, 1, 100000, &rand_gen(SEED));
+            let expected = forward_start_option(&input, 0.5, moneyness, bs_type);
+            assert!((result.price - expected).abs() < 4.0 * result.std_error);
+        }
+    }
+
+    #[test]
+    fn test_cliquet() {
+        // 全体の上限・下限がなければ、各期間のリターンの切り取りは前進開始のCallスプレッドで表せる。
+        // E[min(max(R, f), c)] = f + e^{rΔ}(C(1 + f) - C(1 + c)) (Cは S = 1、期間Δ のCall)
+        let (floor, cap, period, num_period) = (-0.02, 0.03, 0.25, 8);
+        let reset_times: Vec<f64> = (0..num_period + 1).map(|i| i as f64 * period).collect();
+        let unit_call = |strike: f64| {
+            let input = CalcInput {
+                zero_rate: RATE,
+                vol: VOL,
+                term_annu: period,
+                strike,
+                underlying: 1.0,
+            };
+            black_scholes(&input, black_scholes::OptionType::Call)
+        };
+        let expected_return =
+            floor + (RATE * period).exp() * (unit_call(1.0 + floor) - unit_call(1.0 + cap));
+        let maturity = period * num_period as f64;
+        let expected = (-RATE * maturity).exp() * num_period as f64 * expected_return;
+        let local = Cliquet {
+            reset_times: reset_times.clone(),
+            local_floor: floor,
+            local_cap: cap,
+            global_floor: f64::NEG_INFINITY,
+            global_cap: f64::INFINITY,
+        };
+        let result = mc_price(&gbm(RATE, 0.0, VOL), &local, 1, 100000, &rand_gen(SEED));
+        assert!((result.price - expected).abs() < 4.0 * result.std_error);
+
+        // 全体の下限を付けると価格は上がる。
+        let global = Cliquet {
+            global_floor: 0.0,
+            ..local
+        };
+        let floored = mc_price(&gbm(RATE, 0.0, VOL), &global, 1, 100000, &rand_gen(SEED));
+        assert!(floored.price > result.price);
+        assert!(floored.price >= 0.0);
+    }
+}
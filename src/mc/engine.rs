@@ -0,0 +1,381 @@
+use super::mc_result::{run_until_std_error, McResult};
+use super::path_generator::PathGenerator;
+use super::payoff::Payoff;
+use super::rand_num::{stream_rng, NormalGen, RandGen, STREAM_SEED_MASK};
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+use rand_distr::{Distribution, StandardNormal};
+use rayon::prelude::*;
+use std::ops::Range;
+use std::time::Instant;
+
+// ジャンプなどに使う乱数のseedを正規乱数のseedと分けるためのマスク
+const AUX_SEED_MASK: u64 = 0x9E37_79B9_7F4A_7C15;
+
+// ブラウン橋の一様乱数のseedを分けるためのマスク
+const BRIDGE_SEED_MASK: u64 = 0xD1B5_4A32_D192_ED03;
+
+// 同一とみなす時刻の差
+const TIME_TOLERANCE: f64 = 1e-10;
+
+/// 1本のパスです。状態変数とディスカウントファクターを時間グリッドの各時点で保持します。
+pub struct Path<'a> {
+    pub times: &'a [f64],           // 時間グリッド(時刻0を含む)
+    pub states: Vec<Vec<f64>>,      // [時点][状態変数]
+    pub dfs: Vec<f64>,              // 時刻0から各時点までのディスカウントファクター
+    pub log_vols: Vec<Option<f64>>, // 各ステップのstate[0]の対数のボラティリティ(PathGenerator::log_vol)
+    fixing_idx: &'a [usize],        // 観測日の時間グリッド上のインデックス
+    bridge_seed: u64,
+    path_idx: usize,
+}
+
+impl Path<'_> {
+    /// k番目の観測日の観測値(state[0])
+    pub fn fixing(&self, k: usize) -> f64 {
+        self.states[self.fixing_idx[k]][0]
+    }
+
+    /// k番目の観測日の状態変数
+    pub fn fixing_state(&self, k: usize) -> &[f64] {
+        &self.states[self.fixing_idx[k]]
+    }
+
+    /// k番目の観測日のディスカウントファクター
+    pub fn fixing_df(&self, k: usize) -> f64 {
+        self.dfs[self.fixing_idx[k]]
+    }
+
+    /// k番目の観測日の時間グリッド上のインデックス
+    pub fn fixing_index(&self, k: usize) -> usize {
+        self.fixing_idx[k]
+    }
+
+    /// 各ステップのブラウン橋の最大値・最小値のサンプリングに使う(0, 1]の一様乱数を返します。<br>
+    /// パスごとに独立なストリームから生成するため、正規乱数やevolveの乱数とは独立です。
+    pub fn bridge_uniforms(&self) -> Vec<f64> {
+        let mut rng = stream_rng(self.bridge_seed, self.path_idx as u64);
+        (1..self.times.len())
+            .map(|_| 1.0 - rng.gen::<f64>())
+            .collect()
+    }
+}
+
+/// パスの生成モデルと観測日から時間グリッドを作り、パスを生成するエンジンです。
+pub struct McEngine<'a> {
+    generator: &'a dyn PathGenerator,
+    times: Vec<f64>,
+    fixing_idx: Vec<usize>,
+    normal_gen: NormalGen,
+    aux_seed: u64,
+    bridge_seed: u64,
+    inner_seed: u64,
+}
+
+impl<'a> McEngine<'a> {
+    /// 最後の観測日までをtime_step等分したグリッドに観測日を加えた時間グリッドでエンジンを作成します。
+    /// * `generator` - パスの生成モデル
+    /// * `fixing_times` - 観測日
+    /// * `time_step` - 時間方向のステップ数(観測日を除く、1以上)
+    /// * `rand_gen` - 乱数の設定
+    pub fn new(
+        generator: &'a dyn PathGenerator,
+        fixing_times: &[f64],
+        time_step: usize,
+        rand_gen: &RandGen,
+    ) -> Self {
+        assert!(time_step > 0, "time_stepは1以上である必要があります");
+        let end = fixing_times.iter().fold(0.0, |a: f64, b| a.max(*b));
+        let mut times: Vec<f64> = (0..time_step + 1)
+            .map(|i| end * i as f64 / time_step as f64)
+            .chain(fixing_times.iter().copied())
+            .collect();
+        times.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        times.dedup_by(|a, b| (*a - *b).abs() < TIME_TOLERANCE);
+        let fixing_idx: Vec<usize> = fixing_times
+            .iter()
+            .map(|t| {
+                times
+                    .iter()
+                    .position(|s| (s - t).abs() < TIME_TOLERANCE)
+                    .unwrap()
+            })
+            .collect();
+        let normal_gen = NormalGen::new(rand_gen, &times[1..], generator.num_factors());
+        Self {
+            generator,
+            times,
+            fixing_idx,
+            normal_gen,
+            aux_seed: rand_gen.seed ^ AUX_SEED_MASK,
+            bridge_seed: rand_gen.seed ^ BRIDGE_SEED_MASK,
+            inner_seed: rand_gen.seed ^ STREAM_SEED_MASK,
+        }
+    }
+
+    /// path_idx番目のパスを生成します。
+    /// * `path_idx` - パスのインデックス(乱数のストリーム番号)
+    pub fn path(&self, path_idx: usize) -> Path<'_> {
+        let normals = self.normal_gen.normals(path_idx);
+        let mut rng = stream_rng(self.aux_seed, path_idx as u64);
+        let state = self.generator.initial_state();
+        self.simulate(0, state, &normals, &mut rng, path_idx)
+    }
+
+    /// 時間グリッドのstart番目の時点の状態変数から、擬似乱数でパスを生成します(入れ子のシミュレーション用)。<br>
+    /// start番目より前の時点は開始時点の状態変数で埋め、ディスカウントファクターは開始時点からのものとします。
+    /// * `start` - 開始する時間グリッド上のインデックス
+    /// * `state` - 開始時点の状態変数
+    /// * `stream` - 乱数のストリーム番号
+    pub fn path_from(&self, start: usize, state: &[f64], stream: usize) -> Path<'_> {
+        let mut rng = stream_rng(self.inner_seed, stream as u64);
+        let num_normals = (self.times.len() - 1 - start) * self.generator.num_factors();
+        let normals: Vec<f64> = (0..num_normals)
+            .map(|_| StandardNormal.sample(&mut rng))
+            .collect();
+        self.simulate(start, state.to_vec(), &normals, &mut rng, stream)
+    }
+
+    // 時間グリッドのstart番目の時点から状態変数を発展させる
+    fn simulate(
+        &self,
+        start: usize,
+        mut state: Vec<f64>,
+        normals: &[f64],
+        rng: &mut ChaCha8Rng,
+        path_idx: usize,
+    ) -> Path<'_> {
+        let num_factors = self.generator.num_factors();
+        let mut states: Vec<Vec<f64>> = vec![state.clone(); start + 1];
+        let mut dfs: Vec<f64> = vec![1.0; start + 1];
+        let mut log_vols: Vec<Option<f64>> = vec![None; start];
+        states.reserve(self.times.len() - start - 1);
+        let mut integral = 0.0; // 短期金利の積分
+        for (step, window) in self.times[start..].windows(2).enumerate() {
+            let (time, delta_t) = (window[0], window[1] - window[0]);
+            let rate = self.generator.short_rate(&state, time);
+            log_vols.push(self.generator.log_vol(&state, time));
+            self.generator.evolve(
+                &mut state,
+                time,
+                delta_t,
+                &normals[step * num_factors..(step + 1) * num_factors],
+                rng,
+            );
+            integral += self
+                .generator
+                .step_rate_integral(&state)
+                .unwrap_or_else(|| {
+                    0.5 * (rate + self.generator.short_rate(&state, window[1])) * delta_t
+                });
+            states.push(state.clone());
+            dfs.push((-integral).exp());
+        }
+        Path {
+            times: &self.times,
+            states,
+            dfs,
+            log_vols,
+            fixing_idx: &self.fixing_idx,
+            bridge_seed: self.bridge_seed,
+            path_idx,
+        }
+    }
+
+    /// パスのインデックスの範囲に対する割引後のペイオフを返します。
+    /// * `payoff` - ペイオフ(観測日はエンジンの作成時と同じであること)
+    /// * `paths` - パスのインデックスの範囲
+    pub fn present_values(&self, payoff: &dyn Payoff, paths: Range<usize>) -> Vec<f64> {
+        paths
+            .into_par_iter()
+            .map(|path_idx| {
+                let path = self.path(path_idx);
+                payoff
+                    .cashflows(&path)
+                    .iter()
+                    .map(|(k, amount)| amount * path.fixing_df(*k))
+                    .sum()
+            })
+            .collect()
+    }
+}
+
+/// パスの生成モデルとペイオフから価格を返します。
+/// * `generator` - パスの生成モデル
+/// * `payoff` - ペイオフ
+/// * `time_step` - 時間方向のステップ数(観測日を除く)
+/// * `num_path` - パス数
+/// * `rand_gen` - 乱数の設定
+pub fn mc_price(
+    generator: &dyn PathGenerator,
+    payoff: &dyn Payoff,
+    time_step: usize,
+    num_path: usize,
+    rand_gen: &RandGen,
+) -> McResult {
+    let start = Instant::now();
+    let engine = McEngine::new(generator, &payoff.fixing_times(), time_step, rand_gen);
+    McResult::from_samples(&engine.present_values(payoff, 0..num_path), start)
+}
+
+/// 標準誤差が目標値を下回るまでパスを追加して、パスの生成モデルとペイオフから価格を返します。
+/// * `generator` - パスの生成モデル
+/// * `payoff` - ペイオフ
+/// * `time_step` - 時間方向のステップ数(観測日を除く)
+/// * `target_std_error` - 目標の標準誤差
+/// * `batch_size` - 1回に追加するパス数
+/// * `max_path` - パス数の上限
+/// * `rand_gen` - 乱数の設定
+pub fn mc_price_until(
+    generator: &dyn PathGenerator,
+    payoff: &dyn Payoff,
+    time_step: usize,
+    target_std_error: f64,
+    batch_size: usize,
+    max_path: usize,
+    rand_gen: &RandGen,
+) -> McResult {
+    let engine = McEngine::new(generator, &payoff.fixing_times(), time_step, rand_gen);
+    run_until_std_error(target_std_error, batch_size, max_path, |paths| {
+        engine.present_values(payoff, paths)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bs::black_scholes::{self, black_scholes};
+    use crate::hull_white::curve::Curve;
+    use crate::hull_white::hw_lib::HullWhite;
+    use crate::hull_white::simulation::HullWhiteSimulation;
+    use crate::mc::path_generator::{Gbm, Heston, LocalVol, MertonJump};
+    use crate::mc::payoff::{European, OptionType};
+    use crate::mc::test_util::sobol_rand_gen;
+
+    const SEED: u64 = 17;
+
+    fn bs_call(vol: f64) -> f64 {
+        let input = black_scholes::CalcInput {
+            zero_rate: 0.03,
+            vol,
+            term_annu: 1.0,
+            strike: 100.0,
+            underlying: 100.0,
+        };
+        black_scholes(&input, black_scholes::OptionType::Call)
+    }
+
+    #[test]
+    fn test_generators_against_bs() {
+        let call = European {
+            option_type: OptionType::Call,
+            strike: 100.0,
+            maturity: 1.0,
+        };
+        let gbm = Gbm {
+            underlying: 100.0,
+            zero_rate: 0.03,
+            div_yield: 0.0,
+            vol: 0.2,
+        };
+        let local_vol = LocalVol {
+            underlying: 100.0,
+            zero_rate: 0.03,
+            div_yield: 0.0,
+            local_vol: Box::new(|_, _| 0.2),
+        };
+        // 分散のボラティリティが0で分散が長期平均に等しければBlack-Scholesと一致する。
+        let heston = Heston {
+            underlying: 100.0,
+            zero_rate: 0.03,
+            div_yield: 0.0,
+            var0: 0.04,
+            kappa: 1.5,
+            theta: 0.04,
+            vol_of_var: 0.0,
+            corr: -0.7,
+        };
+        let generators: [&dyn PathGenerator; 3] = [&gbm, &local_vol, &heston];
+        for generator in generators {
+            let result = mc_price(generator, &call, 10, 16384, &sobol_rand_gen(SEED));
+            assert!((result.price - bs_call(0.2)).abs() < 3e-2);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "time_step")]
+    fn test_zero_time_step() {
+        let gbm = Gbm {
+            underlying: 100.0,
+            zero_rate: 0.03,
+            div_yield: 0.0,
+            vol: 0.2,
+        };
+        McEngine::new(&gbm, &[0.5, 1.0], 0, &sobol_rand_gen(SEED));
+    }
+
+    #[test]
+    fn test_merton_jump() {
+        let merton = MertonJump {
+            underlying: 100.0,
+            zero_rate: 0.03,
+            div_yield: 0.0,
+            vol: 0.2,
+            intensity: 0.5,
+            jump_mean: -0.1,
+            jump_vol: 0.15,
+        };
+        let call = European {
+            option_type: OptionType::Call,
+            strike: 100.0,
+            maturity: 1.0,
+        };
+        // Merton(1976)の級数解 Σ e^{-λ'T}(λ'T)^n / n! BS(σ_n, r_n)
+        let k = (merton.jump_mean + 0.5 * merton.jump_vol.powi(2)).exp() - 1.0;
+        let lambda = merton.intensity * (1.0 + k);
+        let mut analytic = 0.0;
+        let mut weight = (-lambda).exp();
+        for n in 0..50 {
+            let n_f = n as f64;
+            let input = black_scholes::CalcInput {
+                zero_rate: 0.03 - merton.intensity * k + n_f * (1.0 + k).ln(),
+                vol: (0.04 + n_f * merton.jump_vol.powi(2)).sqrt(),
+                term_annu: 1.0,
+                strike: 100.0,
+                underlying: 100.0,
+            };
+            analytic += weight * black_scholes(&input, black_scholes::OptionType::Call);
+            weight *= lambda / (n_f + 1.0);
+        }
+        let result = mc_price(&merton, &call, 20, 50000, &sobol_rand_gen(SEED));
+        assert!((result.price - analytic).abs() < 4.0 * result.std_error);
+    }
+
+    #[test]
+    fn test_hull_white_discount() {
+        let rand_gen = sobol_rand_gen(SEED);
+        // 割引債の価格は初期カーブのディスカウントファクターに一致する。
+        let hw = HullWhite::new(vec![0.1], vec![0.0], vec![0.01], vec![0.0]);
+        let simulation = HullWhiteSimulation::new(hw, Curve::Ois);
+        struct ZeroCouponBond {
+            maturity: f64,
+        }
+        impl Payoff for ZeroCouponBond {
+            fn fixing_times(&self) -> Vec<f64> {
+                vec![self.maturity]
+            }
+            fn cashflows(&self, _: &Path) -> Vec<(usize, f64)> {
+                vec![(0, 1.0)]
+            }
+        }
+        // 短期金利と2つ目のファクターで積分をサンプリングするため、10ステップ × 2ファクターのSobol列とする。
+        let maturity = 5.0;
+        let result = mc_price(
+            &simulation,
+            &ZeroCouponBond { maturity },
+            10,
+            8192,
+            &rand_gen,
+        );
+        assert!((result.price - simulation.initial_df(maturity)).abs() < 1e-3);
+    }
+}
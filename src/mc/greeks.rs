@@ -0,0 +1,272 @@
+use super::engine::McEngine;
+use super::mc_result::McResult;
+use super::path_generator::Gbm;
+use super::payoff::Payoff;
+use super::rand_num::RandGen;
+use rayon::prelude::*;
+use std::time::Instant;
+
+// Bump and Revalueの原資産価格のバンプ幅(相対)
+const SPOT_BUMP: f64 = 0.01;
+
+// Bump and Revalueのボラティリティのバンプ幅
+const VOL_BUMP: f64 = 0.01;
+
+/* 幾何ブラウン運動のもとでのGreeksの推定
+Pathwise: S_k = S_0 exp((r - q - σ^2/2)t_k + σW_k) より ∂S_k/∂S_0 = S_k / S_0, ∂S_k/∂σ = S_k (W_k - σt_k)。
+          ペイオフの観測値に関する微分(Payoff::cashflow_gradients)と掛け合わせる。
+          Gammaは最初のステップの尤度比とPathwiseのDeltaを組み合わせた推定量 E[D (Z_1 / (S_0 σ √t_1) - 1 / S_0)]
+Likelihood Ratio: パスの密度の対数微分を重みとしてペイオフに掛ける。ペイオフの微分が不要でデジタルやバリアにも使えるが、
+          S_0に関する重みは最初のステップだけに依存するため、ステップが細かいと分散が大きい。
+Bump and Revalue: 同じ乱数(CRN)で原資産価格とボラティリティをバンプして中心差分をとる。 */
+
+#[derive(Debug, Copy, Clone)]
+pub enum GreekMethod {
+    Pathwise,        // ペイオフが微分できなければLikelihoodRatioを使う
+    LikelihoodRatio, // 尤度比法
+    BumpAndRevalue,  // 共通乱数による差分
+}
+
+/// 価格とGreeksのモンテカルロの計算結果です。
+#[derive(Debug, Copy, Clone)]
+pub struct McGreeks {
+    pub price: McResult,
+    pub delta: McResult,
+    pub gamma: McResult,
+    pub vega: McResult,
+}
+
+/// 幾何ブラウン運動のもとで価格とDelta, Gamma, Vegaを返します。
+/// * `gbm` - 幾何ブラウン運動
+/// * `payoff` - ペイオフ
+/// * `time_step` - 時間方向のステップ数(観測日を除く)
+/// * `num_path` - パス数
+/// * `rand_gen` - 乱数の設定
+/// * `method` - Greeksの推定方法
+pub fn mc_greeks(
+    gbm: &Gbm,
+    payoff: &dyn Payoff,
+    time_step: usize,
+    num_path: usize,
+    rand_gen: &RandGen,
+    method: GreekMethod,
+) -> McGreeks {
+    match method {
+        GreekMethod::BumpAndRevalue => bump_and_revalue(gbm, payoff, time_step, num_path, rand_gen),
+        _ => {
+            let start = Instant::now();
+            let engine = McEngine::new(gbm, &payoff.fixing_times(), time_step, rand_gen);
+            // 各パスの[価格, Delta, Gamma, Vega]
+            let samples: Vec<[f64; 4]> = (0..num_path)
+                .into_par_iter()
+                .map(|path_idx| path_greeks(gbm, payoff, &engine, path_idx, method))
+                .collect();
+            let result = |i: usize| {
+                let values: Vec<f64> = samples.iter().map(|s| s[i]).collect();
+                McResult::from_samples(&values, start)
+            };
+            McGreeks {
+                price: result(0),
+                delta: result(1),
+                gamma: result(2),
+                vega: result(3),
+            }
+        }
+    }
+}
+
+// 1本のパスの[価格, Delta, Gamma, Vega]の推定値
+fn path_greeks(
+    gbm: &Gbm,
+    payoff: &dyn Payoff,
+    engine: &McEngine,
+    path_idx: usize,
+    method: GreekMethod,
+) -> [f64; 4] {
+    let Gbm {
+        underlying,
+        zero_rate,
+        div_yield,
+        vol,
+    } = *gbm;
+    let path = engine.path(path_idx);
+    let times = path.times;
+    let value: f64 = payoff
+        .cashflows(&path)
+        .iter()
+        .map(|(k, amount)| amount * path.fixing_df(*k))
+        .sum();
+    // 時間グリッドの各ステップの標準正規乱数 Z_j
+    let drift = zero_rate - div_yield - 0.5 * vol.powi(2);
+    let normals: Vec<f64> = (1..times.len())
+        .map(|j| {
+            let delta_t = times[j] - times[j - 1];
+            ((path.states[j][0] / path.states[j - 1][0]).ln() - drift * delta_t)
+                / (vol * delta_t.sqrt())
+        })
+        .collect();
+    let first_score = normals[0] / (underlying * vol * times[1].sqrt());
+
+    let gradients = match method {
+        GreekMethod::Pathwise => payoff.cashflow_gradients(&path),
+        _ => None,
+    };
+    match gradients {
+        Some(gradients) => {
+            let (mut delta, mut vega) = (0.0, 0.0);
+            for (k, gradient) in gradients.iter() {
+                let df = path.fixing_df(*k);
+                for (j, slope) in gradient.iter().enumerate() {
+                    let spot = path.fixing(j);
+                    let time = times[path.fixing_index(j)];
+                    delta += df * slope * spot / underlying;
+                    vega += df
+                        * slope
+                        * spot
+                        * ((spot / underlying).ln() - (drift + vol.powi(2)) * time)
+                        / vol;
+                }
+            }
+            let gamma = delta * (first_score - 1.0 / underlying);
+            [value, delta, gamma, vega]
+        }
+        None => {
+            let z1 = normals[0];
+            let t1 = times[1];
+            let delta = value * first_score;
+            let gamma = value
+                * ((z1.powi(2) - 1.0) / (underlying * vol).powi(2) / t1
+                    - z1 / (underlying.powi(2) * vol * t1.sqrt()));
+            let vega_score: f64 = normals
+                .iter()
+                .zip(times.windows(2))
+                .map(|(z, w)| (z.powi(2) - 1.0) / vol - z * (w[1] - w[0]).sqrt())
+                .sum();
+            [value, delta, gamma, value * vega_score]
+        }
+    }
+}
+
+// 同じ乱数で原資産価格とボラティリティをバンプした価格の中心差分
+fn bump_and_revalue(
+    gbm: &Gbm,
+    payoff: &dyn Payoff,
+    time_step: usize,
+    num_path: usize,
+    rand_gen: &RandGen,
+) -> McGreeks {
+    let start = Instant::now();
+    let fixing_times = payoff.fixing_times();
+    let values = |model: Gbm| -> Vec<f64> {
+        McEngine::new(&model, &fixing_times, time_step, rand_gen)
+            .present_values(payoff, 0..num_path)
+    };
+    let spot_bump = SPOT_BUMP * gbm.underlying;
+    let base = values(*gbm);
+    let up = values(Gbm {
+        underlying: gbm.underlying + spot_bump,
+        ..*gbm
+    });
+    let down = values(Gbm {
+        underlying: gbm.underlying - spot_bump,
+        ..*gbm
+    });
+    let vol_up = values(Gbm {
+        vol: gbm.vol + VOL_BUMP,
+        ..*gbm
+    });
+    let vol_down = values(Gbm {
+        vol: gbm.vol - VOL_BUMP,
+        ..*gbm
+    });
+    let difference = |f: &dyn Fn(usize) -> f64| -> McResult {
+        let samples: Vec<f64> = (0..num_path).map(f).collect();
+        McResult::from_samples(&samples, start)
+    };
+    McGreeks {
+        price: McResult::from_samples(&base, start),
+        delta: difference(&|i| (up[i] - down[i]) / (2.0 * spot_bump)),
+        gamma: difference(&|i| (up[i] - 2.0 * base[i] + down[i]) / spot_bump.powi(2)),
+        vega: difference(&|i| (vol_up[i] - vol_down[i]) / (2.0 * VOL_BUMP)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hull_white::math::std_normal_cdf;
+    use crate::mc::payoff::{Digital, European, OptionType};
+    use crate::mc::test_util::{gbm, rand_gen};
+    use std::f64::consts::PI;
+
+    const SEED: u64 = 11;
+
+    // Black-Scholesの (d1, d2)
+    fn d1_d2(strike: f64, term: f64) -> (f64, f64) {
+        let model = gbm(0.03, 0.01, 0.25);
+        let d1 = ((model.underlying / strike).ln()
+            + (model.zero_rate - model.div_yield + 0.5 * model.vol.powi(2)) * term)
+            / (model.vol * term.sqrt());
+        (d1, d1 - model.vol * term.sqrt())
+    }
+
+    fn normal_pdf(x: f64) -> f64 {
+        (-0.5 * x * x).exp() / (2.0 * PI).sqrt()
+    }
+
+    fn assert_close(result: &McResult, expected: f64) {
+        assert!((result.price - expected).abs() < 4.0 * result.std_error + 1e-3 * expected.abs());
+    }
+
+    #[test]
+    fn test_european_call_greeks() {
+        let (strike, term) = (105.0, 1.0);
+        let model = gbm(0.03, 0.01, 0.25);
+        let (d1, _) = d1_d2(strike, term);
+        let q_df = (-model.div_yield * term).exp();
+        let delta = q_df * std_normal_cdf(d1);
+        let gamma = q_df * normal_pdf(d1) / (model.underlying * model.vol * term.sqrt());
+        let vega = q_df * model.underlying * normal_pdf(d1) * term.sqrt();
+        let call = European {
+            option_type: OptionType::Call,
+            strike,
+            maturity: term,
+        };
+        let methods = [
+            GreekMethod::Pathwise,
+            GreekMethod::LikelihoodRatio,
+            GreekMethod::BumpAndRevalue,
+        ];
+        for method in methods {
+            let greeks = mc_greeks(&model, &call, 1, 200000, &rand_gen(SEED), method);
+            assert_close(&greeks.delta, delta);
+            assert_close(&greeks.gamma, gamma);
+            assert_close(&greeks.vega, vega);
+        }
+    }
+
+    #[test]
+    fn test_digital_greeks() {
+        // デジタルはパスワイズ微分ができないため尤度比法を使う。
+        let (strike, term) = (100.0, 0.5);
+        let model = gbm(0.03, 0.01, 0.25);
+        let (_, d2) = d1_d2(strike, term);
+        let r_df = (-model.zero_rate * term).exp();
+        let delta = r_df * normal_pdf(d2) / (model.underlying * model.vol * term.sqrt());
+        let digital = Digital {
+            option_type: OptionType::Call,
+            strike,
+            maturity: term,
+        };
+        let greeks = mc_greeks(
+            &model,
+            &digital,
+            1,
+            200000,
+            &rand_gen(SEED),
+            GreekMethod::Pathwise,
+        );
+        assert_close(&greeks.price, r_df * std_normal_cdf(d2));
+        assert_close(&greeks.delta, delta);
+    }
+}
@@ -0,0 +1,90 @@
+use rand::Rng;
+
+/* Halton列
+d次元目はd番目の素数pを基数とするradical inverse φ_p(n) = Σ a_k p^{-k-1} (n = Σ a_k p^k) とする。
+高次元では次元間の相関が強くなるため、Brownian bridgeと組み合わせて先頭の次元に重要な変数を割り当てる。 */
+
+#[derive(Clone, Debug)]
+pub struct Halton {
+    primes: Vec<u64>,
+    shift: Vec<f64>, // random shift(Cranley and Patterson)。ランダム化しない場合は0
+}
+
+impl Halton {
+    /// Halton列を生成します。
+    /// * `dim` - 次元
+    pub fn new(dim: usize) -> Self {
+        Self {
+            primes: primes(dim),
+            shift: vec![0.0; dim],
+        }
+    }
+
+    /// 各次元に一様乱数を足して1の剰余をとるrandom shiftでランダム化します。
+    /// * `rng` - 乱数生成器
+    pub fn randomize<R: Rng>(&mut self, rng: &mut R) {
+        for shift in self.shift.iter_mut() {
+            *shift = rng.gen();
+        }
+    }
+
+    /// index番目の点を[0, 1)の値で返します。
+    /// * `index` - 点のインデックス(0は原点)
+    pub fn point(&self, index: usize) -> Vec<f64> {
+        self.primes
+            .iter()
+            .zip(self.shift.iter())
+            .map(|(base, shift)| {
+                let x = radical_inverse(index as u64, *base) + shift;
+                x - x.floor()
+            })
+            .collect()
+    }
+}
+
+// 基数baseでのradical inverse
+fn radical_inverse(mut index: u64, base: u64) -> f64 {
+    let inv_base = 1.0 / base as f64;
+    let mut factor = inv_base;
+    let mut result = 0.0;
+    while index > 0 {
+        result += (index % base) as f64 * factor;
+        index /= base;
+        factor *= inv_base;
+    }
+    result
+}
+
+// 小さい方からnum個の素数
+fn primes(num: usize) -> Vec<u64> {
+    let mut primes: Vec<u64> = Vec::with_capacity(num);
+    let mut candidate = 2;
+    while primes.len() < num {
+        if primes
+            .iter()
+            .take_while(|p| *p * *p <= candidate)
+            .all(|p| candidate % p != 0)
+        {
+            primes.push(candidate);
+        }
+        candidate += 1;
+    }
+    primes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_halton_points() {
+        let halton = Halton::new(3);
+        let expected = [(1, [0.5, 1.0 / 3.0, 0.2]), (6, [0.375, 2.0 / 9.0, 0.24])];
+        for (index, point) in expected {
+            for (actual, expected) in halton.point(index).iter().zip(point.iter()) {
+                assert!((actual - expected).abs() < 1e-15);
+            }
+        }
+        assert_eq!(primes(6), vec![2, 3, 5, 7, 11, 13]);
+    }
+}
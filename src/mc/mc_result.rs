@@ -0,0 +1,142 @@
+use std::ops::Range;
+use std::time::Instant;
+
+// 95%信頼区間の標準正規分布のクォンタイル
+const QUANTILE_95: f64 = 1.959963984540054;
+
+/// モンテカルロの計算結果です。
+#[derive(Debug, Copy, Clone)]
+pub struct McResult {
+    pub price: f64,
+    pub std_error: f64,
+    pub conf_interval: (f64, f64), // 95%信頼区間(正規近似)
+    pub num_path: usize,
+    pub time_sec: f64, // 計算時間(秒)
+}
+
+impl McResult {
+    /// 推定値と標準誤差から計算結果を作成します。
+    /// * `price` - 推定値
+    /// * `std_error` - 標準誤差
+    /// * `num_path` - 使用したパス数
+    /// * `start` - 計算の開始時刻
+    pub fn new(price: f64, std_error: f64, num_path: usize, start: Instant) -> Self {
+        Self {
+            price,
+            std_error,
+            conf_interval: (
+                price - QUANTILE_95 * std_error,
+                price + QUANTILE_95 * std_error,
+            ),
+            num_path,
+            time_sec: start.elapsed().as_secs_f64(),
+        }
+    }
+
+    /// パスごとの(割引後の)ペイオフから計算結果を作成します。
+    /// * `samples` - パスごとのペイオフ
+    /// * `start` - 計算の開始時刻
+    pub fn from_samples(samples: &[f64], start: Instant) -> Self {
+        let (price, std_error) = mean_std_error(samples);
+        Self::new(price, std_error, samples.len(), start)
+    }
+}
+
+/// 独立な推定値の平均と標準誤差をtupleで返します。
+/// * `estimates` - 推定値
+pub fn mean_std_error(estimates: &[f64]) -> (f64, f64) {
+    let num = estimates.len() as f64;
+    let mean = estimates.iter().sum::<f64>() / num;
+    let var = estimates.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (num - 1.0);
+    (mean, (var / num).sqrt())
+}
+
+/// 標準誤差が目標値を下回るまで、batch_size本ずつパスを追加して計算します。<br>
+/// パスのインデックスの範囲を渡すとパスごとのペイオフを返す関数を使い、和と二乗和を逐次に更新します。
+/// 分散の推定には2本以上のパスが必要で、最初のバッチで分散が0の場合は偶然の可能性があるため収束とはしません。
+/// * `target_std_error` - 目標の標準誤差
+/// * `batch_size` - 1回に追加するパス数(1以上)
+/// * `max_path` - パス数の上限(2以上)
+/// * `simulate` - パスのインデックスの範囲に対するペイオフを返す関数
+pub fn run_until_std_error<F>(
+    target_std_error: f64,
+    batch_size: usize,
+    max_path: usize,
+    mut simulate: F,
+) -> McResult
+where
+    F: FnMut(Range<usize>) -> Vec<f64>,
+{
+    assert!(batch_size > 0, "batch_sizeは1以上である必要があります");
+    assert!(
+        max_path >= 2,
+        "標準誤差の推定にはmax_pathが2以上である必要があります"
+    );
+    let start = Instant::now();
+    let (mut sum, mut sum_sq, mut num_path) = (0.0, 0.0, 0);
+    let mut num_batch = 0;
+    loop {
+        let batch_end = (num_path + batch_size).min(max_path);
+        for val in simulate(num_path..batch_end).iter() {
+            sum += val;
+            sum_sq += val * val;
+        }
+        num_path = batch_end;
+        num_batch += 1;
+        if num_path < 2 {
+            continue;
+        }
+        let num = num_path as f64;
+        let mean = sum / num;
+        let var = ((sum_sq - num * mean.powi(2)) / (num - 1.0)).max(0.0);
+        let std_error = (var / num).sqrt();
+        let converged = std_error < target_std_error && (std_error > 0.0 || num_batch > 1);
+        if converged || num_path >= max_path {
+            return McResult::new(mean, std_error, num_path, start);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_until_std_error() {
+        // 0, 1が交互に並ぶサンプルの標準誤差は約 0.5/√n
+        let simulate = |range: Range<usize>| range.map(|i| (i % 2) as f64).collect();
+        let result = run_until_std_error(0.01, 100, 1000000, simulate);
+        assert_eq!(result.num_path, 2600);
+        assert!(result.std_error < 0.01);
+        assert!((result.price - 0.5).abs() < 1e-12);
+        assert!(result.conf_interval.0 < 0.5 && 0.5 < result.conf_interval.1);
+
+        let result = run_until_std_error(0.01, 100, 1000, simulate);
+        assert_eq!(result.num_path, 1000);
+        assert!(result.std_error > 0.01);
+    }
+
+    #[test]
+    fn test_run_until_std_error_small_batches() {
+        // 1本ずつでも2本目から標準誤差を推定する(1本目の分散は推定できない)。
+        let simulate = |range: Range<usize>| range.map(|i| (i % 2) as f64).collect();
+        let result = run_until_std_error(0.2, 1, 1000, simulate);
+        assert!(result.num_path >= 2);
+        assert!(result.std_error > 0.0 && result.std_error < 0.2);
+        // 最初のバッチが全て同じ値で分散が0でも、次のバッチを加えて確認する。
+        let simulate = |range: Range<usize>| range.map(|i| (i / 100) as f64).collect();
+        let result = run_until_std_error(0.1, 100, 1000, simulate);
+        assert!(result.num_path > 100);
+        assert!(result.std_error > 0.0);
+        // ペイオフが定数なら2バッチ目で標準誤差0として終了する。
+        let result = run_until_std_error(0.01, 100, 1000, |range| vec![1.0; range.len()]);
+        assert_eq!(result.num_path, 200);
+        assert_eq!(result.std_error, 0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "batch_size")]
+    fn test_run_until_std_error_zero_batch() {
+        run_until_std_error(0.01, 0, 1000, |range| vec![1.0; range.len()]);
+    }
+}
@@ -0,0 +1,463 @@
+use super::barrier::{crossing_probability, Barrier};
+use super::mc_result::McResult;
+use super::path_generator::{Gbm, LocalVol};
+use super::payoff::{intrinsic, OptionType};
+use super::rand_num::{stream_rng, RandGen, STREAM_SEED_MASK};
+use rand_distr::{Distribution, StandardNormal};
+use rayon::prelude::*;
+use std::time::Instant;
+
+// ローカルボラティリティの拡散係数の微分の差分幅(相対)
+const DIFF_BUMP: f64 = 1e-4;
+
+/* Multilevel Monte Carlo (Giles 2008)
+レベルlの時間ステップ数を base_step * M^l とし、E[P_L] = E[P_0] + Σ_{l=1}^{L} E[P_l - P_{l-1}] をレベルごとに独立に推定する。
+P_l - P_{l-1} は同じブラウン運動の増分から細かいパスと粗いパス(細かいパスのM個の増分の和を使う)を作って計算するため分散が小さく、
+細かいレベルほど少ないパス数で済む。
+パス数: 分散V_l、1パスのコストC_lに対して N_l = 2/ε^2 √(V_l/C_l) Σ_k √(V_k C_k) とすると標準誤差がε/√2以下となる。
+レベル数: 最後のレベルの平均から残りのバイアス max(|Y_L|, |Y_{L-1}|/M^α) / (M^α - 1) を見積もり、ε/√2を超えればレベルを追加する。 */
+
+/// 1次元の確率微分方程式 dX = a(X, t)dt + b(X, t)dW です。
+pub trait ScalarSde: Sync {
+    /// 時刻0の値
+    fn initial_value(&self) -> f64;
+
+    /// ドリフト a(X, t)
+    fn drift(&self, x: f64, time: f64) -> f64;
+
+    /// 拡散係数 b(X, t)
+    fn diffusion(&self, x: f64, time: f64) -> f64;
+
+    /// 拡散係数のXに関する微分(Milstein法に使う)
+    fn diffusion_derivative(&self, x: f64, time: f64) -> f64;
+
+    /// 割引に使う金利(一定)
+    fn zero_rate(&self) -> f64;
+}
+
+impl ScalarSde for Gbm {
+    fn initial_value(&self) -> f64 {
+        self.underlying
+    }
+
+    fn drift(&self, x: f64, _: f64) -> f64 {
+        (self.zero_rate - self.div_yield) * x
+    }
+
+    fn diffusion(&self, x: f64, _: f64) -> f64 {
+        self.vol * x
+    }
+
+    fn diffusion_derivative(&self, _: f64, _: f64) -> f64 {
+        self.vol
+    }
+
+    fn zero_rate(&self) -> f64 {
+        self.zero_rate
+    }
+}
+
+impl ScalarSde for LocalVol {
+    fn initial_value(&self) -> f64 {
+        self.underlying
+    }
+
+    fn drift(&self, x: f64, _: f64) -> f64 {
+        (self.zero_rate - self.div_yield) * x
+    }
+
+    fn diffusion(&self, x: f64, time: f64) -> f64 {
+        (self.local_vol)(x, time) * x
+    }
+
+    fn diffusion_derivative(&self, x: f64, time: f64) -> f64 {
+        let bump = DIFF_BUMP * x.abs().max(DIFF_BUMP);
+        (self.diffusion(x + bump, time) - self.diffusion(x - bump, time)) / (2.0 * bump)
+    }
+
+    fn zero_rate(&self) -> f64 {
+        self.zero_rate
+    }
+}
+
+/// 離散化スキーム
+#[derive(Debug, Copy, Clone)]
+pub enum Scheme {
+    Euler,
+    Milstein, // Euler法に 1/2 b b' (ΔW^2 - Δt) を加える
+}
+
+/// 等間隔のグリッド上のパスに対する割引前のペイオフです。
+pub trait LevelPayoff: Sync {
+    /// 満期(支払日)
+    fn maturity(&self) -> f64;
+
+    /// パスに対する割引前のペイオフを返します。
+    /// * `path` - 時刻0から満期までの等間隔のグリッド上の値
+    /// * `log_vols` - 各ステップの対数のボラティリティ b(X, t) / X(ブラウン橋の補正に使う)
+    /// * `delta_t` - ステップの幅
+    fn value(&self, path: &[f64], log_vols: &[f64], delta_t: f64) -> f64;
+}
+
+// 連続観測の算術平均Asian Option(平均は台形公式で近似する)
+#[derive(Debug, Copy, Clone)]
+pub struct ContinuousAsian {
+    pub option_type: OptionType,
+    pub strike: f64,
+    pub maturity: f64,
+}
+
+impl LevelPayoff for ContinuousAsian {
+    fn maturity(&self) -> f64 {
+        self.maturity
+    }
+
+    fn value(&self, path: &[f64], _: &[f64], _: f64) -> f64 {
+        let num = path.len() - 1;
+        let sum: f64 = path[1..num].iter().sum::<f64>() + 0.5 * (path[0] + path[num]);
+        intrinsic(sum / num as f64, self.strike, self.option_type)
+    }
+}
+
+impl LevelPayoff for Barrier {
+    fn maturity(&self) -> f64 {
+        self.maturity
+    }
+
+    fn value(&self, path: &[f64], log_vols: &[f64], delta_t: f64) -> f64 {
+        let is_up = self.barrier_type.is_up();
+        let breached = |s: f64| {
+            if is_up {
+                s >= self.barrier
+            } else {
+                s <= self.barrier
+            }
+        };
+        let mut survival = 1.0;
+        for (j, window) in path.windows(2).enumerate() {
+            if breached(window[0]) || breached(window[1]) {
+                survival = 0.0;
+                break;
+            }
+            if self.continuous {
+                let variance = log_vols[j].powi(2) * delta_t;
+                survival *=
+                    1.0 - crossing_probability(window[0], window[1], self.barrier, variance);
+            }
+        }
+        let weight = if self.barrier_type.is_out() {
+            survival
+        } else {
+            1.0 - survival
+        };
+        weight * intrinsic(path[path.len() - 1], self.strike, self.option_type)
+    }
+}
+
+/// MLMCの設定です。
+#[derive(Debug, Copy, Clone)]
+pub struct MlmcConfig {
+    pub scheme: Scheme,
+    pub base_step: usize,    // レベル0の時間ステップ数
+    pub refinement: usize,   // レベルごとのステップ数の倍率M
+    pub initial_path: usize, // 新しいレベルの分散を見積もるパス数
+    pub min_level: usize,    // 最初に使うレベル数 - 1
+    pub max_level: usize,    // レベルの上限
+}
+
+/// レベルごとの統計量です。
+#[derive(Debug, Copy, Clone)]
+pub struct LevelStat {
+    pub num_path: usize,
+    pub mean: f64,     // E[P_l - P_{l-1}]の推定値
+    pub variance: f64, // P_l - P_{l-1}の分散
+    pub cost: f64,     // 1パスあたりの時間ステップ数(細かいパスと粗いパスの和)
+}
+
+/// MLMCの計算結果です。
+#[derive(Debug, Clone)]
+pub struct MlmcResult {
+    pub result: McResult, // num_pathは全レベルのパス数の和
+    pub levels: Vec<LevelStat>,
+    pub converged: bool, // max_levelまでに残りのバイアスの見積もりが目標を下回ったか
+}
+
+// レベルごとの和と二乗和
+#[derive(Debug, Copy, Clone, Default)]
+struct LevelSum {
+    num_path: usize,
+    sum: f64,
+    sum_sq: f64,
+}
+
+impl LevelSum {
+    fn mean(&self) -> f64 {
+        self.sum / self.num_path as f64
+    }
+
+    fn variance(&self) -> f64 {
+        (self.sum_sq / self.num_path as f64 - self.mean().powi(2)).max(0.0)
+    }
+}
+
+/// 目標のRMSEに対して、レベル数とレベルごとのパス数を決めてMLMCで価格を返します。<br>
+/// レベルごとの分散を標本から求めるため、rand_genのseedだけを使い独立な擬似乱数でパスを生成します。
+/// * `sde` - 確率微分方程式
+/// * `payoff` - ペイオフ
+/// * `target_rmse` - 目標のRMSE ε(統計誤差とバイアスをそれぞれε/√2以下とする)
+/// * `config` - MLMCの設定
+/// * `rand_gen` - 乱数の設定
+pub fn mlmc_price(
+    sde: &dyn ScalarSde,
+    payoff: &dyn LevelPayoff,
+    target_rmse: f64,
+    config: &MlmcConfig,
+    rand_gen: &RandGen,
+) -> MlmcResult {
+    let start = Instant::now();
+    let m = config.refinement as f64;
+    let cost = |level: usize| {
+        let fine = (config.base_step * config.refinement.pow(level as u32)) as f64;
+        if level == 0 {
+            fine
+        } else {
+            fine * (1.0 + 1.0 / m)
+        }
+    };
+    let mut sums: Vec<LevelSum> = vec![LevelSum::default(); config.min_level + 1];
+    let mut extra: Vec<usize> = vec![config.initial_path; config.min_level + 1];
+    let mut converged = false;
+    loop {
+        for (level, num) in extra.iter().enumerate() {
+            if *num > 0 {
+                let level_sum = &mut sums[level];
+                let range = level_sum.num_path..level_sum.num_path + num;
+                let samples: Vec<f64> = range
+                    .into_par_iter()
+                    .map(|path_idx| level_sample(sde, payoff, config, level, path_idx, rand_gen))
+                    .collect();
+                level_sum.num_path += num;
+                level_sum.sum += samples.iter().sum::<f64>();
+                level_sum.sum_sq += samples.iter().map(|y| y * y).sum::<f64>();
+            }
+        }
+
+        // 最適なパス数
+        let total: f64 = (0..sums.len())
+            .map(|l| (sums[l].variance() * cost(l)).sqrt())
+            .sum();
+        extra = (0..sums.len())
+            .map(|l| {
+                let optimal =
+                    2.0 / target_rmse.powi(2) * (sums[l].variance() / cost(l)).sqrt() * total;
+                (optimal.ceil() as usize).saturating_sub(sums[l].num_path)
+            })
+            .collect();
+        if extra
+            .iter()
+            .zip(sums.iter())
+            .any(|(n, s)| *n as f64 > 0.01 * s.num_path as f64)
+        {
+            continue;
+        }
+
+        // 残りのバイアスの見積もり(2レベル以上で行う)
+        let last = sums.len() - 1;
+        if last == 0 {
+            sums.push(LevelSum::default());
+            extra.push(config.initial_path);
+            continue;
+        }
+        let (mean_last, mean_prev) = (sums[last].mean().abs(), sums[last - 1].mean().abs());
+        let alpha = if last >= 2 && mean_last > 0.0 && mean_prev > 0.0 {
+            ((mean_prev / mean_last).ln() / m.ln()).max(0.5)
+        } else {
+            1.0
+        };
+        let factor = m.powf(alpha);
+        let remaining_bias = mean_last.max(mean_prev / factor) / (factor - 1.0);
+        if remaining_bias <= target_rmse / 2_f64.sqrt() {
+            converged = true;
+            break;
+        }
+        if last >= config.max_level {
+            break;
+        }
+        sums.push(LevelSum::default());
+        extra.push(config.initial_path);
+    }
+
+    let price: f64 = sums.iter().map(|s| s.mean()).sum();
+    let variance: f64 = sums.iter().map(|s| s.variance() / s.num_path as f64).sum();
+    let num_path = sums.iter().map(|s| s.num_path).sum();
+    let levels = sums
+        .iter()
+        .enumerate()
+        .map(|(l, s)| LevelStat {
+            num_path: s.num_path,
+            mean: s.mean(),
+            variance: s.variance(),
+            cost: cost(l),
+        })
+        .collect();
+    MlmcResult {
+        result: McResult::new(price, variance.sqrt(), num_path, start),
+        levels,
+        converged,
+    }
+}
+
+/// レベルlの1本のパスの割引後の P_l - P_{l-1}(レベル0は P_0)を返します。
+/// * `sde` - 確率微分方程式
+/// * `payoff` - ペイオフ
+/// * `config` - MLMCの設定
+/// * `level` - レベル
+/// * `path_idx` - パスのインデックス(乱数のストリーム番号)
+/// * `rand_gen` - 乱数の設定(seedだけを使う)
+pub fn level_sample(
+    sde: &dyn ScalarSde,
+    payoff: &dyn LevelPayoff,
+    config: &MlmcConfig,
+    level: usize,
+    path_idx: usize,
+    rand_gen: &RandGen,
+) -> f64 {
+    let maturity = payoff.maturity();
+    let num_step = config.base_step * config.refinement.pow(level as u32);
+    let delta_t = maturity / num_step as f64;
+    // レベルごとに乱数のseedを分ける。
+    let seed = rand_gen.seed ^ STREAM_SEED_MASK.wrapping_mul(level as u64 + 1);
+    let mut rng = stream_rng(seed, path_idx as u64);
+    let increments: Vec<f64> = (0..num_step)
+        .map(|_| {
+            let z: f64 = StandardNormal.sample(&mut rng);
+            delta_t.sqrt() * z
+        })
+        .collect();
+    let df = (-sde.zero_rate() * maturity).exp();
+    let fine = level_payoff(sde, payoff, config.scheme, &increments, delta_t);
+    if level == 0 {
+        return df * fine;
+    }
+    // 粗いパスは細かいパスのM個の増分の和を使う。
+    let coarse_increments: Vec<f64> = increments
+        .chunks(config.refinement)
+        .map(|c| c.iter().sum())
+        .collect();
+    let coarse = level_payoff(
+        sde,
+        payoff,
+        config.scheme,
+        &coarse_increments,
+        delta_t * config.refinement as f64,
+    );
+    df * (fine - coarse)
+}
+
+// ブラウン運動の増分からパスを作ってペイオフを返す。
+fn level_payoff(
+    sde: &dyn ScalarSde,
+    payoff: &dyn LevelPayoff,
+    scheme: Scheme,
+    increments: &[f64],
+    delta_t: f64,
+) -> f64 {
+    let mut path: Vec<f64> = Vec::with_capacity(increments.len() + 1);
+    let mut log_vols: Vec<f64> = Vec::with_capacity(increments.len());
+    let mut x = sde.initial_value();
+    path.push(x);
+    for (j, dw) in increments.iter().enumerate() {
+        let time = j as f64 * delta_t;
+        let diffusion = sde.diffusion(x, time);
+        log_vols.push(if x > 0.0 { diffusion / x } else { 0.0 });
+        let mut next = x + sde.drift(x, time) * delta_t + diffusion * dw;
+        if let Scheme::Milstein = scheme {
+            next += 0.5 * diffusion * sde.diffusion_derivative(x, time) * (dw * dw - delta_t);
+        }
+        x = next;
+        path.push(x);
+    }
+    payoff.value(&path, &log_vols, delta_t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bs::black_scholes::{self, CalcInput};
+    use crate::bs::exotic::{barrier_option, BarrierType};
+    use crate::mc::test_util::{gbm, rand_gen};
+
+    const SEED: u64 = 3;
+
+    fn config(scheme: Scheme) -> MlmcConfig {
+        MlmcConfig {
+            scheme,
+            base_step: 8,
+            refinement: 2,
+            initial_path: 2000,
+            min_level: 2,
+            max_level: 10,
+        }
+    }
+
+    #[test]
+    fn test_mlmc_barrier_against_closed_form() {
+        let down_out = Barrier {
+            option_type: OptionType::Call,
+            barrier_type: BarrierType::DownOut,
+            strike: 100.0,
+            barrier: 85.0,
+            maturity: 1.0,
+            continuous: true,
+        };
+        let input = CalcInput {
+            zero_rate: 0.05,
+            vol: 0.2,
+            term_annu: 1.0,
+            strike: 100.0,
+            underlying: 100.0,
+        };
+        let expected = barrier_option(
+            &input,
+            85.0,
+            BarrierType::DownOut,
+            black_scholes::OptionType::Call,
+        );
+        let target_rmse = 0.05;
+        let mlmc = mlmc_price(
+            &gbm(0.05, 0.0, 0.2),
+            &down_out,
+            target_rmse,
+            &config(Scheme::Milstein),
+            &rand_gen(SEED),
+        );
+        assert!(mlmc.converged);
+        assert!(mlmc.result.std_error < 1.01 * target_rmse / 2_f64.sqrt());
+        assert!((mlmc.result.price - expected).abs() < 3.0 * target_rmse);
+    }
+
+    #[test]
+    fn test_milstein_level_variance() {
+        let (gbm, rand_gen) = (gbm(0.05, 0.0, 0.2), rand_gen(SEED));
+        // GBMではMilstein法の強収束の次数が1となり、P_l - P_{l-1}の分散がEuler法より速く減衰する。
+        let asian = ContinuousAsian {
+            option_type: OptionType::Call,
+            strike: 100.0,
+            maturity: 1.0,
+        };
+        let variance = |scheme: Scheme, level: usize| {
+            let samples: Vec<f64> = (0..4000)
+                .map(|i| level_sample(&gbm, &asian, &config(scheme), level, i, &rand_gen))
+                .collect();
+            let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+            samples.iter().map(|y| (y - mean).powi(2)).sum::<f64>() / samples.len() as f64
+        };
+        let (euler_coarse, euler_fine) = (variance(Scheme::Euler, 2), variance(Scheme::Euler, 5));
+        let (milstein_coarse, milstein_fine) =
+            (variance(Scheme::Milstein, 2), variance(Scheme::Milstein, 5));
+        // 3レベルでEulerは約1/8、Milsteinは約1/64になる。
+        assert!(euler_coarse / euler_fine > 4.0);
+        assert!(milstein_coarse / milstein_fine > 16.0);
+        assert!(milstein_fine < euler_fine);
+    }
+}
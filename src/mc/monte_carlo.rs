@@ -1,44 +1,355 @@
-use rand::thread_rng;
-use rand_distr::{Distribution, StandardNormal};
+use super::engine::{mc_price, mc_price_until};
+use super::greeks::{mc_greeks, GreekMethod, McGreeks};
+use super::mc_result::{mean_std_error, McResult};
+use super::mlmc::{mlmc_price, ContinuousAsian, MlmcConfig, MlmcResult};
+use super::path_generator::Gbm;
+use super::payoff::{ArithmeticAsian, OptionType};
+use super::rand_num::{NormalGen, RandGen};
+use crate::ad::dual::Dual;
+use crate::ad::scalar::Scalar;
 use rayon::prelude::*;
-
-/* まだ足していく */
+use std::time::Instant;
 
 #[derive(Debug, Copy, Clone)]
-pub struct CalcInput {
-    pub zero_rate: f64,
-    pub vol: f64,
-    pub term_annu: f64,
-    pub strike: f64,
-    pub underlying: f64,
-}
-
-pub fn mc_bs_asian_call(input: &CalcInput, time_step: usize, num_path: usize) -> f64 {
-    let CalcInput {
-        zero_rate,
-        vol,
-        term_annu,
-        strike,
-        underlying,
-    } = *input;
-    let delta_t = term_annu / time_step as f64;
-
-    let und_paths: Vec<Vec<f64>> = vec![vec![0.0; time_step]; num_path];
-    let vals: Vec<f64> = und_paths
+pub struct CalcInput<T = f64> {
+    pub zero_rate: T,
+    pub vol: T,
+    pub term_annu: T,
+    pub strike: T,
+    pub underlying: T,
+}
+
+/// 算術平均Asian Callの価格を返します。
+/// * `input` - 計算のインプット
+/// * `time_step` - 時間方向のステップ数
+/// * `num_path` - パス数
+/// * `rand_gen` - 乱数の設定
+pub fn mc_bs_asian_call(
+    input: &CalcInput,
+    time_step: usize,
+    num_path: usize,
+    rand_gen: &RandGen,
+) -> McResult {
+    let (gbm, asian) = asian_call_model(input, time_step);
+    mc_price(&gbm, &asian, time_step, num_path, rand_gen)
+}
+
+/// 標準誤差が目標値を下回るまでパスを追加して、算術平均Asian Callの価格を返します。
+/// * `input` - 計算のインプット
+/// * `time_step` - 時間方向のステップ数
+/// * `target_std_error` - 目標の標準誤差
+/// * `batch_size` - 1回に追加するパス数
+/// * `max_path` - パス数の上限
+/// * `rand_gen` - 乱数の設定
+pub fn mc_bs_asian_call_until(
+    input: &CalcInput,
+    time_step: usize,
+    target_std_error: f64,
+    batch_size: usize,
+    max_path: usize,
+    rand_gen: &RandGen,
+) -> McResult {
+    let (gbm, asian) = asian_call_model(input, time_step);
+    mc_price_until(
+        &gbm,
+        &asian,
+        time_step,
+        target_std_error,
+        batch_size,
+        max_path,
+        rand_gen,
+    )
+}
+
+/// 算術平均Asian Callの価格とDelta, Gamma, Vegaを返します。
+/// * `input` - 計算のインプット
+/// * `time_step` - 時間方向のステップ数
+/// * `num_path` - パス数
+/// * `rand_gen` - 乱数の設定
+/// * `method` - Greeksの推定方法
+pub fn mc_bs_asian_call_greeks(
+    input: &CalcInput,
+    time_step: usize,
+    num_path: usize,
+    rand_gen: &RandGen,
+    method: GreekMethod,
+) -> McGreeks {
+    let (gbm, asian) = asian_call_model(input, time_step);
+    mc_greeks(&gbm, &asian, time_step, num_path, rand_gen, method)
+}
+
+/// 連続観測の算術平均Asian Callの価格を、目標のRMSEに対してMLMCで返します。<br>
+/// 単一レベルで細かい時間グリッドを使う場合と比べて、粗いレベルに多くのパスを割り当てるため計算量が少なくなります。
+/// * `input` - 計算のインプット
+/// * `target_rmse` - 目標のRMSE
+/// * `config` - MLMCの設定
+/// * `rand_gen` - 乱数の設定
+pub fn mc_bs_asian_call_mlmc(
+    input: &CalcInput,
+    target_rmse: f64,
+    config: &MlmcConfig,
+    rand_gen: &RandGen,
+) -> MlmcResult {
+    let (gbm, _) = asian_call_model(input, 1);
+    let asian = ContinuousAsian {
+        option_type: OptionType::Call,
+        strike: input.strike,
+        maturity: input.term_annu,
+    };
+    mlmc_price(&gbm, &asian, target_rmse, config, rand_gen)
+}
+
+// 観測日は t_i = iΔt (i = 0, ..., time_step - 1)、支払日は満期
+fn asian_call_model(input: &CalcInput, time_step: usize) -> (Gbm, ArithmeticAsian) {
+    let delta_t = input.term_annu / time_step as f64;
+    let gbm = Gbm {
+        underlying: input.underlying,
+        zero_rate: input.zero_rate,
+        div_yield: 0.0,
+        vol: input.vol,
+    };
+    let asian = ArithmeticAsian {
+        option_type: OptionType::Call,
+        strike: input.strike,
+        observation_times: (0..time_step).map(|i| i as f64 * delta_t).collect(),
+        payment_time: input.term_annu,
+    };
+    (gbm, asian)
+}
+
+/// 算術平均Asian Callの価格と、[原資産価格, ボラティリティ, 金利, 満期, 権利行使価格]に関する感応度を返します。<br>
+/// パスの計算を二重数で行うパスワイズ微分のため、全ての感応度が1回のシミュレーションで求まります。
+/// 乱数はmc_bs_asian_callと同じものを使います。
+/// * `input` - 計算のインプット
+/// * `time_step` - 時間方向のステップ数
+/// * `num_path` - パス数
+/// * `rand_gen` - 乱数の設定
+pub fn mc_bs_asian_call_sensitivities(
+    input: &CalcInput,
+    time_step: usize,
+    num_path: usize,
+    rand_gen: &RandGen,
+) -> (McResult, Vec<McResult>) {
+    let start = Instant::now();
+    let times: Vec<f64> = (1..time_step + 1)
+        .map(|i| input.term_annu * i as f64 / time_step as f64)
+        .collect();
+    let normal_gen = NormalGen::new(rand_gen, &times, 1);
+    let dual_input = CalcInput {
+        underlying: Dual::<5>::variable(input.underlying, 0),
+        vol: Dual::variable(input.vol, 1),
+        zero_rate: Dual::variable(input.zero_rate, 2),
+        term_annu: Dual::variable(input.term_annu, 3),
+        strike: Dual::variable(input.strike, 4),
+    };
+    let samples: Vec<Dual<5>> = (0..num_path)
         .into_par_iter()
-        .map(|mut und_path| -> f64 {
-            und_path[0] = underlying;
-            for i in 1..time_step {
-                let norm_rand: f64 = StandardNormal.sample(&mut thread_rng());
-                und_path[i] = und_path[i - 1]
-                    * ((zero_rate - 0.5 * vol.powi(2)) * delta_t
-                        + vol * delta_t.sqrt() * norm_rand)
-                        .exp();
-            }
-            (-zero_rate * term_annu).exp()
-                * (und_path.par_iter().sum::<f64>() / time_step as f64 - strike).max(0.0)
+        .map(|path_idx| asian_call_path(&dual_input, &normal_gen.normals(path_idx)))
+        .collect();
+    let values: Vec<f64> = samples.iter().map(|s| s.value).collect();
+    let sensitivities = (0..5)
+        .map(|i| {
+            let grads: Vec<f64> = samples.iter().map(|s| s.grad[i]).collect();
+            McResult::from_samples(&grads, start)
+        })
+        .collect();
+    (McResult::from_samples(&values, start), sensitivities)
+}
+
+// 1本のパスの算術平均Asian Callの割引後のペイオフ(観測日は t_i = iΔt (i = 0, ..., time_step - 1))
+fn asian_call_path<T: Scalar>(input: &CalcInput<T>, normals: &[f64]) -> T {
+    let time_step = normals.len();
+    let delta_t = input.term_annu / time_step as f64;
+    let drift = (input.zero_rate - input.vol.powi(2) * 0.5) * delta_t;
+    let diffusion = input.vol * delta_t.sqrt();
+    let mut underlying = input.underlying;
+    let mut sum = underlying;
+    for z in normals[..time_step - 1].iter() {
+        underlying *= (drift + diffusion * *z).exp();
+        sum += underlying;
+    }
+    let average = sum / time_step as f64;
+    if average.value() > input.strike.value() {
+        (average - input.strike) * (-input.zero_rate * input.term_annu).exp()
+    } else {
+        T::from_f64(0.0)
+    }
+}
+
+/// seedを変えてランダム化したQMC(またはMC)の推定値の平均と、そのばらつきから求めた標準誤差を返します。
+/// * `input` - 計算のインプット
+/// * `time_step` - 時間方向のステップ数
+/// * `num_path` - 1回あたりのパス数
+/// * `rand_gen` - 乱数の設定(seedを起点にnum_randomization個のseedを使う)
+/// * `num_randomization` - ランダム化の回数
+pub fn mc_bs_asian_call_rqmc(
+    input: &CalcInput,
+    time_step: usize,
+    num_path: usize,
+    rand_gen: &RandGen,
+    num_randomization: usize,
+) -> McResult {
+    let start = Instant::now();
+    let estimates: Vec<f64> = (0..num_randomization as u64)
+        .map(|k| {
+            let rand_gen = RandGen {
+                seed: rand_gen.seed.wrapping_add(k),
+                ..*rand_gen
+            };
+            mc_bs_asian_call(input, time_step, num_path, &rand_gen).price
         })
         .collect();
+    let (price, std_error) = mean_std_error(&estimates);
+    McResult::new(price, std_error, num_path * num_randomization, start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mc::mlmc::Scheme;
+    use crate::mc::rand_num::{InverseCdf, RandType};
+    use crate::mc::test_util::{rand_gen, sobol_rand_gen};
+
+    #[test]
+    fn test_mc_bs_asian_call_reproducible() {
+        let input = CalcInput {
+            underlying: 100.0,
+            strike: 100.0,
+            vol: 0.2,
+            zero_rate: 0.05,
+            term_annu: 1.0,
+        };
+        for rand_type in [RandType::Pseudo, RandType::Sobol] {
+            let price_with_threads = |num_threads: usize, seed: u64| -> f64 {
+                let rand_gen = RandGen {
+                    rand_type,
+                    ..sobol_rand_gen(seed)
+                };
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(num_threads)
+                    .build()
+                    .unwrap()
+                    .install(|| mc_bs_asian_call(&input, 20, 2000, &rand_gen).price)
+            };
+            let single = price_with_threads(1, 42);
+            let multi = price_with_threads(4, 42);
+            assert_eq!(single.to_bits(), multi.to_bits());
+            assert_eq!(single.to_bits(), price_with_threads(3, 42).to_bits());
+            assert_ne!(single.to_bits(), price_with_threads(4, 43).to_bits());
+        }
+    }
+
+    #[test]
+    fn test_mc_bs_asian_call_rqmc() {
+        let input = CalcInput {
+            underlying: 100.0,
+            strike: 100.0,
+            vol: 0.2,
+            zero_rate: 0.05,
+            term_annu: 1.0,
+        };
+        let rqmc = |rand_type: RandType, brownian_bridge: bool| -> McResult {
+            let rand_gen = RandGen {
+                rand_type,
+                brownian_bridge,
+                inverse_cdf: InverseCdf::Wichura,
+                ..rand_gen(7)
+            };
+            mc_bs_asian_call_rqmc(&input, 20, 4096, &rand_gen, 10)
+        };
+        let mc = rqmc(RandType::Pseudo, false);
+        let sobol = rqmc(RandType::Sobol, true);
+        let halton = rqmc(RandType::Halton, true);
+        // QMC + Brownian bridgeの標準誤差は擬似乱数より十分小さい。
+        assert!(sobol.std_error < 0.25 * mc.std_error);
+        assert!(halton.std_error < 0.5 * mc.std_error);
+        assert!((sobol.price - mc.price).abs() < 4.0 * mc.std_error);
+        assert!((halton.price - sobol.price).abs() < 4.0 * (halton.std_error + sobol.std_error));
+    }
+
+    #[test]
+    fn test_mc_bs_asian_call_until() {
+        let input = CalcInput {
+            underlying: 100.0,
+            strike: 100.0,
+            vol: 0.2,
+            zero_rate: 0.05,
+            term_annu: 1.0,
+        };
+        let rand_gen = rand_gen(3);
+        let result = mc_bs_asian_call_until(&input, 50, 0.05, 1000, 100000, &rand_gen);
+        assert!(result.std_error < 0.05);
+        assert_eq!(result.num_path % 1000, 0);
+        // バッチに分けても同じパスを使うため、同じパス数の一括計算と一致する。
+        let fixed = mc_bs_asian_call(&input, 50, result.num_path, &rand_gen);
+        assert!((result.price - fixed.price).abs() < 1e-10);
+        assert!((result.std_error - fixed.std_error).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_mc_bs_asian_call_mlmc() {
+        // 連続観測のAsian Call(Linetsky(2004)のS = K = 2、r = 0.02、σ = 0.1、T = 1の価格0.0559860415を50倍したもの)
+        let input = CalcInput {
+            underlying: 100.0,
+            strike: 100.0,
+            vol: 0.1,
+            zero_rate: 0.02,
+            term_annu: 1.0,
+        };
+        let config = MlmcConfig {
+            scheme: Scheme::Milstein,
+            base_step: 4,
+            refinement: 2,
+            initial_path: 2000,
+            min_level: 2,
+            max_level: 10,
+        };
+        let rand_gen = rand_gen(5);
+        let target_rmse = 0.01;
+        let result = mc_bs_asian_call_mlmc(&input, target_rmse, &config, &rand_gen);
+        assert!(result.converged);
+        assert!((result.result.price - 50.0 * 0.0559860415).abs() < 3.0 * target_rmse);
+    }
+
+    #[test]
+    fn test_mc_bs_asian_call_greeks() {
+        // パスワイズ微分と共通乱数によるBump and Revalueが一致する。
+        let input = CalcInput {
+            underlying: 100.0,
+            strike: 100.0,
+            vol: 0.2,
+            zero_rate: 0.05,
+            term_annu: 1.0,
+        };
+        let rand_gen = sobol_rand_gen(5);
+        let pathwise = mc_bs_asian_call_greeks(&input, 20, 20000, &rand_gen, GreekMethod::Pathwise);
+        let bump =
+            mc_bs_asian_call_greeks(&input, 20, 20000, &rand_gen, GreekMethod::BumpAndRevalue);
+        for (a, b) in [
+            (pathwise.delta, bump.delta),
+            (pathwise.gamma, bump.gamma),
+            (pathwise.vega, bump.vega),
+        ] {
+            let tolerance = 4.0 * (a.std_error.powi(2) + b.std_error.powi(2)).sqrt();
+            assert!((a.price - b.price).abs() < tolerance + 1e-3 * b.price.abs());
+        }
+    }
 
-    vals.par_iter().sum::<f64>() / num_path as f64
+    #[test]
+    fn test_mc_bs_asian_call_sensitivities() {
+        // 同じ乱数を使うため、価格とDelta, Vegaはパスワイズ微分の結果と丸め誤差の範囲で一致する。
+        let input = CalcInput {
+            underlying: 100.0,
+            strike: 100.0,
+            vol: 0.2,
+            zero_rate: 0.05,
+            term_annu: 1.0,
+        };
+        let rand_gen = sobol_rand_gen(3);
+        let (price, sensitivities) = mc_bs_asian_call_sensitivities(&input, 20, 4096, &rand_gen);
+        let greeks = mc_bs_asian_call_greeks(&input, 20, 4096, &rand_gen, GreekMethod::Pathwise);
+        assert!((price.price - greeks.price.price).abs() < 1e-10);
+        assert!((sensitivities[0].price - greeks.delta.price).abs() < 1e-10);
+        assert!((sensitivities[1].price - greeks.vega.price).abs() < 1e-8);
+    }
 }
@@ -0,0 +1,357 @@
+use super::engine::Path;
+use super::path_generator::PathGenerator;
+use super::payoff::{intrinsic, OptionType, Payoff};
+use ndarray::{Array1, Array2};
+use ndarray_linalg::{Cholesky, Eigh, UPLO};
+use rand_chacha::ChaCha8Rng;
+
+// 補正後の相関行列の固有値の下限(Cholesky分解できるように正定値とする)
+const MIN_EIGENVALUE: f64 = 1e-8;
+const MAX_ITERATION: usize = 200;
+const TOLERANCE: f64 = 1e-12;
+
+/// Higham(2002)の交互射影法で、対称行列に最も近い相関行列(半正定値・対角成分が1)を返します。
+/// * `corr` - 相関行列(正定値でなくてもよい)
+pub fn nearest_correlation(corr: &Array2<f64>) -> Array2<f64> {
+    let mut y = corr.clone();
+    let mut correction: Array2<f64> = Array2::zeros(corr.dim()); // Dykstraの補正
+    for _ in 0..MAX_ITERATION {
+        let r = &y - &correction;
+        let x = clip_eigenvalues(&r, 0.0);
+        correction = &x - &r;
+        let mut y_next = x;
+        y_next.diag_mut().fill(1.0);
+        let diff = (&y_next - &y).mapv(|v| v * v).sum().sqrt();
+        y = y_next;
+        if diff < TOLERANCE {
+            break;
+        }
+    }
+    // 固有値を下限で切り上げて正定値とし、対角成分を1に戻す。
+    let x = clip_eigenvalues(&y, MIN_EIGENVALUE);
+    let scale: Array1<f64> = x.diag().mapv(|v| 1.0 / v.sqrt());
+    Array2::from_shape_fn(x.dim(), |(i, j)| x[[i, j]] * scale[i] * scale[j])
+}
+
+// 固有値をmin_eigenvalueで切り上げた行列 V max(Λ, ε) V^T
+fn clip_eigenvalues(mat: &Array2<f64>, min_eigenvalue: f64) -> Array2<f64> {
+    let (eigenvalues, eigenvectors) = mat.eigh(UPLO::Lower).unwrap();
+    let clipped = Array2::from_diag(&eigenvalues.mapv(|v| v.max(min_eigenvalue)));
+    eigenvectors.dot(&clipped).dot(&eigenvectors.t())
+}
+
+/// 相関行列のCholesky分解(下三角行列)を返します。正定値でない場合は最も近い相関行列に補正してから分解します。
+/// * `corr` - 相関行列
+pub fn correlation_cholesky(corr: &Array2<f64>) -> Array2<f64> {
+    corr.cholesky(UPLO::Lower).unwrap_or_else(|_| {
+        nearest_correlation(corr)
+            .cholesky(UPLO::Lower)
+            .expect("failed to repair the correlation matrix")
+    })
+}
+
+// 相関のある多資産の幾何ブラウン運動。状態変数は各資産の価格
+#[derive(Debug, Clone)]
+pub struct MultiGbm {
+    pub underlyings: Vec<f64>,
+    pub div_yields: Vec<f64>,
+    pub vols: Vec<f64>,
+    pub zero_rate: f64,
+    chol: Array2<f64>, // 相関行列のCholesky分解
+}
+
+impl MultiGbm {
+    /// * `underlyings` - 各資産の価格
+    /// * `div_yields` - 各資産の配当利回り
+    /// * `vols` - 各資産のボラティリティ
+    /// * `zero_rate` - ゼロレート
+    /// * `corr` - 相関行列
+    pub fn new(
+        underlyings: Vec<f64>,
+        div_yields: Vec<f64>,
+        vols: Vec<f64>,
+        zero_rate: f64,
+        corr: &Array2<f64>,
+    ) -> Self {
+        Self {
+            underlyings,
+            div_yields,
+            vols,
+            zero_rate,
+            chol: correlation_cholesky(corr),
+        }
+    }
+}
+
+impl PathGenerator for MultiGbm {
+    fn num_factors(&self) -> usize {
+        self.underlyings.len()
+    }
+
+    fn initial_state(&self) -> Vec<f64> {
+        self.underlyings.clone()
+    }
+
+    fn evolve(&self, state: &mut [f64], _: f64, delta_t: f64, normals: &[f64], _: &mut ChaCha8Rng) {
+        for (i, und) in state.iter_mut().enumerate() {
+            let z: f64 = (0..i + 1).map(|j| self.chol[[i, j]] * normals[j]).sum();
+            *und *= ((self.zero_rate - self.div_yields[i] - 0.5 * self.vols[i].powi(2)) * delta_t
+                + self.vols[i] * delta_t.sqrt() * z)
+                .exp();
+        }
+    }
+
+    fn short_rate(&self, _: &[f64], _: f64) -> f64 {
+        self.zero_rate
+    }
+}
+
+// k番目の観測日の各資産のパフォーマンス S_i(t_k) / S_i(0)
+fn performances(path: &Path, k: usize) -> Vec<f64> {
+    path.fixing_state(k)
+        .iter()
+        .zip(path.states[0].iter())
+        .map(|(und, init)| und / init)
+        .collect()
+}
+
+// バスケットオプション。ペイオフは max(Σ w_i S_i(T) - K, 0) (Putは逆)
+#[derive(Debug, Clone)]
+pub struct Basket {
+    pub option_type: OptionType,
+    pub strike: f64,
+    pub weights: Vec<f64>,
+    pub maturity: f64,
+}
+
+impl Payoff for Basket {
+    fn fixing_times(&self) -> Vec<f64> {
+        vec![self.maturity]
+    }
+
+    fn cashflows(&self, path: &Path) -> Vec<(usize, f64)> {
+        let basket: f64 = path
+            .fixing_state(0)
+            .iter()
+            .zip(self.weights.iter())
+            .map(|(und, w)| und * w)
+            .sum();
+        vec![(0, intrinsic(basket, self.strike, self.option_type))]
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum RainbowType {
+    BestOf,
+    WorstOf,
+}
+
+// Best-of/Worst-ofオプション。パフォーマンスの最大値・最小値に対するCall/Put(strikeは初期値に対する割合)
+#[derive(Debug, Copy, Clone)]
+pub struct Rainbow {
+    pub rainbow_type: RainbowType,
+    pub option_type: OptionType,
+    pub strike: f64,
+    pub maturity: f64,
+}
+
+impl Payoff for Rainbow {
+    fn fixing_times(&self) -> Vec<f64> {
+        vec![self.maturity]
+    }
+
+    fn cashflows(&self, path: &Path) -> Vec<(usize, f64)> {
+        let perfs = performances(path, 0);
+        let perf = match self.rainbow_type {
+            RainbowType::BestOf => perfs.iter().fold(f64::NEG_INFINITY, |a, b| a.max(*b)),
+            RainbowType::WorstOf => perfs.iter().fold(f64::INFINITY, |a, b| a.min(*b)),
+        };
+        vec![(0, intrinsic(perf, self.strike, self.option_type))]
+    }
+}
+
+// スプレッドオプション。ペイオフは max(S_1(T) - S_2(T) - K, 0) (Putは逆)
+#[derive(Debug, Copy, Clone)]
+pub struct Spread {
+    pub option_type: OptionType,
+    pub strike: f64,
+    pub maturity: f64,
+}
+
+impl Payoff for Spread {
+    fn fixing_times(&self) -> Vec<f64> {
+        vec![self.maturity]
+    }
+
+    fn cashflows(&self, path: &Path) -> Vec<(usize, f64)> {
+        let state = path.fixing_state(0);
+        vec![(
+            0,
+            intrinsic(state[0] - state[1], self.strike, self.option_type),
+        )]
+    }
+}
+
+// Himalayaオプション
+// 各観測日に残っている資産のうちパフォーマンスが最大の資産を取り除き、そのパフォーマンスを記録する。
+// 最後の観測日に記録したパフォーマンスの平均に対するCallを支払う。観測日の数は資産数以下とする。
+#[derive(Debug, Clone)]
+pub struct Himalaya {
+    pub strike: f64,
+    pub observation_times: Vec<f64>,
+}
+
+impl Payoff for Himalaya {
+    fn fixing_times(&self) -> Vec<f64> {
+        self.observation_times.clone()
+    }
+
+    fn cashflows(&self, path: &Path) -> Vec<(usize, f64)> {
+        let num_obs = self.observation_times.len();
+        let mut remaining: Vec<usize> = (0..path.states[0].len()).collect();
+        let mut recorded = 0.0;
+        for k in 0..num_obs {
+            let perfs = performances(path, k);
+            let (pos, best) = remaining
+                .iter()
+                .enumerate()
+                .map(|(pos, asset)| (pos, perfs[*asset]))
+                .fold((0, f64::NEG_INFINITY), |a, b| if b.1 > a.1 { b } else { a });
+            recorded += best;
+            remaining.remove(pos);
+        }
+        vec![(
+            num_obs - 1,
+            intrinsic(recorded / num_obs as f64, self.strike, OptionType::Call),
+        )]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lattice::lattice_two_asset::{stulz_two_asset, TwoAssetInput, TwoAssetPayoff};
+    use crate::mc::engine::mc_price;
+    use crate::mc::test_util::sobol_rand_gen;
+    use ndarray::arr2;
+
+    #[test]
+    fn test_nearest_correlation() {
+        let corr = arr2(&[[1.0, 0.9, 0.7], [0.9, 1.0, 0.3], [0.7, 0.3, 1.0]]);
+        assert!(corr.cholesky(UPLO::Lower).is_err());
+        let repaired = nearest_correlation(&corr);
+        let (eigenvalues, _) = repaired.eigh(UPLO::Lower).unwrap();
+        assert!(eigenvalues.iter().all(|v| *v > 0.0));
+        assert!(repaired.diag().iter().all(|v| (v - 1.0).abs() < 1e-12));
+        assert!((&repaired - &corr).iter().all(|v| v.abs() < 0.15));
+        let chol = correlation_cholesky(&corr);
+        assert!((chol.dot(&chol.t()) - repaired)
+            .iter()
+            .all(|v| v.abs() < 1e-10));
+
+        // 正定値の相関行列はそのまま分解する。
+        let corr = arr2(&[[1.0, 0.5], [0.5, 1.0]]);
+        let chol = correlation_cholesky(&corr);
+        assert!((chol.dot(&chol.t()) - corr).iter().all(|v| v.abs() < 1e-12));
+    }
+
+    #[test]
+    fn test_rainbow_against_stulz() {
+        let input = TwoAssetInput {
+            underlying1: 100.0,
+            underlying2: 100.0,
+            vol1: 0.2,
+            vol2: 0.3,
+            div_yield1: 0.02,
+            div_yield2: 0.01,
+            corr: 0.5,
+            strike: 100.0,
+            zero_rate: 0.05,
+            term_annu: 1.0,
+        };
+        let gbm = MultiGbm::new(
+            vec![100.0, 100.0],
+            vec![0.02, 0.01],
+            vec![0.2, 0.3],
+            0.05,
+            &arr2(&[[1.0, 0.5], [0.5, 1.0]]),
+        );
+        let rand_gen = sobol_rand_gen(23);
+        let cases = [
+            (
+                RainbowType::BestOf,
+                OptionType::Call,
+                TwoAssetPayoff::CallOnMax,
+            ),
+            (
+                RainbowType::WorstOf,
+                OptionType::Call,
+                TwoAssetPayoff::CallOnMin,
+            ),
+            (
+                RainbowType::WorstOf,
+                OptionType::Put,
+                TwoAssetPayoff::PutOnMin,
+            ),
+        ];
+        for (rainbow_type, option_type, two_asset_payoff) in cases {
+            let rainbow = Rainbow {
+                rainbow_type,
+                option_type,
+                strike: 1.0,
+                maturity: 1.0,
+            };
+            let result = mc_price(&gbm, &rainbow, 1, 32768, &rand_gen);
+            let analytic = stulz_two_asset(&input, two_asset_payoff) / 100.0;
+            assert!((result.price - analytic).abs() < 4.0 * result.std_error + 1e-4);
+        }
+        // strikeが0のスプレッドオプションはExchange Option(Margrabe)
+        let spread = Spread {
+            option_type: OptionType::Call,
+            strike: 0.0,
+            maturity: 1.0,
+        };
+        let result = mc_price(&gbm, &spread, 1, 32768, &rand_gen);
+        let analytic = stulz_two_asset(&input, TwoAssetPayoff::Exchange);
+        assert!((result.price - analytic).abs() < 4.0 * result.std_error + 1e-2);
+    }
+
+    #[test]
+    fn test_basket_and_himalaya() {
+        // 相関1(半正定値)の同一の資産のバスケットは1資産のオプションに等しい。
+        let corr = arr2(&[[1.0, 1.0, 1.0], [1.0, 1.0, 1.0], [1.0, 1.0, 1.0]]);
+        let gbm = MultiGbm::new(vec![100.0; 3], vec![0.0; 3], vec![0.2; 3], 0.05, &corr);
+        let rand_gen = sobol_rand_gen(29);
+        let basket = Basket {
+            option_type: OptionType::Call,
+            strike: 100.0,
+            weights: vec![0.5, 0.3, 0.2],
+            maturity: 1.0,
+        };
+        let result = mc_price(&gbm, &basket, 1, 16384, &rand_gen);
+        // Black-Scholes(S = K = 100, σ = 0.2, r = 0.05, T = 1)
+        assert!((result.price - 10.450583572185565).abs() < 2e-2);
+
+        // 1回だけ観測するHimalayaはBest-of Callと同じ。
+        let himalaya = Himalaya {
+            strike: 1.0,
+            observation_times: vec![1.0],
+        };
+        let best_of = Rainbow {
+            rainbow_type: RainbowType::BestOf,
+            option_type: OptionType::Call,
+            strike: 1.0,
+            maturity: 1.0,
+        };
+        let gbm = MultiGbm::new(
+            vec![100.0; 3],
+            vec![0.0; 3],
+            vec![0.2; 3],
+            0.05,
+            &arr2(&[[1.0, 0.3, 0.3], [0.3, 1.0, 0.3], [0.3, 0.3, 1.0]]),
+        );
+        let himalaya_price = mc_price(&gbm, &himalaya, 4, 4096, &rand_gen).price;
+        let best_of_price = mc_price(&gbm, &best_of, 4, 4096, &rand_gen).price;
+        assert!((himalaya_price - best_of_price).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,217 @@
+use rand_chacha::ChaCha8Rng;
+use rand_distr::{Distribution, Poisson, StandardNormal};
+
+/* パスの生成モデル
+状態変数はモデルごとに決まり、state[0]が観測する値(株価モデルでは原資産価格、短期金利モデルでは短期金利)。
+エンジンが時間グリッドに沿ってevolveを呼び、short_rateを台形公式で積分してディスカウントファクターを求める。
+短期金利の積分を状態変数と同時に厳密にサンプリングできるモデルは、step_rate_integralでその値を返す。 */
+
+pub trait PathGenerator: Sync {
+    /// 1ステップあたりに使う標準正規乱数の数
+    fn num_factors(&self) -> usize;
+
+    /// 時刻0の状態変数
+    fn initial_state(&self) -> Vec<f64>;
+
+    /// 状態変数をtimeからtime + delta_tまで進めます。
+    /// * `state` - 状態変数
+    /// * `time` - 現在の時刻
+    /// * `delta_t` - ステップの幅
+    /// * `normals` - 標準正規乱数(num_factors個)
+    /// * `rng` - ジャンプなど正規乱数以外に使う乱数生成器
+    fn evolve(
+        &self,
+        state: &mut [f64],
+        time: f64,
+        delta_t: f64,
+        normals: &[f64],
+        rng: &mut ChaCha8Rng,
+    );
+
+    /// ディスカウントに使う短期金利
+    /// * `state` - 状態変数
+    /// * `time` - 時刻
+    fn short_rate(&self, state: &[f64], time: f64) -> f64;
+
+    /// timeからのステップでのstate[0]の対数のボラティリティ(ステップ内で一定とみなす)。
+    /// ブラウン橋による連続観測の補正に使い、対数正規の拡散でないモデルはNoneを返します。
+    /// * `state` - 状態変数
+    /// * `time` - 時刻
+    fn log_vol(&self, _state: &[f64], _time: f64) -> Option<f64> {
+        None
+    }
+
+    /// 直前のevolveのステップでの短期金利の積分 ∫r(u) du。
+    /// 状態変数と同時に厳密にサンプリングするモデルが返し、Noneならエンジンが台形公式で近似します。
+    /// * `state` - evolve後の状態変数
+    fn step_rate_integral(&self, _state: &[f64]) -> Option<f64> {
+        None
+    }
+}
+
+// 幾何ブラウン運動(対数価格で厳密にシミュレーションする)
+#[derive(Debug, Copy, Clone)]
+pub struct Gbm {
+    pub underlying: f64,
+    pub zero_rate: f64,
+    pub div_yield: f64,
+    pub vol: f64,
+}
+
+impl PathGenerator for Gbm {
+    fn num_factors(&self) -> usize {
+        1
+    }
+
+    fn initial_state(&self) -> Vec<f64> {
+        vec![self.underlying]
+    }
+
+    fn evolve(&self, state: &mut [f64], _: f64, delta_t: f64, normals: &[f64], _: &mut ChaCha8Rng) {
+        state[0] *= ((self.zero_rate - self.div_yield - 0.5 * self.vol.powi(2)) * delta_t
+            + self.vol * delta_t.sqrt() * normals[0])
+            .exp();
+    }
+
+    fn short_rate(&self, _: &[f64], _: f64) -> f64 {
+        self.zero_rate
+    }
+
+    fn log_vol(&self, _: &[f64], _: f64) -> Option<f64> {
+        Some(self.vol)
+    }
+}
+
+// ローカルボラティリティ σ(S, t)(対数価格のEuler法)
+pub struct LocalVol {
+    pub underlying: f64,
+    pub zero_rate: f64,
+    pub div_yield: f64,
+    pub local_vol: Box<dyn Fn(f64, f64) -> f64 + Sync>, // (原資産価格, 時刻)に対するボラティリティ
+}
+
+impl PathGenerator for LocalVol {
+    fn num_factors(&self) -> usize {
+        1
+    }
+
+    fn initial_state(&self) -> Vec<f64> {
+        vec![self.underlying]
+    }
+
+    fn evolve(
+        &self,
+        state: &mut [f64],
+        time: f64,
+        delta_t: f64,
+        normals: &[f64],
+        _: &mut ChaCha8Rng,
+    ) {
+        let vol = (self.local_vol)(state[0], time);
+        state[0] *= ((self.zero_rate - self.div_yield - 0.5 * vol.powi(2)) * delta_t
+            + vol * delta_t.sqrt() * normals[0])
+            .exp();
+    }
+
+    fn short_rate(&self, _: &[f64], _: f64) -> f64 {
+        self.zero_rate
+    }
+
+    fn log_vol(&self, state: &[f64], time: f64) -> Option<f64> {
+        Some((self.local_vol)(state[0], time))
+    }
+}
+
+// Hestonモデル(分散はfull truncationのEuler法、価格は対数価格で進める)
+// dS/S = (r - q)dt + √v dW_1, dv = κ(θ - v)dt + ξ√v dW_2, dW_1 dW_2 = ρdt
+// 状態変数は[原資産価格, 分散]
+#[derive(Debug, Copy, Clone)]
+pub struct Heston {
+    pub underlying: f64,
+    pub zero_rate: f64,
+    pub div_yield: f64,
+    pub var0: f64,       // 分散の初期値
+    pub kappa: f64,      // 分散の平均回帰速度
+    pub theta: f64,      // 分散の長期平均
+    pub vol_of_var: f64, // 分散のボラティリティ
+    pub corr: f64,       // 価格と分散の相関
+}
+
+impl PathGenerator for Heston {
+    fn num_factors(&self) -> usize {
+        2
+    }
+
+    fn initial_state(&self) -> Vec<f64> {
+        vec![self.underlying, self.var0]
+    }
+
+    fn evolve(&self, state: &mut [f64], _: f64, delta_t: f64, normals: &[f64], _: &mut ChaCha8Rng) {
+        let var = state[1].max(0.0);
+        let z_var = normals[0];
+        let z_und = self.corr * normals[0] + (1.0 - self.corr.powi(2)).sqrt() * normals[1];
+        state[0] *= ((self.zero_rate - self.div_yield - 0.5 * var) * delta_t
+            + (var * delta_t).sqrt() * z_und)
+            .exp();
+        state[1] += self.kappa * (self.theta - var) * delta_t
+            + self.vol_of_var * (var * delta_t).sqrt() * z_var;
+    }
+
+    fn short_rate(&self, _: &[f64], _: f64) -> f64 {
+        self.zero_rate
+    }
+
+    fn log_vol(&self, state: &[f64], _: f64) -> Option<f64> {
+        Some(state[1].max(0.0).sqrt())
+    }
+}
+
+// Merton(1976)のジャンプ拡散モデル。ジャンプ幅の対数は正規分布N(jump_mean, jump_vol^2)
+#[derive(Debug, Copy, Clone)]
+pub struct MertonJump {
+    pub underlying: f64,
+    pub zero_rate: f64,
+    pub div_yield: f64,
+    pub vol: f64,
+    pub intensity: f64, // ジャンプの強度(年あたりの回数)
+    pub jump_mean: f64,
+    pub jump_vol: f64,
+}
+
+impl PathGenerator for MertonJump {
+    fn num_factors(&self) -> usize {
+        1
+    }
+
+    fn initial_state(&self) -> Vec<f64> {
+        vec![self.underlying]
+    }
+
+    fn evolve(
+        &self,
+        state: &mut [f64],
+        _: f64,
+        delta_t: f64,
+        normals: &[f64],
+        rng: &mut ChaCha8Rng,
+    ) {
+        // ジャンプの期待値を補正してマルチンゲールとする。
+        let compensator =
+            self.intensity * ((self.jump_mean + 0.5 * self.jump_vol.powi(2)).exp() - 1.0);
+        let mut log_return =
+            (self.zero_rate - self.div_yield - compensator - 0.5 * self.vol.powi(2)) * delta_t
+                + self.vol * delta_t.sqrt() * normals[0];
+        if self.intensity > 0.0 {
+            let num_jump = Poisson::new(self.intensity * delta_t).unwrap().sample(rng) as usize;
+            for _ in 0..num_jump {
+                let z: f64 = StandardNormal.sample(rng);
+                log_return += self.jump_mean + self.jump_vol * z;
+            }
+        }
+        state[0] *= log_return.exp();
+    }
+
+    fn short_rate(&self, _: &[f64], _: f64) -> f64 {
+        self.zero_rate
+    }
+}
@@ -0,0 +1,177 @@
+use super::engine::Path;
+
+/* パス依存のペイオフ
+fixing_timesで観測日と支払日を指定し、cashflowsはパスから(fixing_timesのインデックス, 割引前の金額)を返す。
+エンジンが各キャッシュフローをパスに沿ったディスカウントファクターで割り引く。 */
+
+pub trait Payoff: Sync {
+    /// 観測日と支払日(昇順)
+    fn fixing_times(&self) -> Vec<f64>;
+
+    /// パスに対するキャッシュフローを(fixing_timesのインデックス, 金額)で返します。
+    /// * `path` - パス
+    fn cashflows(&self, path: &Path) -> Vec<(usize, f64)>;
+
+    /// パスワイズ微分に使う、各キャッシュフローの金額の観測値(fixing(k))に関する偏微分を
+    /// (fixing_timesのインデックス, [∂金額/∂fixing(k)])で返します。微分できないペイオフはNoneを返します。
+    /// * `path` - パス
+    fn cashflow_gradients(&self, _path: &Path) -> Option<Vec<(usize, Vec<f64>)>> {
+        None
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum OptionType {
+    Call,
+    Put,
+}
+
+/// Call/Putの行使価値を返します。
+/// * `underlying` - 原資産価格
+/// * `strike` - 権利行使価格
+/// * `option_type` - Call/Put
+pub fn intrinsic(underlying: f64, strike: f64, option_type: OptionType) -> f64 {
+    match option_type {
+        OptionType::Call => (underlying - strike).max(0.0),
+        OptionType::Put => (strike - underlying).max(0.0),
+    }
+}
+
+/// Call/Putの行使価値の原資産価格に関する微分(権利行使価格ちょうどでは0)を返します。
+/// * `underlying` - 原資産価格
+/// * `strike` - 権利行使価格
+/// * `option_type` - Call/Put
+pub fn intrinsic_derivative(underlying: f64, strike: f64, option_type: OptionType) -> f64 {
+    match option_type {
+        OptionType::Call if underlying > strike => 1.0,
+        OptionType::Put if underlying < strike => -1.0,
+        _ => 0.0,
+    }
+}
+
+// European Option
+#[derive(Debug, Copy, Clone)]
+pub struct European {
+    pub option_type: OptionType,
+    pub strike: f64,
+    pub maturity: f64,
+}
+
+impl Payoff for European {
+    fn fixing_times(&self) -> Vec<f64> {
+        vec![self.maturity]
+    }
+
+    fn cashflows(&self, path: &Path) -> Vec<(usize, f64)> {
+        vec![(0, intrinsic(path.fixing(0), self.strike, self.option_type))]
+    }
+
+    fn cashflow_gradients(&self, path: &Path) -> Option<Vec<(usize, Vec<f64>)>> {
+        let slope = intrinsic_derivative(path.fixing(0), self.strike, self.option_type);
+        Some(vec![(0, vec![slope])])
+    }
+}
+
+// デジタルオプション(満期に原資産価格が権利行使価格を超えていれば(Putは下回っていれば)1を支払う)
+#[derive(Debug, Copy, Clone)]
+pub struct Digital {
+    pub option_type: OptionType,
+    pub strike: f64,
+    pub maturity: f64,
+}
+
+impl Payoff for Digital {
+    fn fixing_times(&self) -> Vec<f64> {
+        vec![self.maturity]
+    }
+
+    fn cashflows(&self, path: &Path) -> Vec<(usize, f64)> {
+        let in_the_money = match self.option_type {
+            OptionType::Call => path.fixing(0) > self.strike,
+            OptionType::Put => path.fixing(0) < self.strike,
+        };
+        vec![(0, if in_the_money { 1.0 } else { 0.0 })]
+    }
+}
+
+// 算術平均Asian Option。支払日は最後の観測日以降
+#[derive(Debug, Clone)]
+pub struct ArithmeticAsian {
+    pub option_type: OptionType,
+    pub strike: f64,
+    pub observation_times: Vec<f64>,
+    pub payment_time: f64,
+}
+
+impl Payoff for ArithmeticAsian {
+    fn fixing_times(&self) -> Vec<f64> {
+        let mut times = self.observation_times.clone();
+        times.push(self.payment_time);
+        times
+    }
+
+    fn cashflows(&self, path: &Path) -> Vec<(usize, f64)> {
+        let num = self.observation_times.len();
+        let average = (0..num).map(|k| path.fixing(k)).sum::<f64>() / num as f64;
+        vec![(num, intrinsic(average, self.strike, self.option_type))]
+    }
+
+    fn cashflow_gradients(&self, path: &Path) -> Option<Vec<(usize, Vec<f64>)>> {
+        let num = self.observation_times.len();
+        let average = (0..num).map(|k| path.fixing(k)).sum::<f64>() / num as f64;
+        let slope = intrinsic_derivative(average, self.strike, self.option_type) / num as f64;
+        let mut gradient = vec![slope; num];
+        gradient.push(0.0); // 支払日の観測値には依存しない
+        Some(vec![(num, gradient)])
+    }
+}
+
+// 離散観測の変動ストライクLookback Option
+// Call: S_T - min S, Put: max S - S_T (最後の観測日が満期)
+#[derive(Debug, Clone)]
+pub struct FloatingLookback {
+    pub option_type: OptionType,
+    pub observation_times: Vec<f64>,
+}
+
+impl Payoff for FloatingLookback {
+    fn fixing_times(&self) -> Vec<f64> {
+        self.observation_times.clone()
+    }
+
+    fn cashflows(&self, path: &Path) -> Vec<(usize, f64)> {
+        let last = self.observation_times.len() - 1;
+        let fixings = (0..last + 1).map(|k| path.fixing(k));
+        let payoff = match self.option_type {
+            OptionType::Call => path.fixing(last) - fixings.fold(f64::INFINITY, f64::min),
+            OptionType::Put => fixings.fold(f64::NEG_INFINITY, f64::max) - path.fixing(last),
+        };
+        vec![(last, payoff)]
+    }
+
+    fn cashflow_gradients(&self, path: &Path) -> Option<Vec<(usize, Vec<f64>)>> {
+        let last = self.observation_times.len() - 1;
+        let fixings: Vec<f64> = (0..last + 1).map(|k| path.fixing(k)).collect();
+        let extreme = |better: fn(f64, f64) -> bool| {
+            (0..last + 1).fold(0, |best, k| {
+                if better(fixings[k], fixings[best]) {
+                    k
+                } else {
+                    best
+                }
+            })
+        };
+        let mut gradient = vec![0.0; last + 1];
+        match self.option_type {
+            OptionType::Call => {
+                gradient[last] += 1.0;
+                gradient[extreme(|a, b| a < b)] -= 1.0;
+            }
+            OptionType::Put => {
+                gradient[extreme(|a, b| a > b)] += 1.0;
+                gradient[last] -= 1.0;
+            }
+        }
+        Some(vec![(last, gradient)])
+    }
+}
@@ -1,19 +1,196 @@
+use super::brownian_bridge::BrownianBridge;
+use super::halton::Halton;
+use super::sobol::{Sobol, JOE_KUO_DIRECTIONS};
+use crate::hull_white::math::{moro_inverse_std_normal_cdf, wichura_inverse_normal_cdf};
 use ndarray::Array1;
-use rand::prelude::*;
-use rand_distr::StandardNormal;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rand_distr::{Distribution, StandardNormal};
 use rayon::prelude::*;
 
-pub fn normal_rand() {
+/// 入れ子のシミュレーションやMLMCのレベルなど、用途ごとの乱数列のseedを元のseedから分けるためのマスク
+pub const STREAM_SEED_MASK: u64 = 0x94D0_49BB_1331_11EB;
+
+/// seedとストリーム番号から乱数生成器を返します。<br>
+/// ChaCha8はカウンターベースの乱数生成器で、ストリーム番号ごとに独立な乱数列となります。
+/// パスのインデックスをストリーム番号とすれば、スレッド数や実行順序によらず各パスの乱数は同一になります。
+/// * `seed` - シード
+/// * `stream` - ストリーム番号(パスのインデックスなど)
+pub fn stream_rng(seed: u64, stream: u64) -> ChaCha8Rng {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    rng.set_stream(stream);
+    rng
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum RandType {
+    Pseudo, // ChaCha8の擬似乱数
+    Sobol,  // Sobol列(scrambled)。1パスの乱数の数は組み込みの方向数の次元(21)まで
+    Halton, // Halton列(random shift)
+}
+
+// 一様乱数から標準正規乱数への変換
+#[derive(Debug, Copy, Clone)]
+pub enum InverseCdf {
+    Moro,
+    Wichura,
+}
+
+/// 乱数の設定です。QMCの場合もseedでランダム化するため、seedを変えた複数回の推定値から誤差を評価できます。
+#[derive(Debug, Copy, Clone)]
+pub struct RandGen {
+    pub rand_type: RandType,
+    pub seed: u64,
+    pub brownian_bridge: bool, // Brownian bridgeでパスを構築するか
+    pub inverse_cdf: InverseCdf,
+}
+
+/// パスごとの標準正規乱数を生成します。<br>
+/// 1パスの乱数は num_steps × num_factors 個で、z[step * num_factors + factor] の順に並びます。
+/// Brownian bridgeを使う場合、低食い違い量列の先頭のnum_factors次元が各ファクターの満期の値に対応します。
+pub struct NormalGen {
+    rand_gen: RandGen,
+    num_steps: usize,
+    num_factors: usize,
+    sobol: Option<Sobol>,
+    halton: Option<Halton>,
+    bridge: Option<BrownianBridge>,
+}
+
+impl NormalGen {
+    /// Sobol列で1パスの乱数の数(times.len() × num_factors)が組み込みの方向数の次元を超える場合はpanicします。
+    /// * `rand_gen` - 乱数の設定
+    /// * `times` - 時間グリッド(時刻0を含まない)。Brownian bridgeで使用する。
+    /// * `num_factors` - 1時点あたりの乱数の数
+    pub fn new(rand_gen: &RandGen, times: &[f64], num_factors: usize) -> Self {
+        let num_steps = times.len();
+        let dim = num_steps * num_factors;
+        // QMCのランダム化には、パスの乱数と重ならないストリームを使う。
+        let mut rng = stream_rng(rand_gen.seed, u64::MAX);
+        let (mut sobol, mut halton) = (None, None);
+        match rand_gen.rand_type {
+            RandType::Pseudo => {}
+            RandType::Sobol => {
+                // 足りない次元を擬似乱数で補うとSobol列と言えなくなるため、エラーとする。
+                let max_dim = Sobol::max_dim(JOE_KUO_DIRECTIONS);
+                if dim > max_dim {
+                    panic!(
+                        "Sobol direction numbers are available up to {} dimensions, but {} time steps x {} factors = {} are required",
+                        max_dim, num_steps, num_factors, dim
+                    );
+                }
+                let mut gen = Sobol::new(dim);
+                gen.scramble(&mut rng);
+                sobol = Some(gen);
+            }
+            RandType::Halton => {
+                let mut gen = Halton::new(dim);
+                gen.randomize(&mut rng);
+                halton = Some(gen);
+            }
+        }
+        Self {
+            rand_gen: *rand_gen,
+            num_steps,
+            num_factors,
+            sobol,
+            halton,
+            bridge: rand_gen.brownian_bridge.then(|| BrownianBridge::new(times)),
+        }
+    }
+
+    /// path_idx番目のパスの標準正規乱数を返します。
+    /// * `path_idx` - パスのインデックス
+    pub fn normals(&self, path_idx: usize) -> Vec<f64> {
+        let dim = self.num_steps * self.num_factors;
+        let mut rng = stream_rng(self.rand_gen.seed, path_idx as u64);
+        let inverse_cdf = |u: f64| match self.rand_gen.inverse_cdf {
+            InverseCdf::Moro => moro_inverse_std_normal_cdf(u),
+            InverseCdf::Wichura => wichura_inverse_normal_cdf(u),
+        };
+        // 低食い違い量列の部分。[0, 1)の端点を避けるため区間の中点に写す。
+        let mut normals: Vec<f64> = match (&self.sobol, &self.halton) {
+            (Some(sobol), _) => sobol
+                .point_int(path_idx)
+                .iter()
+                .map(|x| inverse_cdf((*x as f64 + 0.5) / 2f64.powi(32)))
+                .collect(),
+            (_, Some(halton)) => halton
+                .point(path_idx + 1)
+                .iter()
+                .map(|u| inverse_cdf(u.max(f64::EPSILON)))
+                .collect(),
+            _ => Vec::with_capacity(dim),
+        };
+        while normals.len() < dim {
+            normals.push(StandardNormal.sample(&mut rng));
+        }
+
+        match &self.bridge {
+            None => normals,
+            Some(bridge) => {
+                // 低食い違い量列のj次元目をファクター j % num_factors の j / num_factors 番目の構築順に割り当てる。
+                let mut result = vec![0.0; dim];
+                for factor in 0..self.num_factors {
+                    let factor_normals: Vec<f64> = (0..self.num_steps)
+                        .map(|order| normals[order * self.num_factors + factor])
+                        .collect();
+                    for (step, z) in bridge.transform(&factor_normals).iter().enumerate() {
+                        result[step * self.num_factors + factor] = *z;
+                    }
+                }
+                result
+            }
+        }
+    }
+}
+
+pub fn normal_rand(seed: u64) {
     const NUM: usize = 1000000;
+    const CHUNK: usize = 1000; // 1つのストリームで生成する乱数の数
     let mut norm_rands: Array1<f64> = Array1::zeros(NUM);
-    norm_rands.par_mapv_inplace(|_| StandardNormal.sample(&mut thread_rng()));
-    let mean = norm_rands.par_iter().sum::<f64>() / NUM as f64;
-    let vol = (norm_rands
-        .par_iter()
-        .map(|x| (x - mean).powi(2))
-        .sum::<f64>()
-        / NUM as f64)
-        .sqrt();
+    norm_rands
+        .as_slice_mut()
+        .unwrap()
+        .par_chunks_mut(CHUNK)
+        .enumerate()
+        .for_each(|(idx, chunk)| {
+            let mut rng = stream_rng(seed, idx as u64);
+            for x in chunk.iter_mut() {
+                *x = StandardNormal.sample(&mut rng);
+            }
+        });
+    // 浮動小数点の和は順序に依存するため、再現性のため逐次に足し合わせる。
+    let mean = norm_rands.iter().sum::<f64>() / NUM as f64;
+    let vol = (norm_rands.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / NUM as f64).sqrt();
     println!("mean: {}", mean);
     println!("vol: {}", vol);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mc::test_util::{rand_gen, sobol_rand_gen};
+
+    #[test]
+    fn test_sobol_dimension() {
+        // 組み込みの方向数の次元(21)までのSobol列と、次元の上限がないHalton列
+        let times: Vec<f64> = (1..13).map(|i| i as f64 / 12.0).collect();
+        let max_dim = Sobol::max_dim(JOE_KUO_DIRECTIONS);
+        let qmc = NormalGen::new(&sobol_rand_gen(3), &times[..7], 3);
+        assert_eq!(qmc.normals(5).len(), max_dim);
+        let halton = RandGen {
+            rand_type: RandType::Halton,
+            ..rand_gen(3)
+        };
+        assert_eq!(NormalGen::new(&halton, &times, 2).normals(5).len(), 24);
+    }
+
+    #[test]
+    #[should_panic(expected = "Sobol direction numbers are available up to 21 dimensions")]
+    fn test_sobol_dimension_exceeded() {
+        // 21次元を超える12ステップ × 2ファクターの24次元はエラー
+        let times: Vec<f64> = (1..13).map(|i| i as f64 / 12.0).collect();
+        NormalGen::new(&sobol_rand_gen(3), &times, 2);
+    }
+}
@@ -0,0 +1,196 @@
+use rand::Rng;
+
+/* Sobol列(Joe and Kuo(2008)の方向数)
+1次元目は方向数 v_k = 2^{-k} (van der Corput列)、2次元目以降は原始多項式と初期値m_kから
+v_k = v_{k-s} ^ (v_{k-s} >> s) ^ a_1 v_{k-1} ^ ... ^ a_{s-1} v_{k-s+1} で方向数を生成する。
+点はGray codeを使って x_n = g_1 v_1 ^ g_2 v_2 ^ ... (g = n ^ (n >> 1)) で求める。 */
+
+const BITS: usize = 32;
+
+// new-joe-kuo-6.21201 の先頭部分(2次元目～21次元目)。書式は "d s a m_1 m_2 ..."
+// これより高い次元が必要な場合は、Joe and Kuoが公開しているファイル全体をSobol::from_joe_kuoに渡す。
+pub const JOE_KUO_DIRECTIONS: &str = "d s a m_i
+2 1 0 1
+3 2 1 1 3
+4 3 1 1 3 1
+5 3 2 1 1 1
+6 4 1 1 1 3 3
+7 4 4 1 3 5 13
+8 5 2 1 1 5 5 17
+9 5 4 1 1 5 5 5
+10 5 7 1 1 7 11 19
+11 5 11 1 1 5 1 1
+12 5 13 1 1 1 3 11
+13 5 14 1 3 5 5 31
+14 6 1 1 3 3 9 7 49
+15 6 13 1 1 1 15 21 21
+16 6 16 1 3 1 13 27 49
+17 6 19 1 1 1 15 7 5
+18 6 22 1 3 1 15 13 25
+19 6 25 1 1 5 5 19 61
+20 7 1 1 3 7 11 23 15 103
+21 7 4 1 3 7 13 13 15 69
+";
+
+#[derive(Clone, Debug)]
+pub struct Sobol {
+    directions: Vec<[u32; BITS]>, // 各次元の方向数(2^32倍した整数)
+    shift: Vec<u32>,              // digital shift(スクランブルしない場合は0)
+}
+
+impl Sobol {
+    /// 組み込みの方向数でSobol列を生成します。21次元まで使用できます。
+    /// * `dim` - 次元
+    pub fn new(dim: usize) -> Self {
+        Self::from_joe_kuo(JOE_KUO_DIRECTIONS, dim)
+    }
+
+    /// Joe and Kuoの方向数ファイルの書式の文字列からSobol列を生成します。<br>
+    /// 1行目はヘッダーとして読み飛ばします。
+    /// * `table` - 方向数ファイルの内容
+    /// * `dim` - 次元
+    pub fn from_joe_kuo(table: &str, dim: usize) -> Self {
+        let mut directions: Vec<[u32; BITS]> = Vec::with_capacity(dim);
+        if dim > 0 {
+            let mut first = [0; BITS];
+            for (k, v) in first.iter_mut().enumerate() {
+                *v = 1 << (BITS - 1 - k);
+            }
+            directions.push(first);
+        }
+        for line in table.lines().skip(1).take(dim.saturating_sub(1)) {
+            let nums: Vec<u32> = line
+                .split_whitespace()
+                .map(|s| s.parse().expect("invalid direction number"))
+                .collect();
+            let (s, a, m) = (nums[1] as usize, nums[2], &nums[3..]);
+            let mut v = [0; BITS];
+            for k in 0..BITS {
+                v[k] = if k < s {
+                    m[k] << (BITS - 1 - k)
+                } else {
+                    let mut val = v[k - s] ^ (v[k - s] >> s);
+                    for i in 1..s {
+                        if (a >> (s - 1 - i)) & 1 == 1 {
+                            val ^= v[k - i];
+                        }
+                    }
+                    val
+                };
+            }
+            directions.push(v);
+        }
+        assert!(
+            directions.len() == dim,
+            "direction numbers are available up to {} dimensions",
+            directions.len()
+        );
+        Self {
+            directions,
+            shift: vec![0; dim],
+        }
+    }
+
+    /// 方向数の最大の次元を返します。
+    pub fn max_dim(table: &str) -> usize {
+        table
+            .lines()
+            .skip(1)
+            .filter(|l| !l.trim().is_empty())
+            .count()
+            + 1
+    }
+
+    /// linear matrix scrambling(Matoušek(1998))とrandom digital shiftでランダム化します。<br>
+    /// 方向数に下三角のランダムな行列を掛けるため、Owen scramblingと同様に(t,m,s)-netの性質が保たれます。
+    /// * `rng` - 乱数生成器
+    pub fn scramble<R: Rng>(&mut self, rng: &mut R) {
+        for (v, shift) in self.directions.iter_mut().zip(self.shift.iter_mut()) {
+            // 行iはビットi(上位から数える)に対して、上位のビットjのXORを足す。対角成分は1。
+            let rows: Vec<u32> = (0..BITS)
+                .map(|i| {
+                    let upper: u32 = if i == 0 {
+                        0
+                    } else {
+                        rng.gen::<u32>() & !(u32::MAX >> i)
+                    };
+                    upper | (1 << (BITS - 1 - i))
+                })
+                .collect();
+            for val in v.iter_mut() {
+                let mut scrambled = 0;
+                for (i, row) in rows.iter().enumerate() {
+                    if (*val & row).count_ones() % 2 == 1 {
+                        scrambled |= 1 << (BITS - 1 - i);
+                    }
+                }
+                *val = scrambled;
+            }
+            *shift = rng.gen();
+        }
+    }
+
+    /// index番目の点を2^32倍した整数で返します。
+    /// * `index` - 点のインデックス(0始まり)
+    pub fn point_int(&self, index: usize) -> Vec<u32> {
+        let gray = index ^ (index >> 1);
+        self.directions
+            .iter()
+            .zip(self.shift.iter())
+            .map(|(v, shift)| {
+                let mut x = *shift;
+                for (k, vk) in v.iter().enumerate() {
+                    if (gray >> k) & 1 == 1 {
+                        x ^= vk;
+                    }
+                }
+                x
+            })
+            .collect()
+    }
+
+    /// index番目の点を[0, 1)の値で返します。
+    /// * `index` - 点のインデックス(0始まり)
+    #[cfg(test)]
+    pub fn point(&self, index: usize) -> Vec<f64> {
+        self.point_int(index)
+            .iter()
+            .map(|x| *x as f64 / 2f64.powi(BITS as i32))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mc::rand_num::stream_rng;
+
+    #[test]
+    fn test_sobol_first_points() {
+        let sobol = Sobol::new(2);
+        let dim1 = [0.0, 0.5, 0.75, 0.25, 0.375, 0.875, 0.625, 0.125];
+        let dim2 = [0.0, 0.5, 0.25, 0.75, 0.375, 0.875, 0.125, 0.625];
+        for n in 0..8 {
+            assert_eq!(sobol.point(n), vec![dim1[n], dim2[n]]);
+        }
+    }
+
+    #[test]
+    fn test_sobol_stratification() {
+        // 先頭2^m点は各次元で長さ2^-mの区間に1点ずつ入る。スクランブル後も保たれる。
+        let m = 10;
+        let num = 1 << m;
+        let dim = Sobol::max_dim(JOE_KUO_DIRECTIONS);
+        let mut scrambled = Sobol::new(dim);
+        scrambled.scramble(&mut stream_rng(1, 0));
+        for sobol in [Sobol::new(dim), scrambled] {
+            let mut counts = vec![vec![0; num]; dim];
+            for n in 0..num {
+                for (d, x) in sobol.point_int(n).iter().enumerate() {
+                    counts[d][(*x >> (BITS - m)) as usize] += 1;
+                }
+            }
+            assert!(counts.iter().all(|c| c.iter().all(|n| *n == 1)));
+        }
+    }
+}
@@ -0,0 +1,413 @@
+use super::path_generator::PathGenerator;
+use crate::hull_white::math::{
+    inverse_std_normal_cdf, noncentral_chi_square_cdf, regularized_lower_gamma, std_normal_cdf,
+};
+use ndarray_linalg::c64;
+use rand_chacha::ChaCha8Rng;
+use std::f64::consts::PI;
+
+// QEスキームで二次関数近似と指数近似を切り替えるψの閾値(Andersen 2008)
+const PSI_CRITICAL: f64 = 1.5;
+
+// SABRの低バイアススキームで非心カイ二乗分布の逆関数の代わりに二次関数近似を使う吸収確率の上限
+const ABSORPTION_THRESHOLD: f64 = 1e-4;
+
+// 非心カイ二乗分布の逆関数を求める二分法の相対誤差
+const BISECTION_TOLERANCE: f64 = 1e-8;
+
+/// Andersen(2008)のQEスキームで分散の次の値を返します。<br>
+/// ψ = s^2 / m^2 が閾値以下では v' = a(b + Z)^2、それより大きければ確率pで0、それ以外は指数分布で近似します。
+/// 戻り値は(次の値, マルチンゲール補正に使う近似のパラメータ)です。
+/// * `mean` - 次の値の条件付き期待値m
+/// * `var` - 次の値の条件付き分散s^2
+/// * `normal` - 標準正規乱数(指数近似では一様乱数 Φ(Z) として使う)
+fn quadratic_exponential(mean: f64, var: f64, normal: f64) -> (f64, QeBranch) {
+    let psi = var / mean.powi(2);
+    if psi <= PSI_CRITICAL {
+        let b_sq = 2.0 / psi - 1.0 + (2.0 / psi).sqrt() * (2.0 / psi - 1.0).sqrt();
+        let a = mean / (1.0 + b_sq);
+        (
+            a * (b_sq.sqrt() + normal).powi(2),
+            QeBranch::Quadratic { a, b_sq },
+        )
+    } else {
+        let p = (psi - 1.0) / (psi + 1.0);
+        let beta = (1.0 - p) / mean;
+        let uniform = std_normal_cdf(normal);
+        let next = if uniform <= p {
+            0.0
+        } else {
+            ((1.0 - p) / (1.0 - uniform)).ln() / beta
+        };
+        (next, QeBranch::Exponential { p, beta })
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+enum QeBranch {
+    Quadratic { a: f64, b_sq: f64 },
+    Exponential { p: f64, beta: f64 },
+}
+
+// Hestonモデル(分散はAndersen(2008)のQuadratic-Exponentialスキーム)
+// dS/S = (r - q)dt + √v dW_1, dv = κ(θ - v)dt + ξ√v dW_2, dW_1 dW_2 = ρdt
+// 対数価格は分散の積分を台形公式(γ1 = γ2 = 1/2)で近似して進める。ξ > 0 であること。
+// martingale_correctionがtrueのとき、割引後の価格がマルチンゲールとなるようにK0を補正する。
+// 状態変数は[原資産価格, 分散]
+#[derive(Debug, Copy, Clone)]
+pub struct HestonQe {
+    pub underlying: f64,
+    pub zero_rate: f64,
+    pub div_yield: f64,
+    pub var0: f64,       // 分散の初期値
+    pub kappa: f64,      // 分散の平均回帰速度
+    pub theta: f64,      // 分散の長期平均
+    pub vol_of_var: f64, // 分散のボラティリティ
+    pub corr: f64,       // 価格と分散の相関
+    pub martingale_correction: bool,
+}
+
+impl PathGenerator for HestonQe {
+    fn num_factors(&self) -> usize {
+        2
+    }
+
+    fn initial_state(&self) -> Vec<f64> {
+        vec![self.underlying, self.var0]
+    }
+
+    fn evolve(&self, state: &mut [f64], _: f64, delta_t: f64, normals: &[f64], _: &mut ChaCha8Rng) {
+        let (kappa, theta, xi, rho) = (self.kappa, self.theta, self.vol_of_var, self.corr);
+        let var = state[1];
+        let decay = (-kappa * delta_t).exp();
+        let mean = theta + (var - theta) * decay;
+        let var_var = var * xi.powi(2) * decay * (1.0 - decay) / kappa
+            + theta * xi.powi(2) * (1.0 - decay).powi(2) / (2.0 * kappa);
+        let (next_var, branch) = quadratic_exponential(mean, var_var, normals[0]);
+
+        let (gamma1, gamma2) = (0.5, 0.5);
+        let k1 = gamma1 * delta_t * (kappa * rho / xi - 0.5) - rho / xi;
+        let k2 = gamma2 * delta_t * (kappa * rho / xi - 0.5) + rho / xi;
+        let k3 = gamma1 * delta_t * (1.0 - rho.powi(2));
+        let k4 = gamma2 * delta_t * (1.0 - rho.powi(2));
+        let mut k0 = -rho * kappa * theta * delta_t / xi;
+        if self.martingale_correction {
+            // E[exp(K0 + K1 v + K2 v' + (K3 v + K4 v') / 2)] = 1 となるK0 (モーメント母関数が存在するとき)
+            let coef = k2 + 0.5 * k4;
+            match branch {
+                QeBranch::Quadratic { a, b_sq } if coef < 0.5 / a => {
+                    k0 = -coef * b_sq * a / (1.0 - 2.0 * coef * a)
+                        + 0.5 * (1.0 - 2.0 * coef * a).ln()
+                        - (k1 + 0.5 * k3) * var;
+                }
+                QeBranch::Exponential { p, beta } if coef < beta => {
+                    k0 = -(p + beta * (1.0 - p) / (beta - coef)).ln() - (k1 + 0.5 * k3) * var;
+                }
+                _ => {}
+            }
+        }
+        state[0] *= ((self.zero_rate - self.div_yield) * delta_t
+            + k0
+            + k1 * var
+            + k2 * next_var
+            + (k3 * var + k4 * next_var).sqrt() * normals[1])
+            .exp();
+        state[1] = next_var;
+    }
+
+    fn short_rate(&self, _: &[f64], _: f64) -> f64 {
+        self.zero_rate
+    }
+
+    fn log_vol(&self, state: &[f64], _: f64) -> Option<f64> {
+        Some(state[1].sqrt())
+    }
+}
+
+/// Hestonモデルのヨーロピアン・コールの価格をLewis(2000)の公式で返します。<br>
+/// C = S e^{-qT} - √(SK) e^{-(r+q)T/2} / π ∫_0^∞ Re[e^{iux} φ(u - i/2)] / (u^2 + 1/4) du, x = ln(S/K) + (r - q)T<br>
+/// 特性関数φはGatheral(2006)の形(分岐の不連続が起きない形)で計算し、積分はSimpson公式で行います。
+/// * `model` - Hestonモデルのパラメータ
+/// * `strike` - 権利行使価格
+/// * `term` - 満期(年)
+pub fn heston_call(model: &HestonQe, strike: f64, term: f64) -> f64 {
+    let (kappa, theta, xi, rho) = (model.kappa, model.theta, model.vol_of_var, model.corr);
+    let i = c64::new(0.0, 1.0);
+    // ln(F_T / F_0) の特性関数
+    let char_fn = |z: c64| -> c64 {
+        let a = kappa - rho * xi * i * z;
+        let d = (a * a + xi.powi(2) * (i * z + z * z)).sqrt();
+        let g = (a - d) / (a + d);
+        let e = (-d * term).exp();
+        let c =
+            kappa * theta / xi.powi(2) * ((a - d) * term - 2.0 * ((1.0 - g * e) / (1.0 - g)).ln());
+        let d_coef = (a - d) / xi.powi(2) * (1.0 - e) / (1.0 - g * e);
+        (c + d_coef * model.var0).exp()
+    };
+    let x = (model.underlying / strike).ln() + (model.zero_rate - model.div_yield) * term;
+    let integrand = |u: f64| -> f64 {
+        ((i * u * x).exp() * char_fn(c64::new(u, -0.5))).re / (u.powi(2) + 0.25)
+    };
+    let (upper, num_interval) = (200.0, 4000); // 偶数
+    let h = upper / num_interval as f64;
+    let mut sum = integrand(0.0) + integrand(upper);
+    for k in 1..num_interval {
+        let weight = if k % 2 == 1 { 4.0 } else { 2.0 };
+        sum += weight * integrand(k as f64 * h);
+    }
+    let integral = sum * h / 3.0;
+    model.underlying * (-model.div_yield * term).exp()
+        - (model.underlying * strike).sqrt()
+            * (-0.5 * (model.zero_rate + model.div_yield) * term).exp()
+            / PI
+            * integral
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum SabrScheme {
+    LogEuler, // ボラティリティは厳密、フォワードは対数Euler法
+    LowBias,  // Chen, Oosterlee and van Weeren(2012)の低バイアススキーム
+}
+
+// SABRモデル(フォワード測度で割引なし)
+// dF = σ F^β dW_1, dσ = ν σ dW_2, dW_1 dW_2 = ρdt
+// LowBiasではボラティリティの積分を台形公式で近似し、ボラティリティのパスを条件としたフォワードを
+// 0で吸収されるCEV過程の遷移分布(非心カイ二乗分布)から生成する。ν > 0 であること。
+// 状態変数は[フォワード, ボラティリティ]
+#[derive(Debug, Copy, Clone)]
+pub struct Sabr {
+    pub fwd: f64,
+    pub beta: f64,
+    pub alpha: f64, // ボラティリティの初期値
+    pub rho: f64,
+    pub nu: f64, // ボラティリティのボラティリティ
+    pub scheme: SabrScheme,
+}
+
+impl Sabr {
+    /// ボラティリティのパスを条件として、0で吸収されるCEV過程の次のフォワードを返します。
+    /// * `fwd` - 現在のフォワード
+    /// * `vol` - 現在のボラティリティ
+    /// * `next_vol` - 次のボラティリティ
+    /// * `integrated_var` - ボラティリティの2乗の積分
+    /// * `normal` - 標準正規乱数
+    fn low_bias_step(
+        &self,
+        fwd: f64,
+        vol: f64,
+        next_vol: f64,
+        integrated_var: f64,
+        normal: f64,
+    ) -> f64 {
+        let (beta, rho) = (self.beta, self.rho);
+        let cond_var = (1.0 - rho.powi(2)) * integrated_var;
+        if beta >= 1.0 {
+            // 対数正規: ∫σdW_1 = (σ' - σ) / ν
+            return fwd
+                * (rho / self.nu * (next_vol - vol) - 0.5 * integrated_var
+                    + cond_var.sqrt() * normal)
+                    .exp();
+        }
+        let one_beta = 1.0 - beta;
+        // Z = F^{1-β} / (1-β) は dZ = σdW - βσ^2 / (2(1-β)Z) dt に従う。
+        // W_1の部分 ∫σdW_1 = (σ' - σ) / ν とそのドリフトをZの初期値に反映する。
+        let base = fwd.powf(one_beta) + one_beta * rho / self.nu * (next_vol - vol)
+            - 0.5 * beta * one_beta * rho.powi(2) * integrated_var / fwd.powf(one_beta);
+        if base <= 0.0 {
+            return 0.0;
+        }
+        // X = F^{2(1-β)} / ((1-β)^2 v) は次元 δ = (1-2β)/(1-β) の0で吸収される2乗Bessel過程
+        let scale = one_beta.powi(2) * cond_var;
+        let x0 = base.powi(2) / scale;
+        let half_dof = 0.5 / one_beta;
+        let absorption = 1.0 - regularized_lower_gamma(half_dof, 0.5 * x0);
+        let uniform = std_normal_cdf(normal);
+        if uniform <= absorption {
+            return 0.0;
+        }
+        let next_x = if absorption < ABSORPTION_THRESHOLD {
+            // 吸収がほぼ起きないときは次元 δ = (1-2β)/(1-β) の2乗Bessel過程の平均と分散を合わせた二次関数近似
+            let dim = (1.0 - 2.0 * beta) / one_beta;
+            let cond_normal = inverse_std_normal_cdf((uniform - absorption) / (1.0 - absorption));
+            quadratic_exponential(x0 + dim, 2.0 * (dim + 2.0 * x0), cond_normal).0
+        } else {
+            // P(F' <= K) = 1 - χ'^2(x0; 1/(1-β), y(K)) をyについて二分法で解く。
+            let prob = |y: f64| 1.0 - noncentral_chi_square_cdf(x0, 2.0 * half_dof, y);
+            let (mut lower, mut upper) = (0.0, x0 + 10.0);
+            while prob(upper) < uniform {
+                upper *= 2.0;
+            }
+            while upper - lower > BISECTION_TOLERANCE * upper {
+                let mid = 0.5 * (lower + upper);
+                if prob(mid) < uniform {
+                    lower = mid;
+                } else {
+                    upper = mid;
+                }
+            }
+            0.5 * (lower + upper)
+        };
+        (scale * next_x).powf(0.5 / one_beta)
+    }
+}
+
+impl PathGenerator for Sabr {
+    fn num_factors(&self) -> usize {
+        2
+    }
+
+    fn initial_state(&self) -> Vec<f64> {
+        vec![self.fwd, self.alpha]
+    }
+
+    fn evolve(&self, state: &mut [f64], _: f64, delta_t: f64, normals: &[f64], _: &mut ChaCha8Rng) {
+        let (fwd, vol) = (state[0], state[1]);
+        let next_vol =
+            vol * (self.nu * delta_t.sqrt() * normals[0] - 0.5 * self.nu.powi(2) * delta_t).exp();
+        if fwd > 0.0 {
+            state[0] = match self.scheme {
+                SabrScheme::LogEuler => {
+                    let local_vol = vol * fwd.powf(self.beta - 1.0);
+                    let z = self.rho * normals[0] + (1.0 - self.rho.powi(2)).sqrt() * normals[1];
+                    fwd * (local_vol * delta_t.sqrt() * z - 0.5 * local_vol.powi(2) * delta_t).exp()
+                }
+                SabrScheme::LowBias => {
+                    let integrated_var = 0.5 * (vol.powi(2) + next_vol.powi(2)) * delta_t;
+                    self.low_bias_step(fwd, vol, next_vol, integrated_var, normals[1])
+                }
+            };
+        }
+        state[1] = next_vol;
+    }
+
+    fn short_rate(&self, _: &[f64], _: f64) -> f64 {
+        0.0
+    }
+
+    fn log_vol(&self, state: &[f64], _: f64) -> Option<f64> {
+        // 吸収後(F = 0)はパスが動かない。
+        (state[0] > 0.0).then(|| state[1] * state[0].powf(self.beta - 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bs::black_scholes::{self, black_scholes, implied_vol};
+    use crate::mc::engine::mc_price;
+    use crate::mc::payoff::{European, OptionType};
+    use crate::mc::rand_num::{RandGen, RandType};
+    use crate::mc::test_util::{rand_gen, sobol_rand_gen};
+    use crate::sabr::sabr_lognormal::sabr_lognormal;
+
+    const SEED: u64 = 7;
+
+    fn heston() -> HestonQe {
+        HestonQe {
+            underlying: 100.0,
+            zero_rate: 0.03,
+            div_yield: 0.01,
+            var0: 0.04,
+            kappa: 0.5,
+            theta: 0.04,
+            vol_of_var: 1.0,
+            corr: -0.9,
+            martingale_correction: true,
+        }
+    }
+
+    #[test]
+    fn test_heston_call_bs_limit() {
+        // 分散のボラティリティが小さく分散が長期平均に等しければBlack-Scholesに近づく。
+        let model = HestonQe {
+            vol_of_var: 1e-3,
+            corr: 0.0,
+            ..heston()
+        };
+        let input = black_scholes::CalcInput {
+            zero_rate: 0.03,
+            vol: 0.2,
+            term_annu: 2.0,
+            strike: 110.0,
+            underlying: 100.0 * (-0.01_f64 * 2.0).exp(),
+        };
+        let expected = black_scholes(&input, black_scholes::OptionType::Call);
+        assert!((heston_call(&model, 110.0, 2.0) - expected).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_heston_qe_against_semi_analytic() {
+        // Andersen(2008)の厳しいケース(ξ = 1, ρ = -0.9)で1ステップ0.25年
+        // 20ステップ × 2ファクターはSobol列の次元を超えるため、擬似乱数を使う。
+        let model = heston();
+        for strike in [80.0, 100.0, 120.0] {
+            let call = European {
+                option_type: OptionType::Call,
+                strike,
+                maturity: 5.0,
+            };
+            let result = mc_price(&model, &call, 20, 50000, &rand_gen(SEED));
+            let expected = heston_call(&model, strike, 5.0);
+            assert!((result.price - expected).abs() < 4.0 * result.std_error + 0.1);
+        }
+    }
+
+    #[test]
+    fn test_sabr_against_hagan() {
+        let (fwd, term, beta, alpha, rho, nu) = (0.04, 1.0, 0.5, 0.04, -0.3, 0.4);
+        let schemes = [(SabrScheme::LogEuler, 50), (SabrScheme::LowBias, 10)];
+        // 50ステップ × 2ファクターはSobol列の次元を超えるため、Halton列を使う。
+        let rand_gen = RandGen {
+            rand_type: RandType::Halton,
+            ..sobol_rand_gen(SEED)
+        };
+        for (scheme, time_step) in schemes {
+            let model = Sabr {
+                fwd,
+                beta,
+                alpha,
+                rho,
+                nu,
+                scheme,
+            };
+            for strike in [0.03, 0.04, 0.05] {
+                let call = European {
+                    option_type: OptionType::Call,
+                    strike,
+                    maturity: term,
+                };
+                let result = mc_price(&model, &call, time_step, 20000, &rand_gen);
+                let input = black_scholes::CalcInput {
+                    zero_rate: 0.0,
+                    vol: 0.0,
+                    term_annu: term,
+                    strike,
+                    underlying: fwd,
+                };
+                let mc_vol = implied_vol(result.price, &input, black_scholes::OptionType::Call);
+                let hagan = sabr_lognormal(strike, fwd, term, beta, alpha, rho, nu);
+                assert!((mc_vol - hagan).abs() < 3e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sabr_low_bias_absorption() {
+        // フォワードが0に吸収されやすい設定でもフォワードはマルチンゲール(行使価格0のコールがフォワードに一致)
+        let model = Sabr {
+            fwd: 0.005,
+            beta: 0.2,
+            alpha: 0.03,
+            rho: -0.5,
+            nu: 0.3,
+            scheme: SabrScheme::LowBias,
+        };
+        let forward = European {
+            option_type: OptionType::Call,
+            strike: 0.0,
+            maturity: 5.0,
+        };
+        let result = mc_price(&model, &forward, 10, 10000, &sobol_rand_gen(SEED));
+        assert!((result.price - model.fwd).abs() < 4.0 * result.std_error);
+    }
+}
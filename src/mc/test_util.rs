@@ -0,0 +1,38 @@
+use super::path_generator::Gbm;
+use super::rand_num::{InverseCdf, RandGen, RandType};
+
+/* テストで共通に使う乱数の設定とパスの生成モデル */
+
+/// 擬似乱数の設定を返します。
+/// * `seed` - 乱数のシード
+pub fn rand_gen(seed: u64) -> RandGen {
+    RandGen {
+        rand_type: RandType::Pseudo,
+        seed,
+        brownian_bridge: false,
+        inverse_cdf: InverseCdf::Moro,
+    }
+}
+
+/// Sobol列とBrownian bridgeの準乱数の設定を返します。
+/// * `seed` - 乱数のシード
+pub fn sobol_rand_gen(seed: u64) -> RandGen {
+    RandGen {
+        rand_type: RandType::Sobol,
+        brownian_bridge: true,
+        ..rand_gen(seed)
+    }
+}
+
+/// 原資産価格100のGBMを返します。
+/// * `zero_rate` - ゼロレート
+/// * `div_yield` - 配当利回り
+/// * `vol` - ボラティリティ
+pub fn gbm(zero_rate: f64, div_yield: f64, vol: f64) -> Gbm {
+    Gbm {
+        underlying: 100.0,
+        zero_rate,
+        div_yield,
+        vol,
+    }
+}
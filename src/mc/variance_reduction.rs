@@ -0,0 +1,304 @@
+use super::mc_result::{mean_std_error, McResult};
+use super::monte_carlo::CalcInput;
+use super::rand_num::{NormalGen, RandGen};
+use crate::hull_white::math::std_normal_cdf;
+use ndarray::{Array1, Array2};
+use ndarray_linalg::Solve;
+use rayon::prelude::*;
+use std::time::Instant;
+
+/* 分散減少法
+各オプションは組み合わせて使用できる。
+・antithetic: 乱数zと-zのパスの平均を1サンプルとする。
+・control variate: 期待値が解析的にわかる量Xとの回帰 Y - β(X - E[X]) で分散を減らす。
+・moment matching: 各時点の乱数の標本平均を0、標本分散を1に補正する。
+・importance sampling: 乱数にθを足したパスでシミュレーションし、尤度比 exp(-θz + θ^2/2) を掛ける。 */
+
+// moment matchingで標準誤差を求めるためのバッチ数
+const NUM_BATCH: usize = 20;
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct VarianceReduction {
+    pub antithetic: bool,
+    pub geometric_control: bool, // 幾何平均Asian Callをcontrol variateとする
+    pub stock_control: bool,     // 最終時点の原資産価格(割引後)をcontrol variateとする
+    pub moment_matching: bool,
+    pub drift_shift: f64, // importance samplingで各時点の乱数に足す値(0で不使用)
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct VarianceReductionResult {
+    pub result: McResult, // 分散減少法を使った計算結果(計算時間にplain MCは含まない)
+    pub plain_std_error: f64, // 同じパス数のplain MCの標準誤差
+    pub variance_reduction_factor: f64, // plain MCとの推定量の分散の比
+}
+
+/// 離散観測の幾何平均Asian Callの理論価格を返します。<br>
+/// 観測時点はmc_bs_asian_callと同じ t_i = iΔt (i = 0, ..., time_step - 1) とします。
+/// * `input` - 計算のインプット
+/// * `time_step` - 時間方向のステップ数
+pub fn geometric_asian_call(input: &CalcInput, time_step: usize) -> f64 {
+    let CalcInput {
+        zero_rate,
+        vol,
+        term_annu,
+        strike,
+        underlying,
+    } = *input;
+    let delta_t = term_annu / time_step as f64;
+    let num = time_step as f64;
+    // ln G = ln S_0 + (r - σ^2/2) Σt_i / n + σ ΣW(t_i) / n
+    let time_sum: f64 = (0..time_step).map(|i| i as f64 * delta_t).sum();
+    let min_sum: f64 = (0..time_step)
+        .map(|i| {
+            (0..time_step)
+                .map(|j| i.min(j) as f64 * delta_t)
+                .sum::<f64>()
+        })
+        .sum();
+    let mean = underlying.ln() + (zero_rate - 0.5 * vol.powi(2)) * time_sum / num;
+    let std_dev = vol * min_sum.sqrt() / num;
+    let d2 = (mean - strike.ln()) / std_dev;
+    let d1 = d2 + std_dev;
+    (-zero_rate * term_annu).exp()
+        * ((mean + 0.5 * std_dev.powi(2)).exp() * std_normal_cdf(d1) - strike * std_normal_cdf(d2))
+}
+
+/// 分散減少法を使った算術平均Asian Callの価格を返します。<br>
+/// moment matchingを使う場合はサンプルが独立でなくなるため、バッチに分けてバッチ平均から標準誤差を求めます。
+/// * `input` - 計算のインプット
+/// * `time_step` - 時間方向のステップ数
+/// * `num_path` - パス数(antitheticの場合はペアの数の2倍)
+/// * `rand_gen` - 乱数の設定
+/// * `vr` - 分散減少法の設定
+pub fn mc_bs_asian_call_vr(
+    input: &CalcInput,
+    time_step: usize,
+    num_path: usize,
+    rand_gen: &RandGen,
+    vr: &VarianceReduction,
+) -> VarianceReductionResult {
+    let start = Instant::now();
+    let CalcInput {
+        zero_rate,
+        vol,
+        term_annu,
+        strike,
+        underlying,
+    } = *input;
+    let delta_t = term_annu / time_step as f64;
+    let df = (-zero_rate * term_annu).exp();
+    let times: Vec<f64> = (1..time_step).map(|i| i as f64 * delta_t).collect();
+    let normal_gen = NormalGen::new(rand_gen, &times, 1);
+    let num_sample = if vr.antithetic {
+        num_path / 2
+    } else {
+        num_path
+    };
+
+    let mut normals: Vec<Vec<f64>> = (0..num_sample)
+        .into_par_iter()
+        .map(|path_idx| normal_gen.normals(path_idx))
+        .collect();
+    // moment matchingはバッチごとに行い、標準誤差はバッチ平均のばらつきから求める。
+    let batch_size = if vr.moment_matching {
+        (num_sample / NUM_BATCH).max(1)
+    } else {
+        1
+    };
+    if vr.moment_matching {
+        for batch in normals.chunks_mut(batch_size) {
+            moment_match(batch, vr.antithetic);
+        }
+    }
+
+    // 乱数から(ペイオフ, 幾何平均のペイオフ, 最終時点の原資産価格)に尤度比を掛けたものを返す。
+    let theta = vr.drift_shift;
+    let simulate = |normals: &[f64], sign: f64| -> [f64; 3] {
+        let mut und = underlying;
+        let (mut sum, mut log_sum) = (underlying, underlying.ln());
+        let mut log_weight = 0.0;
+        for z in normals.iter() {
+            let z = sign * z + theta;
+            log_weight += -theta * z + 0.5 * theta.powi(2);
+            und *= ((zero_rate - 0.5 * vol.powi(2)) * delta_t + vol * delta_t.sqrt() * z).exp();
+            sum += und;
+            log_sum += und.ln();
+        }
+        let weight = log_weight.exp();
+        let num = time_step as f64;
+        [
+            weight * df * (sum / num - strike).max(0.0),
+            weight * df * ((log_sum / num).exp() - strike).max(0.0),
+            weight * df * und,
+        ]
+    };
+    let samples: Vec<[f64; 3]> = normals
+        .par_iter()
+        .map(|z| {
+            if vr.antithetic {
+                let (plus, minus) = (simulate(z, 1.0), simulate(z, -1.0));
+                [0, 1, 2].map(|i| 0.5 * (plus[i] + minus[i]))
+            } else {
+                simulate(z, 1.0)
+            }
+        })
+        .collect();
+
+    let mut controls: Vec<(usize, f64)> = Vec::new(); // (samplesのインデックス, 期待値)
+    if vr.geometric_control {
+        controls.push((1, geometric_asian_call(input, time_step)));
+    }
+    if vr.stock_control {
+        let t_last = times.last().copied().unwrap_or(0.0);
+        controls.push((2, df * underlying * (zero_rate * t_last).exp()));
+    }
+    let vals = apply_controls(&samples, &controls);
+    let price = vals.iter().sum::<f64>() / vals.len() as f64;
+    let batch_means: Vec<f64> = vals
+        .chunks(batch_size)
+        .map(|batch| batch.iter().sum::<f64>() / batch.len() as f64)
+        .collect();
+    let (_, std_error) = mean_std_error(&batch_means);
+    let result = McResult::new(price, std_error, num_path, start);
+
+    // 同じパス数のplain MC
+    let plain: Vec<f64> = (0..num_path)
+        .into_par_iter()
+        .map(|path_idx| plain_payoff(&normal_gen.normals(path_idx), input, time_step, delta_t))
+        .collect();
+    let (_, plain_std_error) = mean_std_error(&plain);
+
+    VarianceReductionResult {
+        result,
+        plain_std_error,
+        variance_reduction_factor: (plain_std_error / std_error).powi(2),
+    }
+}
+
+// 分散減少法を使わない場合のペイオフ(割引後)
+fn plain_payoff(normals: &[f64], input: &CalcInput, time_step: usize, delta_t: f64) -> f64 {
+    let mut und = input.underlying;
+    let mut sum = und;
+    for z in normals.iter() {
+        und *= ((input.zero_rate - 0.5 * input.vol.powi(2)) * delta_t
+            + input.vol * delta_t.sqrt() * z)
+            .exp();
+        sum += und;
+    }
+    (-input.zero_rate * input.term_annu).exp() * (sum / time_step as f64 - input.strike).max(0.0)
+}
+
+// 各時点の乱数をパス方向に標準化する。antitheticの場合は平均が0となるため分散のみ補正する。
+fn moment_match(normals: &mut [Vec<f64>], antithetic: bool) {
+    let num = normals.len() as f64;
+    let num_step = normals.first().map_or(0, |z| z.len());
+    for step in 0..num_step {
+        let mean = if antithetic {
+            0.0
+        } else {
+            normals.iter().map(|z| z[step]).sum::<f64>() / num
+        };
+        let std_dev = (normals
+            .iter()
+            .map(|z| (z[step] - mean).powi(2))
+            .sum::<f64>()
+            / num)
+            .sqrt();
+        for z in normals.iter_mut() {
+            z[step] = (z[step] - mean) / std_dev;
+        }
+    }
+}
+
+// control variateの係数βを最小二乗法で推定し、Y - β(X - E[X]) を返す。
+fn apply_controls(samples: &[[f64; 3]], controls: &[(usize, f64)]) -> Vec<f64> {
+    if controls.is_empty() {
+        return samples.iter().map(|s| s[0]).collect();
+    }
+    let num = samples.len();
+    let mean = |idx: usize| samples.iter().map(|s| s[idx]).sum::<f64>() / num as f64;
+    let y_mean = mean(0);
+    let x_means: Vec<f64> = controls.iter().map(|(idx, _)| mean(*idx)).collect();
+    let x_centered: Array2<f64> = Array2::from_shape_fn((num, controls.len()), |(i, j)| {
+        samples[i][controls[j].0] - x_means[j]
+    });
+    let y_centered: Array1<f64> = samples.iter().map(|s| s[0] - y_mean).collect();
+    let beta: Array1<f64> = x_centered
+        .t()
+        .dot(&x_centered)
+        .solve_into(x_centered.t().dot(&y_centered))
+        .unwrap();
+    samples
+        .iter()
+        .map(|s| {
+            s[0] - controls
+                .iter()
+                .zip(beta.iter())
+                .map(|((idx, expected), b)| b * (s[*idx] - expected))
+                .sum::<f64>()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mc::test_util::rand_gen;
+
+    #[test]
+    fn test_variance_reduction() {
+        let input = CalcInput {
+            underlying: 100.0,
+            strike: 100.0,
+            vol: 0.2,
+            zero_rate: 0.05,
+            term_annu: 1.0,
+        };
+        let rand_gen = rand_gen(11);
+        let reference = mc_bs_asian_call_vr(
+            &input,
+            50,
+            100000,
+            &rand_gen,
+            &VarianceReduction {
+                geometric_control: true,
+                ..Default::default()
+            },
+        );
+        let vr_list = [
+            VarianceReduction {
+                antithetic: true,
+                ..Default::default()
+            },
+            VarianceReduction {
+                stock_control: true,
+                ..Default::default()
+            },
+            VarianceReduction {
+                moment_matching: true,
+                ..Default::default()
+            },
+            VarianceReduction {
+                drift_shift: 0.02,
+                ..Default::default()
+            },
+            VarianceReduction {
+                antithetic: true,
+                geometric_control: true,
+                stock_control: true,
+                moment_matching: true,
+                drift_shift: 0.0,
+            },
+        ];
+        for vr in vr_list.iter() {
+            let result = mc_bs_asian_call_vr(&input, 50, 10000, &rand_gen, vr);
+            assert!(result.variance_reduction_factor > 1.0);
+            assert!(
+                (result.result.price - reference.result.price).abs() < 4.0 * result.plain_std_error
+            );
+        }
+        // 幾何平均は算術平均と相関が非常に高い。
+        assert!(reference.variance_reduction_factor > 100.0);
+    }
+}
@@ -0,0 +1,126 @@
+use super::engine::Path;
+use super::payoff::Payoff;
+
+/* バリアンス・スワップとボラティリティ・スワップ(想定元本1、満期に支払う)
+実現分散は観測日の対数リターンの二乗和を期間で割った年率 RV = Σ ln(S_i / S_{i-1})^2 / (t_n - t_0) とする(平均は引かない)。
+バリアンス・スワップは RV - K_var、ボラティリティ・スワップは √RV - K_vol を支払う。
+Jensenの不等式から E[√RV] ≤ √E[RV] となり、差(凸性調整)はボラティリティのボラティリティが大きいほど大きい。 */
+
+// 観測日の実現分散(年率)
+fn realized_variance(path: &Path, observation_times: &[f64]) -> f64 {
+    let last = observation_times.len() - 1;
+    let sum_sq: f64 = (1..last + 1)
+        .map(|i| (path.fixing(i) / path.fixing(i - 1)).ln().powi(2))
+        .sum();
+    sum_sq / (observation_times[last] - observation_times[0])
+}
+
+// バリアンス・スワップ
+#[derive(Debug, Clone)]
+pub struct VarianceSwap {
+    pub observation_times: Vec<f64>, // 最初の観測日(開始日)を含む
+    pub strike: f64,                 // 年率の分散
+}
+
+impl Payoff for VarianceSwap {
+    fn fixing_times(&self) -> Vec<f64> {
+        self.observation_times.clone()
+    }
+
+    fn cashflows(&self, path: &Path) -> Vec<(usize, f64)> {
+        let last = self.observation_times.len() - 1;
+        let rv = realized_variance(path, &self.observation_times);
+        vec![(last, rv - self.strike)]
+    }
+}
+
+// ボラティリティ・スワップ
+#[derive(Debug, Clone)]
+pub struct VolatilitySwap {
+    pub observation_times: Vec<f64>, // 最初の観測日(開始日)を含む
+    pub strike: f64,                 // 年率のボラティリティ
+}
+
+impl Payoff for VolatilitySwap {
+    fn fixing_times(&self) -> Vec<f64> {
+        self.observation_times.clone()
+    }
+
+    fn cashflows(&self, path: &Path) -> Vec<(usize, f64)> {
+        let last = self.observation_times.len() - 1;
+        let rv = realized_variance(path, &self.observation_times);
+        vec![(last, rv.sqrt() - self.strike)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bs::black_scholes::{self, implied_vol, CalcInput};
+    use crate::bs::variance_swap::variance_swap_fair_strike;
+    use crate::mc::engine::mc_price;
+    use crate::mc::stochastic_vol::{heston_call, HestonQe};
+    use crate::mc::test_util::rand_gen;
+
+    const SEED: u64 = 29;
+
+    #[test]
+    fn test_replication_against_heston() {
+        let (rate, term) = (0.02, 1.0_f64);
+        let heston = HestonQe {
+            underlying: 100.0,
+            zero_rate: rate,
+            div_yield: 0.0,
+            var0: 0.05,
+            kappa: 2.0,
+            theta: 0.04,
+            vol_of_var: 0.5,
+            corr: -0.7,
+            martingale_correction: true,
+        };
+        let input = CalcInput {
+            zero_rate: rate,
+            vol: 0.0,
+            term_annu: term,
+            strike: 0.0,
+            underlying: 100.0,
+        };
+        // 平坦なスマイルでは分散そのもの(black_scholesの正規分布関数の近似誤差を除く)
+        let flat = variance_swap_fair_strike(&input, &|_| 0.2, 200);
+        assert!((flat - 0.04).abs() < 1e-5);
+        // Simpson公式の分割数が奇数なら1つ増やす
+        assert_eq!(variance_swap_fair_strike(&input, &|_| 0.2, 199), flat);
+
+        // Hestonモデルでは E[∫v dt] / T = θ + (v_0 - θ)(1 - e^{-κT}) / (κT)
+        let expected = heston.theta
+            + (heston.var0 - heston.theta) * (1.0 - (-heston.kappa * term).exp())
+                / (heston.kappa * term);
+        let smile = |strike: f64| {
+            let price = heston_call(&heston, strike, term);
+            implied_vol(
+                price,
+                &CalcInput { strike, ..input },
+                black_scholes::OptionType::Call,
+            )
+        };
+        let replicated = variance_swap_fair_strike(&input, &smile, 100);
+        assert!((replicated - expected).abs() < 5e-4);
+
+        // 実現分散のモンテカルロ(日次観測)
+        let observation_times: Vec<f64> = (0..253).map(|i| i as f64 * term / 252.0).collect();
+        let swap = VarianceSwap {
+            observation_times: observation_times.clone(),
+            strike: replicated,
+        };
+        let result = mc_price(&heston, &swap, 252, 5000, &rand_gen(SEED));
+        assert!(result.price.abs() < 4.0 * result.std_error + 5e-4);
+
+        // ボラティリティ・スワップの公正な行使価格は凸性調整により√K_varより小さい。
+        let vol_swap = VolatilitySwap {
+            observation_times,
+            strike: replicated.sqrt(),
+        };
+        let result = mc_price(&heston, &vol_swap, 252, 5000, &rand_gen(SEED));
+        assert!(result.price < -4.0 * result.std_error);
+    }
+}
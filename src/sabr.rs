@@ -1,9 +1,43 @@
+pub mod arbitrage_free;
 mod lm;
-mod sabr_lognormal;
+pub mod sabr_lognormal;
+pub mod sabr_normal;
 
-use lm::levenberg_marquardt;
-use ndarray::arr1;
+use crate::ad::dual::Dual;
+use crate::ad::scalar::Scalar;
+use crate::ad::tape::Var;
+use crate::bs::black_scholes::{black_scholes, CalcInput, OptionType};
+use arbitrage_free::ArbitrageFreeSabr;
+use lm::{levenberg_marquardt, Jacobian, LmConfig};
 use sabr_lognormal::sabr_lognormal;
+use sabr_lognormal::shifted_sabr_lognormal;
+use sabr_normal::shifted_sabr_normal;
+
+/// SABRのパラメータです。シフトしない場合はshiftを0とします。<br>
+/// 型をDualやVarにするとパラメータに関する感応度を計算できます。
+#[derive(Debug, Copy, Clone)]
+pub struct SabrParams<T> {
+    pub alpha: T,
+    pub beta: T,
+    pub rho: T,
+    pub nu: T,
+    pub shift: T,
+}
+
+impl<T: Copy> SabrParams<T> {
+    /// LMのパラメータ[alpha, rho, nu, beta]とシフトからパラメータを作ります。
+    /// * `params` - [alpha, rho, nu, beta]
+    /// * `shift` - シフト
+    pub fn from_slice(params: &[T], shift: T) -> Self {
+        SabrParams {
+            alpha: params[0],
+            beta: params[3],
+            rho: params[1],
+            nu: params[2],
+            shift,
+        }
+    }
+}
 
 pub fn run() {
     let fwd = 0.03571;
@@ -18,26 +52,200 @@ pub fn run() {
         fwd + 0.01,
         fwd + 0.02,
     ];
-    let mut params = vec![0.01, 0.01, 0.5];
-    let target_vals = arr1(&[0.3215, 0.248, 0.2222, 0.2040, 0.1923, 0.1867, 0.1887]);
-    let max_iter: usize = 1000;
-    let threshold = 1e-7;
-    let rap_fn = |arg: &f64, params: &Vec<f64>| -> f64 {
-        sabr_lognormal(*arg, fwd, term, beta, params[0], params[1], params[2])
-    };
-    levenberg_marquardt(
-        &rap_fn,
+    // パラメータは[alpha, rho, nu, beta]で、betaは固定する。
+    let params = vec![0.01, 0.01, 0.5, beta];
+    let target_vals = vec![0.3215, 0.248, 0.2222, 0.2040, 0.1923, 0.1867, 0.1887];
+    let config = LmConfig {
+        is_free: vec![true, true, true, false],
+        lower: vec![0.0, -1.0, 0.0, 0.0],
+        upper: vec![f64::INFINITY, 1.0, f64::INFINITY, 1.0],
+        ..LmConfig::new(params.len())
+    };
+    let sabr_fn = |arg: f64, params: &[f64]| -> f64 {
+        sabr_lognormal(arg, fwd, term, params[3], params[0], params[1], params[2])
+    };
+    let rap_fn = |arg: f64, params: &[Var]| -> Var {
+        let c = Var::from_f64;
+        sabr_lognormal(
+            c(arg),
+            c(fwd),
+            c(term),
+            params[3],
+            params[0],
+            params[1],
+            params[2],
+        )
+    };
+    let report = levenberg_marquardt(
+        &sabr_fn,
+        &Jacobian::ReverseAd(Box::new(rap_fn)),
+        &strikes,
+        &target_vals,
+        &params,
+        &config,
+    )
+    .unwrap();
+    println!(
+        "(sabr) params:{:?} num_iter:{} converged:{} squared_sum:{:e}",
+        report.params, report.num_iter, report.converged, report.squared_sum
+    );
+    for (target, residual) in target_vals.iter().zip(report.residuals.iter()) {
+        println!(
+            "(sabr, target, residual): {}, {}, {:e}",
+            target + residual,
+            target,
+            residual
+        );
+    }
+
+    // 前進型自動微分(二重数)で求めた偏導関数を与えても同じパラメータになる。
+    let dual_grad = |arg: f64, params: &[f64]| -> Vec<f64> {
+        let c = Dual::<4>::from_f64;
+        let p: Vec<Dual<4>> = (0..4).map(|i| Dual::variable(params[i], i)).collect();
+        sabr_lognormal(c(arg), c(fwd), c(term), p[3], p[0], p[1], p[2])
+            .grad
+            .to_vec()
+    };
+    let analytic = levenberg_marquardt(
+        &sabr_fn,
+        &Jacobian::Analytic(Box::new(dual_grad)),
+        &strikes,
+        &target_vals,
+        &params,
+        &config,
+    )
+    .unwrap();
+    println!(
+        "(sabr, dual number jacobian) params:{:?} num_iter:{} converged:{}",
+        analytic.params, analytic.num_iter, analytic.converged
+    );
+    let params = report.params;
+
+    // 10年の満期ではHaganの式は低い行使価格で密度(Callの価格の2階微分)が負になる。無裁定SABRの密度と比べる。
+    let arbitrage_free = ArbitrageFreeSabr {
+        fwd,
+        shift: 0.0,
+        term,
+        beta,
+        alpha: params[0],
+        rho: params[1],
+        nu: params[2],
+        num_grid: 500,
+        num_time: 200,
+        num_std: 5.0,
+    };
+    let density = arbitrage_free.density();
+    let hagan_call = |strike: f64| {
+        let input = CalcInput {
+            zero_rate: 0.0,
+            vol: sabr_lognormal(strike, fwd, term, beta, params[0], params[1], params[2]),
+            term_annu: term,
+            strike,
+            underlying: fwd,
+        };
+        black_scholes(&input, OptionType::Call)
+    };
+    let h = 1e-4;
+    for strike in [0.002, 0.005, 0.01, 0.02, fwd, 0.05, 0.08] {
+        let hagan_density = (hagan_call(strike - h) - 2.0 * hagan_call(strike)
+            + hagan_call(strike + h))
+            / h.powi(2);
+        println!(
+            "(arbitrage free sabr) strike {}: vol {} (hagan {}, hagan density {})",
+            strike,
+            density.implied_lognormal_vol(strike),
+            sabr_lognormal(strike, fwd, term, beta, params[0], params[1], params[2]),
+            hagan_density
+        );
+    }
+    println!(
+        "(arbitrage free sabr) probability mass at zero: {} (total probability {})",
+        density.mass_lower,
+        density.total_mass()
+    );
+
+    // 負の金利のスワップションのスマイル(ノーマル・ボラティリティ)にシフトしたSABRをfitさせる。
+    let fwd = -0.0015;
+    let shift = 0.02;
+    let term = 5.0;
+    let strikes = vec![-0.0065, -0.004, -0.0015, 0.0, 0.001, 0.0035, 0.006, 0.0085];
+    let target_vals = vec![
+        0.00642, 0.00571, 0.00532, 0.00531, 0.00538, 0.00566, 0.00607, 0.00652,
+    ];
+    let config = LmConfig {
+        is_free: vec![true, true, true, false],
+        lower: vec![0.0, -1.0, 0.0, 0.0],
+        upper: vec![f64::INFINITY, 1.0, f64::INFINITY, 1.0],
+        threshold: 1e-20,
+        ..LmConfig::new(4)
+    };
+    let shifted_fn = |arg: f64, params: &[f64]| -> f64 {
+        shifted_sabr_normal(arg, fwd, term, &SabrParams::from_slice(params, shift))
+    };
+    let report = levenberg_marquardt(
+        &shifted_fn,
+        &Jacobian::CentralDifference,
         &strikes,
-        &mut params,
         &target_vals,
-        max_iter,
-        threshold,
+        &[0.01, 0.0, 0.3, beta],
+        &config,
+    )
+    .unwrap();
+    println!(
+        "(shifted sabr) params:{:?} num_iter:{} converged:{} squared_sum:{:e}",
+        report.params, report.num_iter, report.converged, report.squared_sum
     );
+    // 同じパラメータの無裁定SABRのノーマル・ボラティリティと比べる。
+    let params = &report.params;
+    let density = ArbitrageFreeSabr {
+        fwd,
+        shift,
+        term,
+        beta: params[3],
+        alpha: params[0],
+        rho: params[1],
+        nu: params[2],
+        num_grid: 500,
+        num_time: 200,
+        num_std: 5.0,
+    }
+    .density();
     for i in 0..strikes.len() {
         println!(
-            "(sabr, target): {}, {}",
-            rap_fn(&strikes[i], &params),
+            "(shifted sabr normal vol, arbitrage free, target): {}, {}, {}",
+            shifted_fn(strikes[i], params),
+            density.implied_normal_vol(strikes[i]),
             target_vals[i]
         );
     }
+
+    // 無裁定SABRのスマイルをシフトしたBlackモデルのボラティリティで表し、シフトした対数正規のSABRをfitさせる。
+    let lognormal_targets: Vec<f64> = strikes
+        .iter()
+        .map(|strike| density.implied_lognormal_vol(*strike))
+        .collect();
+    let shifted_lognormal_fn = |arg: f64, params: &[f64]| -> f64 {
+        shifted_sabr_lognormal(arg, fwd, term, &SabrParams::from_slice(params, shift))
+    };
+    let shifted_lognormal_var = |arg: f64, params: &[Var]| -> Var {
+        let c = Var::from_f64;
+        let sabr_params = SabrParams::from_slice(params, c(shift));
+        shifted_sabr_lognormal(c(arg), c(fwd), c(term), &sabr_params)
+    };
+    let report = levenberg_marquardt(
+        &shifted_lognormal_fn,
+        &Jacobian::ReverseAd(Box::new(shifted_lognormal_var)),
+        &strikes,
+        &lognormal_targets,
+        &[0.01, 0.0, 0.3, beta],
+        &LmConfig {
+            threshold: 1e-14,
+            ..config
+        },
+    )
+    .unwrap();
+    println!(
+        "(shifted lognormal sabr) params:{:?} num_iter:{} converged:{} squared_sum:{:e}",
+        report.params, report.num_iter, report.converged, report.squared_sum
+    );
 }
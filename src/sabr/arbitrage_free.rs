@@ -0,0 +1,347 @@
+use crate::bs::black_scholes::{self, implied_vol, norm_cdf_matic2016};
+use std::f64::consts::PI;
+
+/* 無裁定SABR(Hagan et al. 2014 "Arbitrage-Free SABR")
+Haganの漸近展開(2002)は低い行使価格や長い満期で密度が負になり、裁定機会が生じる。
+シフトしたフォワード x = F + shift について、実効的な1次元の密度 Q(t, x) の前進方程式
+    ∂Q/∂t = ∂^2/∂x^2 (M(t, x) Q),  M = D(x)^2 E(t, x) / 2
+    C(x) = x^β,  y(x) = ∫_{f}^{x} du / C(u) = (x^{1-β} - f^{1-β}) / (1 - β)
+    D(x) = √(α^2 + 2αρν y + ν^2 y^2) C(x),  E(t, x) = exp(ρναΓ(x) t),  Γ(x) = (C(x) - C(f)) / (x - f)
+をフラックスの形で差分化して解く。x = 0 と x = x_max を吸収境界 (M Q = 0) とし、境界に吸収された確率を
+P_L(x = 0 の質量)、P_R として持つと、確率の総和とフォワードの期待値がちょうど保存される(マルチンゲール)。
+格子はセルの中心 x_j = (j - 1/2) h で、境界はセルの境界に置き、フォワード f はセルの中心に合わせる。
+時間方向はLawson-Swayne法(後退オイラー2回の外挿、2次精度)で進める。
+価格は Q をセル内で一定として積分するため、Put-Callパリティが成り立つ。 */
+
+/// 無裁定SABRのパラメータと差分法の設定です。
+#[derive(Debug, Copy, Clone)]
+pub struct ArbitrageFreeSabr {
+    pub fwd: f64,
+    pub shift: f64, // シフト(F + shift > 0 とする。シフトしなければ0)
+    pub term: f64,
+    pub beta: f64,
+    pub alpha: f64,
+    pub rho: f64,
+    pub nu: f64,
+    pub num_grid: usize, // フォワード方向のセルの数の目安
+    pub num_time: usize, // 時間方向のステップ数
+    pub num_std: f64,    // 格子の上端(フォワードから、ATMの対数正規ボラティリティの何標準偏差上か)
+}
+
+/// 無裁定SABRの満期の密度です。
+#[derive(Debug, Clone)]
+pub struct SabrDensity {
+    pub fwd: f64,
+    pub shift: f64,
+    pub term: f64,
+    pub step: f64,         // セルの幅 h
+    pub grid: Vec<f64>,    // セルの中心のフォワード(シフト前)
+    pub density: Vec<f64>, // セルごとの密度
+    pub mass_lower: f64,   // 下端(F = -shift)に吸収された確率
+    pub mass_upper: f64,   // 上端に吸収された確率
+}
+
+impl ArbitrageFreeSabr {
+    /// 密度の前進方程式を満期まで解きます。
+    pub fn density(&self) -> SabrDensity {
+        let f = self.fwd + self.shift;
+        // フォワードがセルの中心になるようにセルの幅を決める。
+        let atm_vol = self.alpha * f.powf(self.beta - 1.0);
+        let upper = f * (self.num_std * atm_vol * self.term.sqrt()).exp();
+        let fwd_index = ((f / upper * self.num_grid as f64) + 0.5).round().max(1.0);
+        let step = f / (fwd_index - 0.5);
+        let num_grid = (upper / step).ceil() as usize;
+        let grid: Vec<f64> = (1..num_grid + 1).map(|j| (j as f64 - 0.5) * step).collect();
+
+        let mut density = vec![0.0; num_grid];
+        density[fwd_index as usize - 1] = 1.0 / step;
+        let mut masses = (0.0, 0.0);
+        let delta_t = self.term / self.num_time as f64;
+        let theta = 1.0 - 0.5 * 2f64.sqrt();
+        for n in 0..self.num_time {
+            let time = n as f64 * delta_t;
+            // Lawson-Swayne: θΔtの後退オイラーを2回行い、(√2 + 1) Q2 - √2 Q1 とする。
+            let (first, first_masses) =
+                self.implicit_step(&grid, &density, time + theta * delta_t, theta * delta_t);
+            let (second, second_masses) =
+                self.implicit_step(&grid, &first, time + 2.0 * theta * delta_t, theta * delta_t);
+            let (w2, w1) = (2f64.sqrt() + 1.0, 2f64.sqrt());
+            density = second
+                .iter()
+                .zip(first.iter())
+                .map(|(q2, q1)| w2 * q2 - w1 * q1)
+                .collect();
+            masses.0 += w2 * (first_masses.0 + second_masses.0) - w1 * first_masses.0;
+            masses.1 += w2 * (first_masses.1 + second_masses.1) - w1 * first_masses.1;
+        }
+
+        SabrDensity {
+            fwd: self.fwd,
+            shift: self.shift,
+            term: self.term,
+            step,
+            grid: grid.iter().map(|x| x - self.shift).collect(),
+            density,
+            mass_lower: masses.0,
+            mass_upper: masses.1,
+        }
+    }
+
+    // M(t, x) = D(x)^2 E(t, x) / 2
+    fn diffusion(&self, x: f64, time: f64) -> f64 {
+        let f = self.fwd + self.shift;
+        let one_beta = 1.0 - self.beta;
+        let c = x.powf(self.beta);
+        let y = if one_beta == 0.0 {
+            (x / f).ln()
+        } else {
+            (x.powf(one_beta) - f.powf(one_beta)) / one_beta
+        };
+        let d_squared = (self.alpha.powi(2)
+            + 2.0 * self.alpha * self.rho * self.nu * y
+            + (self.nu * y).powi(2))
+            * c.powi(2);
+        let gamma = if (x - f).abs() < 1e-12 {
+            self.beta * f.powf(self.beta - 1.0)
+        } else {
+            (c - f.powf(self.beta)) / (x - f)
+        };
+        0.5 * d_squared * (self.rho * self.nu * self.alpha * gamma * time).exp()
+    }
+
+    // 時刻timeのMで後退オイラーを1ステップ進めた密度と、ステップで境界に吸収された確率(P_L, P_R)を返す。
+    // 吸収境界 M_0 Q_0 = -M_1 Q_1、M_{J+1} Q_{J+1} = -M_J Q_J でゴーストセルを消去する。
+    fn implicit_step(
+        &self,
+        grid: &[f64],
+        density: &[f64],
+        time: f64,
+        delta_t: f64,
+    ) -> (Vec<f64>, (f64, f64)) {
+        let num = grid.len();
+        let step = grid[0] * 2.0;
+        let ratio = delta_t / step.powi(2);
+        let m: Vec<f64> = grid.iter().map(|x| self.diffusion(*x, time)).collect();
+        let lower: Vec<f64> = (0..num)
+            .map(|j| if j == 0 { 0.0 } else { -ratio * m[j - 1] })
+            .collect();
+        let upper: Vec<f64> = (0..num)
+            .map(|j| if j == num - 1 { 0.0 } else { -ratio * m[j + 1] })
+            .collect();
+        let diagonal: Vec<f64> = (0..num)
+            .map(|j| {
+                let boundary = if j == 0 || j == num - 1 { 3.0 } else { 2.0 };
+                1.0 + boundary * ratio * m[j]
+            })
+            .collect();
+        let next = solve_tridiagonal(&lower, &diagonal, &upper, density);
+        let mass_lower = 2.0 * delta_t * m[0] * next[0] / step;
+        let mass_upper = 2.0 * delta_t * m[num - 1] * next[num - 1] / step;
+        (next, (mass_lower, mass_upper))
+    }
+}
+
+impl SabrDensity {
+    /// Callの価格(割引なし)を返します。
+    /// * `strike` - 行使価格
+    pub fn call_price(&self, strike: f64) -> f64 {
+        let (lower, upper) = self.boundaries();
+        let mut price = (upper - strike).max(0.0) * self.mass_upper
+            + (lower - strike).max(0.0) * self.mass_lower;
+        for (x, q) in self.grid.iter().zip(self.density.iter()) {
+            let (cell_lower, cell_upper) = (x - 0.5 * self.step, x + 0.5 * self.step);
+            if cell_lower >= strike {
+                price += (x - strike) * self.step * q;
+            } else if cell_upper > strike {
+                price += 0.5 * (cell_upper - strike).powi(2) * q;
+            }
+        }
+        price
+    }
+
+    /// Putの価格(割引なし)を返します。
+    /// * `strike` - 行使価格
+    pub fn put_price(&self, strike: f64) -> f64 {
+        let (lower, upper) = self.boundaries();
+        let mut price = (strike - lower).max(0.0) * self.mass_lower
+            + (strike - upper).max(0.0) * self.mass_upper;
+        for (x, q) in self.grid.iter().zip(self.density.iter()) {
+            let (cell_lower, cell_upper) = (x - 0.5 * self.step, x + 0.5 * self.step);
+            if cell_upper <= strike {
+                price += (strike - x) * self.step * q;
+            } else if cell_lower < strike {
+                price += 0.5 * (strike - cell_lower).powi(2) * q;
+            }
+        }
+        price
+    }
+
+    /// OTMのオプションの価格から求めた(シフトした)Blackモデルのインプライド・ボラティリティを返します。
+    /// * `strike` - 行使価格
+    pub fn implied_lognormal_vol(&self, strike: f64) -> f64 {
+        let input = black_scholes::CalcInput {
+            zero_rate: 0.0,
+            vol: 0.0,
+            term_annu: self.term,
+            strike: strike + self.shift,
+            underlying: self.fwd + self.shift,
+        };
+        if strike >= self.fwd {
+            implied_vol(
+                self.call_price(strike),
+                &input,
+                black_scholes::OptionType::Call,
+            )
+        } else {
+            implied_vol(
+                self.put_price(strike),
+                &input,
+                black_scholes::OptionType::Put,
+            )
+        }
+    }
+
+    /// OTMのオプションの価格から求めたBachelierモデルのインプライド・ボラティリティ(ノーマル・ボラティリティ)を返します。
+    /// * `strike` - 行使価格
+    pub fn implied_normal_vol(&self, strike: f64) -> f64 {
+        // OTMの価格は Call: bachelier(F - K)、Put: bachelier(K - F)
+        let (moneyness, price) = if strike >= self.fwd {
+            (self.fwd - strike, self.call_price(strike))
+        } else {
+            (strike - self.fwd, self.put_price(strike))
+        };
+        let (mut lower, mut upper) = (1e-8, 1.0);
+        for _ in 0..100 {
+            let mid = 0.5 * (lower + upper);
+            if bachelier_call(moneyness, mid, self.term) > price {
+                upper = mid;
+            } else {
+                lower = mid;
+            }
+        }
+        0.5 * (lower + upper)
+    }
+
+    // 格子の下端と上端のフォワード(シフト前)
+    fn boundaries(&self) -> (f64, f64) {
+        (
+            -self.shift,
+            self.grid[self.grid.len() - 1] + 0.5 * self.step,
+        )
+    }
+
+    /// 確率の総和(密度の積分と境界の質量の和)を返します。
+    pub fn total_mass(&self) -> f64 {
+        self.density.iter().sum::<f64>() * self.step + self.mass_lower + self.mass_upper
+    }
+}
+
+// Bachelierモデルの Call の価格(割引なし)。moneyness = F - K
+fn bachelier_call(moneyness: f64, vol: f64, term: f64) -> f64 {
+    let std_dev = vol * term.sqrt();
+    let d = moneyness / std_dev;
+    moneyness * norm_cdf_matic2016(d) + std_dev * (-0.5 * d.powi(2)).exp() / (2.0 * PI).sqrt()
+}
+
+// Thomas algorithmで3重対角行列の連立方程式を解く。
+fn solve_tridiagonal(lower: &[f64], diagonal: &[f64], upper: &[f64], rhs: &[f64]) -> Vec<f64> {
+    let num = diagonal.len();
+    let mut c = vec![0.0; num];
+    let mut d = vec![0.0; num];
+    c[0] = upper[0] / diagonal[0];
+    d[0] = rhs[0] / diagonal[0];
+    for i in 1..num {
+        let denominator = diagonal[i] - lower[i] * c[i - 1];
+        c[i] = upper[i] / denominator;
+        d[i] = (rhs[i] - lower[i] * d[i - 1]) / denominator;
+    }
+    for i in (0..num - 1).rev() {
+        d[i] -= c[i] * d[i + 1];
+    }
+    d
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sabr::sabr_lognormal::sabr_lognormal;
+    use crate::sabr::sabr_normal::shifted_sabr_normal;
+    use crate::sabr::SabrParams;
+
+    fn sabr(term: f64) -> ArbitrageFreeSabr {
+        ArbitrageFreeSabr {
+            fwd: 0.03571,
+            shift: 0.0,
+            term,
+            beta: 0.5,
+            alpha: 0.0357,
+            rho: -0.25,
+            nu: 0.36,
+            num_grid: 500,
+            num_time: 200,
+            num_std: 5.0,
+        }
+    }
+
+    #[test]
+    fn test_conservation_and_parity() {
+        // 確率の総和とフォワードが保存され、Put-Callパリティが成り立つ(負のフォワードをシフトした場合も)。
+        let shifted = ArbitrageFreeSabr {
+            fwd: -0.002,
+            shift: 0.02,
+            ..sabr(10.0)
+        };
+        for params in [sabr(10.0), shifted] {
+            let density = params.density();
+            assert!((density.total_mass() - 1.0).abs() < 1e-12);
+            assert!(density.density.iter().all(|q| *q >= 0.0));
+            assert!(density.mass_lower > 0.0);
+            for strike in [-0.01, 0.0, 0.01, 0.03571, 0.05, 0.1] {
+                let parity = density.call_price(strike) - density.put_price(strike);
+                assert!((parity - (params.fwd - strike)).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn test_short_expiry_matches_hagan() {
+        // 満期が短ければ密度はほぼ正で、インプライド・ボラティリティはHaganの式に近い。
+        let params = sabr(1.0);
+        let density = params.density();
+        for strike in [0.025, 0.03, 0.03571, 0.04, 0.05] {
+            let hagan = sabr_lognormal(
+                strike,
+                params.fwd,
+                params.term,
+                params.beta,
+                params.alpha,
+                params.rho,
+                params.nu,
+            );
+            assert!((density.implied_lognormal_vol(strike) - hagan).abs() < 2e-3);
+        }
+    }
+
+    #[test]
+    fn test_shifted_normal_vol_matches_hagan() {
+        // 負のフォワードをシフトした場合も、満期が短ければノーマル・ボラティリティはHaganの式に近い。
+        let params = ArbitrageFreeSabr {
+            fwd: -0.002,
+            shift: 0.02,
+            alpha: 0.027,
+            ..sabr(1.0)
+        };
+        let density = params.density();
+        for strike in [-0.008, -0.005, -0.002, 0.0, 0.004] {
+            let sabr_params = SabrParams {
+                alpha: params.alpha,
+                beta: params.beta,
+                rho: params.rho,
+                nu: params.nu,
+                shift: params.shift,
+            };
+            let hagan = shifted_sabr_normal(strike, params.fwd, params.term, &sabr_params);
+            assert!((density.implied_normal_vol(strike) - hagan).abs() < 5e-5);
+        }
+    }
+}
@@ -1,104 +1,488 @@
-use ndarray::{Array1, Array2, ArrayBase, Dim, OwnedRepr};
+use crate::ad::tape::{gradient, Var};
+use ndarray::{Array1, Array2, Axis};
 use ndarray_linalg::{FactorizeInto, Solve};
 
-// TODO 調整するパラメータと固定するパラメータを合わせたベクタ引数として、調整するかどうかを同じ長さのbooleanのベクタとして引数に追加する
-// TODO 微分方法を指定できるように変更する。
-pub fn levenberg_marquardt(
-    f: &dyn Fn(&f64, &Vec<f64>) -> f64,
-    args: &Vec<f64>,           // fitさせるときに固定されるパラメータ
-    params: &mut Vec<f64>,     // fitさせるときに変動させるパラメータの初期値
-    target_vals: &Array1<f64>, // argsごとの関数の値の収束先  TODO IFを考えると引数にndarrayの型は使わない方がよい。
-    max_iter: usize,           // イテレーション回数の最大
-    threshold: f64,            // ループ終了のための閾値
-) {
-    let mut square_diff_new: f64; //残差二乗和(更新後)
-    let mut square_diff_old = 0.0; //残差二乗和(更新前)
-    let mut lamda = 0.001; //fudge factor
-    let mut func_vals: Array1<f64> = Array1::zeros(target_vals.len()); // argsごとの関数の値
-    let mut residuals: Array1<f64>; // 残差ベクトル
-    let mut num_it = 0; //イテレーション回数
-
-    while num_it < max_iter {
-        for (idx, arg) in args.iter().enumerate() {
-            func_vals[idx] = f(arg, &params);
-        }
-        residuals = &func_vals - target_vals;
-
-        let jacobian = jacobian(f, &args, &params);
-        // println!("jacobian: \n{:?}", jacobian);
-        // println!(
-        //     "jacobian.t().dot(&jacobian): \n{:?}",
-        //     jacobian.t().dot(&jacobian)
-        // );
-        let lm_mat: ArrayBase<OwnedRepr<f64>, Dim<[usize; 2]>> =
-            jacobian.t().dot(&jacobian) + lamda * Array2::eye(params.len());
-        // println!("lm_mat: \n{:?}", lm_mat);
-        let lm_mat = lm_mat.factorize_into().unwrap();
-        let delta_params = lm_mat.solve_into(-jacobian.t().dot(&residuals)).unwrap();
-
-        for i in 0..delta_params.len() {
-            params[i] += delta_params[i];
+// 中心差分の幅(変換後のパラメータに対する相対値)
+const DIFF_STEP: f64 = 1e-6;
+// damping parameter がこれを超えたら残差二乗和を減らす更新はないものとして終了する
+const MAX_LAMBDA: f64 = 1e12;
+
+/* 固定するパラメータと上下限を指定できるLevenberg-Marquardt
+上下限は変換で扱い、変換後の制約のないパラメータqを更新する。固定するパラメータは変換せずにそのまま使う。
+    上下限あり: p = l + (u - l) / (1 + e^{-q})、下限のみ: p = l + e^q、上限のみ: p = u - e^q
+ヤコビアンは変換の微分 dp/dq を掛けて q に関するものにし、固定するパラメータの列は除く。
+残差二乗和が減る場合だけ更新を採用し、damping parameter を小さくする(減らなければ大きくして解き直す)。
+ヤコビアンの各列と残差の余弦 |J_j^T r| / (|J_j| |r|) が全て小さければ停留点として収束とする(MINPACKのgtol)。 */
+
+/// (独立変数, Varのパラメータ)に対する関数の値
+pub type VarFn<'a> = Box<dyn Fn(f64, &[Var]) -> Var + 'a>;
+
+/// (独立変数, パラメータ)に対する全てのパラメータに関する偏微分
+pub type GradientFn<'a> = Box<dyn Fn(f64, &[f64]) -> Vec<f64> + 'a>;
+
+/// ヤコビアン(各独立変数での関数のパラメータに関する偏微分)の計算方法です。
+pub enum Jacobian<'a> {
+    // 後退型自動微分(パラメータをVarとした関数)
+    ReverseAd(VarFn<'a>),
+    // ユーザーが与える偏導関数(固定するパラメータを含む全てのパラメータについて)
+    Analytic(GradientFn<'a>),
+    // 中心差分
+    CentralDifference,
+}
+
+/// Levenberg-Marquardtの設定です。
+#[derive(Debug, Clone)]
+pub struct LmConfig {
+    pub is_free: Vec<bool>, // パラメータごとに調整するか(falseなら初期値に固定する)
+    pub lower: Vec<f64>,    // パラメータの下限(なければf64::NEG_INFINITY)
+    pub upper: Vec<f64>,    // パラメータの上限(なければf64::INFINITY)
+    pub max_iter: usize,    // イテレーション回数の最大
+    pub threshold: f64,     // 残差二乗和の減少がこれより小さくなったら収束とする
+    pub gradient_threshold: f64, // ヤコビアンの列と残差の余弦が全てこれより小さければ収束とする
+}
+
+impl LmConfig {
+    /// 全てのパラメータを上下限なしで調整する設定を返します。
+    /// * `num_params` - パラメータの数
+    pub fn new(num_params: usize) -> Self {
+        Self {
+            is_free: vec![true; num_params],
+            lower: vec![f64::NEG_INFINITY; num_params],
+            upper: vec![f64::INFINITY; num_params],
+            max_iter: 1000,
+            threshold: 1e-12,
+            gradient_threshold: 1e-10,
         }
+    }
+}
+
+/// Levenberg-Marquardtの結果です。
+#[derive(Debug, Clone)]
+pub struct LmReport {
+    pub params: Vec<f64>,    // 固定したパラメータを含むパラメータ
+    pub residuals: Vec<f64>, // 独立変数ごとの 関数の値 - 目標値
+    pub squared_sum: f64,    // 残差二乗和
+    pub num_iter: usize,     // イテレーション回数
+    pub converged: bool, // max_iterまでに収束したか(停留点でないまま更新できずに止まった場合はfalse)
+}
 
-        square_diff_new = residuals.iter().map(|diff| diff.powi(2)).sum();
+/// Levenberg-Marquardtで関数を目標値にfitさせたパラメータと残差などを返します。<br>
+/// 調整するパラメータの初期値が上下限の内側にない場合はエラーを返します。
+/// 全てのパラメータを固定した場合は初期値のまま収束として返します。
+/// * `f` - (独立変数, パラメータ)に対する関数の値
+/// * `jacobian` - ヤコビアンの計算方法
+/// * `args` - 独立変数のベクタ
+/// * `target_vals` - 独立変数ごとの関数の値の目標値
+/// * `init_params` - パラメータの初期値(調整するパラメータは上下限の内側)
+/// * `config` - 固定するパラメータ、上下限、終了条件
+pub fn levenberg_marquardt(
+    f: &dyn Fn(f64, &[f64]) -> f64,
+    jacobian: &Jacobian,
+    args: &[f64],
+    target_vals: &[f64],
+    init_params: &[f64],
+    config: &LmConfig,
+) -> Result<LmReport, String> {
+    let bounds = Bounds { config };
+    let mut unbounded = bounds.to_unbounded(init_params)?;
+    let residuals = |params: &[f64]| -> Array1<f64> {
+        args.iter()
+            .zip(target_vals.iter())
+            .map(|(arg, target)| f(*arg, params) - target)
+            .collect()
+    };
 
-        if (square_diff_new - square_diff_old).abs() < threshold {
+    let mut params = bounds.to_bounded(&unbounded);
+    let mut errors = residuals(&params);
+    let mut squared_sum = errors.dot(&errors);
+    let mut lambda = 0.001; //damping parameter
+    let mut num_iter = 0;
+    let mut converged = false;
+    let mut jac = bounds.jacobian(f, jacobian, args, &unbounded);
+    while num_iter < config.max_iter {
+        // 調整するパラメータがない(ヤコビアンが0列)場合もここで終了する。
+        if max_cosine(&jac, &errors) < config.gradient_threshold {
+            converged = true;
             break;
         }
-        lamda *= if square_diff_new > square_diff_old {
-            10.0
-        } else {
-            0.1
+        num_iter += 1;
+        let lm_mat: Array2<f64> = jac.t().dot(&jac) + lambda * Array2::eye(jac.ncols());
+        let delta = match lm_mat
+            .factorize_into()
+            .and_then(|lu| lu.solve_into(-jac.t().dot(&errors)))
+        {
+            Ok(delta) => delta,
+            Err(e) => {
+                return Err(format!(
+                    "failed to solve the damped normal equations: {}",
+                    e
+                ))
+            }
         };
-        square_diff_old = square_diff_new;
-        num_it += 1;
+        let mut new_unbounded = unbounded.clone();
+        for (i, d) in bounds.free_indices().zip(delta.iter()) {
+            new_unbounded[i] += d;
+        }
+        let new_params = bounds.to_bounded(&new_unbounded);
+        let new_errors = residuals(&new_params);
+        let new_squared_sum = new_errors.dot(&new_errors);
+
+        if new_squared_sum.is_finite() && new_squared_sum < squared_sum {
+            let decrease = squared_sum - new_squared_sum;
+            unbounded = new_unbounded;
+            params = new_params;
+            errors = new_errors;
+            squared_sum = new_squared_sum;
+            if decrease < config.threshold {
+                converged = true;
+                break;
+            }
+            jac = bounds.jacobian(f, jacobian, args, &unbounded);
+            lambda *= 0.1;
+        } else {
+            lambda *= 10.0;
+            if lambda > MAX_LAMBDA {
+                break;
+            }
+        }
     }
-    println!("params:{:?}", params);
-    println!("num_it:{}", num_it);
+    Ok(LmReport {
+        params,
+        residuals: errors.to_vec(),
+        squared_sum,
+        num_iter,
+        converged,
+    })
 }
 
-fn jacobian(f: &dyn Fn(&f64, &Vec<f64>) -> f64, args: &Vec<f64>, params: &Vec<f64>) -> Array2<f64> {
-    let mut jac: Array2<f64> = Array2::zeros((args.len(), params.len()));
-    for i in 0..args.len() {
-        for j in 0..params.len() {
-            // TODO パラメータを動かしていない純粋な関数の値を何度も計算しているので、要改善
-            jac[[i, j]] = numerical_difference(f, &args[i], params, j);
-        }
+// ヤコビアンの各列と残差の余弦の絶対値の最大。残差が0の場合と0の列は0とする。
+fn max_cosine(jac: &Array2<f64>, errors: &Array1<f64>) -> f64 {
+    let errors_norm = errors.dot(errors).sqrt();
+    if errors_norm == 0.0 {
+        return 0.0;
     }
-    jac
+    jac.axis_iter(Axis(1))
+        .map(|col| {
+            let col_norm = col.dot(&col).sqrt();
+            if col_norm == 0.0 {
+                0.0
+            } else {
+                col.dot(errors).abs() / (col_norm * errors_norm)
+            }
+        })
+        .fold(0.0, f64::max)
 }
 
-fn numerical_difference(
-    f: &dyn Fn(&f64, &Vec<f64>) -> f64,
-    arg: &f64,
-    params: &Vec<f64>,
-    idx: usize,
-) -> f64 {
-    let h;
-    if params[idx] == 0.0 {
-        h = 0.0001;
-    } else {
-        h = params[idx].abs() * 0.001;
+// パラメータの上下限による変換
+struct Bounds<'a> {
+    config: &'a LmConfig,
+}
+
+impl Bounds<'_> {
+    fn free_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.config.is_free.len()).filter(move |i| self.config.is_free[*i])
     }
 
-    let mut incre_params = params.clone();
-    incre_params[idx] += h;
+    // (下限があるか, 上限があるか)。固定するパラメータは上下限なしとして変換しない。
+    fn kind(&self, i: usize) -> (bool, bool) {
+        let config = self.config;
+        (
+            config.is_free[i] && config.lower[i].is_finite(),
+            config.is_free[i] && config.upper[i].is_finite(),
+        )
+    }
 
-    // let mut decre_params = params.clone();
-    // decre_params[idx] -= h;
+    // p = p(q)
+    fn to_bounded(&self, unbounded: &[f64]) -> Vec<f64> {
+        let (lower, upper) = (&self.config.lower, &self.config.upper);
+        unbounded
+            .iter()
+            .enumerate()
+            .map(|(i, q)| match self.kind(i) {
+                (true, true) => lower[i] + (upper[i] - lower[i]) / (1.0 + (-q).exp()),
+                (true, false) => lower[i] + q.exp(),
+                (false, true) => upper[i] - q.exp(),
+                (false, false) => *q,
+            })
+            .collect()
+    }
 
-    // (f(arg, &incre_params) - f(arg, &decre_params)) / h
-    (f(arg, &incre_params) - f(arg, params)) / h
+    // q = p^{-1}(p)
+    fn to_unbounded(&self, params: &[f64]) -> Result<Vec<f64>, String> {
+        let (lower, upper) = (&self.config.lower, &self.config.upper);
+        params
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                if self.config.is_free[i] && !(lower[i] < *p && *p < upper[i]) {
+                    return Err(format!(
+                        "initial parameter {} = {} is not within the bounds ({}, {})",
+                        i, p, lower[i], upper[i]
+                    ));
+                }
+                Ok(match self.kind(i) {
+                    (true, true) => {
+                        let ratio = (p - lower[i]) / (upper[i] - lower[i]);
+                        (ratio / (1.0 - ratio)).ln()
+                    }
+                    (true, false) => (p - lower[i]).ln(),
+                    (false, true) => (upper[i] - p).ln(),
+                    (false, false) => *p,
+                })
+            })
+            .collect()
+    }
+
+    // dp/dq
+    fn derivative(&self, unbounded: &[f64]) -> Vec<f64> {
+        let (lower, upper) = (&self.config.lower, &self.config.upper);
+        unbounded
+            .iter()
+            .enumerate()
+            .map(|(i, q)| match self.kind(i) {
+                (true, true) => {
+                    let logistic = 1.0 / (1.0 + (-q).exp());
+                    (upper[i] - lower[i]) * logistic * (1.0 - logistic)
+                }
+                (true, false) => q.exp(),
+                (false, true) => -q.exp(),
+                (false, false) => 1.0,
+            })
+            .collect()
+    }
+
+    // 調整するパラメータのqに関するヤコビアン(独立変数の数 × 調整するパラメータの数)
+    fn jacobian(
+        &self,
+        f: &dyn Fn(f64, &[f64]) -> f64,
+        method: &Jacobian,
+        args: &[f64],
+        unbounded: &[f64],
+    ) -> Array2<f64> {
+        let params = self.to_bounded(unbounded);
+        let derivative = self.derivative(unbounded);
+        let free: Vec<usize> = self.free_indices().collect();
+        let mut jac = Array2::zeros((args.len(), free.len()));
+        for (row, arg) in args.iter().enumerate() {
+            let grad: Vec<f64> = match method {
+                Jacobian::ReverseAd(f_var) => gradient(|p| f_var(*arg, p), &params).1,
+                Jacobian::Analytic(f_grad) => f_grad(*arg, &params),
+                Jacobian::CentralDifference => {
+                    // qを動かすので、上下限を越えずに dp/dq を掛けた値が直接求まる。
+                    for (col, i) in free.iter().enumerate() {
+                        let h = DIFF_STEP * unbounded[*i].abs().max(1.0);
+                        let mut bumped = unbounded.to_vec();
+                        bumped[*i] = unbounded[*i] + h;
+                        let up = f(*arg, &self.to_bounded(&bumped));
+                        bumped[*i] = unbounded[*i] - h;
+                        let down = f(*arg, &self.to_bounded(&bumped));
+                        jac[[row, col]] = (up - down) / (2.0 * h);
+                    }
+                    continue;
+                }
+            };
+            for (col, i) in free.iter().enumerate() {
+                jac[[row, col]] = grad[*i] * derivative[*i];
+            }
+        }
+        jac
+    }
 }
 
-// fn gradient(
-//     f: &Box<dyn Fn(&f64, &Array1<f64>) -> f64>,
-//     arg: &f64,
-//     params: &Array1<f64>,
-// ) -> Vec<f64> {
-//     let mut grad = vec![0.0; params.len()];
-//     for idx in 0..grad.len() {
-//         grad[idx] = numerical_difference(f, arg, params, idx);
-//     }
-//     grad
-// }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ad::dual::Dual;
+    use crate::ad::scalar::Scalar;
+    use crate::sabr::sabr_lognormal::sabr_lognormal;
+
+    #[test]
+    fn test_sabr_fit_with_fixed_beta() {
+        // betaを固定して、Haganの式で作ったスマイルからalpha、rho、nuを復元する。
+        let (fwd, term) = (0.03, 5.0);
+        let strikes: Vec<f64> = (0..9).map(|i| 0.01 + 0.005 * i as f64).collect();
+        let sabr = |k: f64, p: &[f64]| sabr_lognormal(k, fwd, term, p[3], p[0], p[1], p[2]);
+        let exact = [0.04, -0.3, 0.4, 0.5];
+        let targets: Vec<f64> = strikes.iter().map(|k| sabr(*k, &exact)).collect();
+        let config = LmConfig {
+            is_free: vec![true, true, true, false],
+            lower: vec![0.0, -1.0, 0.0, f64::NEG_INFINITY],
+            upper: vec![f64::INFINITY, 1.0, f64::INFINITY, f64::INFINITY],
+            ..LmConfig::new(4)
+        };
+        let sabr_var = |k: f64, p: &[Var]| {
+            let c = Var::from_f64;
+            sabr_lognormal(c(k), c(fwd), c(term), p[3], p[0], p[1], p[2])
+        };
+        for jacobian in [
+            Jacobian::ReverseAd(Box::new(sabr_var)),
+            Jacobian::CentralDifference,
+        ] {
+            let report = levenberg_marquardt(
+                &sabr,
+                &jacobian,
+                &strikes,
+                &targets,
+                &[0.02, 0.0, 0.2, 0.5],
+                &config,
+            )
+            .unwrap();
+            assert!(report.converged);
+            assert_eq!(report.params[3], 0.5);
+            for (param, exact) in report.params.iter().zip(exact.iter()) {
+                assert!((param - exact).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bounds() {
+        // y = a x + b で制約なしの最適解 a = 2 が上限 1.5 を越える場合は、上限に張り付く。
+        let args = vec![0.0, 1.0, 2.0, 3.0];
+        let targets = vec![1.0, 3.0, 5.0, 7.0];
+        let line = |x: f64, p: &[f64]| p[0] * x + p[1];
+        let config = LmConfig {
+            upper: vec![1.5, f64::INFINITY],
+            ..LmConfig::new(2)
+        };
+        let jacobian = Jacobian::CentralDifference;
+        let report =
+            levenberg_marquardt(&line, &jacobian, &args, &targets, &[0.0, 0.0], &config).unwrap();
+        assert!(report.params[0] < 1.5 && report.params[0] > 1.49);
+        // a = 1.5 のとき最適な b は 1 + 0.5 * (xの平均) = 1.75
+        assert!((report.params[1] - 1.75).abs() < 0.01);
+        assert_eq!(report.residuals.len(), 4);
+    }
+
+    #[test]
+    fn test_fixed_parameter_on_bound() {
+        // 上下限 [0, 1] のbetaを端の値に固定しても、そのままの値で使われる。
+        let (fwd, term) = (0.03, 5.0);
+        let strikes: Vec<f64> = (0..9).map(|i| 0.01 + 0.005 * i as f64).collect();
+        let sabr = |k: f64, p: &[f64]| sabr_lognormal(k, fwd, term, p[3], p[0], p[1], p[2]);
+        let config = LmConfig {
+            is_free: vec![true, true, true, false],
+            lower: vec![0.0, -1.0, 0.0, 0.0],
+            upper: vec![f64::INFINITY, 1.0, f64::INFINITY, 1.0],
+            ..LmConfig::new(4)
+        };
+        for beta in [0.0, 1.0] {
+            let exact = [0.2, -0.3, 0.4, beta];
+            let targets: Vec<f64> = strikes.iter().map(|k| sabr(*k, &exact)).collect();
+            let init = [0.1, 0.0, 0.2, beta];
+            let report = levenberg_marquardt(
+                &sabr,
+                &Jacobian::CentralDifference,
+                &strikes,
+                &targets,
+                &init,
+                &config,
+            )
+            .unwrap();
+            assert!(report.converged);
+            assert_eq!(report.params[3], beta);
+            for (param, exact) in report.params.iter().zip(exact.iter()) {
+                assert!((param - exact).abs() < 1e-5);
+            }
+        }
+        // 調整するパラメータの初期値が上下限の外ならエラー
+        let result = levenberg_marquardt(
+            &sabr,
+            &Jacobian::CentralDifference,
+            &strikes,
+            &strikes,
+            &[0.1, 1.0, 0.2, 0.5],
+            &config,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_analytic_jacobian() {
+        // 二重数で与えた偏導関数に dp/dq を掛けたヤコビアンは、qの中心差分と一致する。
+        let (fwd, term) = (0.03, 5.0);
+        let strikes: Vec<f64> = (0..9).map(|i| 0.01 + 0.005 * i as f64).collect();
+        let sabr = |k: f64, p: &[f64]| sabr_lognormal(k, fwd, term, p[3], p[0], p[1], p[2]);
+        let sabr_grad = |k: f64, p: &[f64]| -> Vec<f64> {
+            let c = Dual::<4>::from_f64;
+            let d: Vec<Dual<4>> = (0..4).map(|i| Dual::variable(p[i], i)).collect();
+            sabr_lognormal(c(k), c(fwd), c(term), d[3], d[0], d[1], d[2])
+                .grad
+                .to_vec()
+        };
+        let config = LmConfig {
+            is_free: vec![true, true, true, false],
+            lower: vec![0.0, -1.0, 0.0, 0.0],
+            upper: vec![f64::INFINITY, 1.0, f64::INFINITY, 1.0],
+            ..LmConfig::new(4)
+        };
+        let bounds = Bounds { config: &config };
+        let unbounded = bounds.to_unbounded(&[0.05, -0.2, 0.3, 0.5]).unwrap();
+        let analytic = Jacobian::Analytic(Box::new(sabr_grad));
+        let jac = bounds.jacobian(&sabr, &analytic, &strikes, &unbounded);
+        let diff = bounds.jacobian(&sabr, &Jacobian::CentralDifference, &strikes, &unbounded);
+        assert_eq!(jac.dim(), (9, 3));
+        for (a, b) in jac.iter().zip(diff.iter()) {
+            assert!((a - b).abs() < 1e-7 * b.abs().max(1.0));
+        }
+        let exact = [0.04, -0.3, 0.4, 0.5];
+        let targets: Vec<f64> = strikes.iter().map(|k| sabr(*k, &exact)).collect();
+        let init = [0.02, 0.0, 0.2, 0.5];
+        let report =
+            levenberg_marquardt(&sabr, &analytic, &strikes, &targets, &init, &config).unwrap();
+        assert!(report.converged);
+        for (param, exact) in report.params.iter().zip(exact.iter()) {
+            assert!((param - exact).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_stationary_point() {
+        let args = [0.0, 1.0, 2.0];
+        let line = |x: f64, p: &[f64]| p[0] * x + p[1];
+        let jacobian = Jacobian::CentralDifference;
+        // 初期値が最適解なら更新せずに収束とする。
+        let targets = [1.0, 3.0, 5.0];
+        let config = LmConfig::new(2);
+        let report =
+            levenberg_marquardt(&line, &jacobian, &args, &targets, &[2.0, 1.0], &config).unwrap();
+        assert!(report.converged);
+        assert_eq!(report.num_iter, 0);
+        // 直線に乗らない目標値でも、最小二乗解で勾配が0になり収束とする。
+        let targets = [1.0, 2.0, 4.0];
+        let report =
+            levenberg_marquardt(&line, &jacobian, &args, &targets, &[0.0, 0.0], &config).unwrap();
+        assert!(report.converged);
+        assert!((report.params[0] - 1.5).abs() < 1e-8);
+        assert!((report.params[1] - 5.0 / 6.0).abs() < 1e-8);
+        // 全てのパラメータを固定した場合は初期値のまま返す。
+        let fixed = LmConfig {
+            is_free: vec![false, false],
+            ..LmConfig::new(2)
+        };
+        let report =
+            levenberg_marquardt(&line, &jacobian, &args, &targets, &[1.0, 1.0], &fixed).unwrap();
+        assert!(report.converged);
+        assert_eq!(report.num_iter, 0);
+        assert_eq!(report.params, vec![1.0, 1.0]);
+        assert_eq!(report.squared_sum, 1.0);
+    }
+
+    #[test]
+    fn test_stalled_fit_not_converged() {
+        // 符号の誤ったヤコビアンでは残差二乗和を減らせず、停留点でもないので収束しなかったことを返す。
+        let line = |x: f64, p: &[f64]| p[0] * x;
+        let wrong_grad = |x: f64, _: &[f64]| vec![-x];
+        let report = levenberg_marquardt(
+            &line,
+            &Jacobian::Analytic(Box::new(wrong_grad)),
+            &[1.0, 2.0],
+            &[2.0, 4.0],
+            &[0.5],
+            &LmConfig::new(1),
+        )
+        .unwrap();
+        assert!(!report.converged);
+        assert_eq!(report.params, vec![0.5]);
+    }
+}
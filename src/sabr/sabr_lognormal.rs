@@ -1,39 +1,61 @@
+use super::SabrParams;
+use crate::ad::scalar::Scalar;
+
 // Hagan et al. 2002
-pub fn sabr_lognormal(
-    strike: f64,
-    fwd: f64,
-    term: f64,
-    beta: f64,
-    alpha: f64,
-    rho: f64,
-    nu: f64,
-) -> f64 {
-    let one_beta = 1.0 - beta;
-    if (fwd - strike).abs() > 1e-5 {
+// 引数の型をDualやVarにするとパラメータに関する感応度を同時に計算できる。
+pub fn sabr_lognormal<T: Scalar>(
+    strike: T,
+    fwd: T,
+    term: T,
+    beta: T,
+    alpha: T,
+    rho: T,
+    nu: T,
+) -> T {
+    let one_beta = -beta + 1.0;
+    if (fwd.value() - strike.value()).abs() > 1e-5 {
         // ATMでない
         let fwd_mul_strike = fwd * strike;
         let fwd_div_strike = fwd / strike;
-        let z = nu / alpha * fwd_mul_strike.powf(one_beta * 0.5) * (fwd_div_strike).ln();
-        let x_z = (((1.0 - 2.0 * rho * z + z.powi(2)).sqrt() + z - rho) / (1.0 - rho)).ln();
+        let z = nu / alpha * fwd_mul_strike.pow(one_beta * 0.5) * (fwd_div_strike).ln();
+        let x_z = (((-rho * z * 2.0 + z.powi(2) + 1.0).sqrt() + z - rho) / (-rho + 1.0)).ln();
         alpha
-            * (1.0
-                + term
-                    * ((one_beta * alpha).powi(2) / (24.0 * fwd_mul_strike.powf(one_beta))
-                        + rho * beta * nu * alpha / (4.0 * fwd_mul_strike.powf(one_beta * 0.5))
-                        + (2.0 - 3.0 * rho.powi(2)) * nu.powi(2) / 24.0))
-            / (fwd_mul_strike.powf(one_beta * 0.5)
-                * (1.0
-                    + (one_beta * fwd_div_strike.ln()).powi(2) / 24.0
-                    + (one_beta * fwd_div_strike.ln()).powi(4) / 1920.0))
+            * (term
+                * ((one_beta * alpha).powi(2) / (fwd_mul_strike.pow(one_beta) * 24.0)
+                    + rho * beta * nu * alpha / (fwd_mul_strike.pow(one_beta * 0.5) * 4.0)
+                    + (-rho.powi(2) * 3.0 + 2.0) * nu.powi(2) / 24.0)
+                + 1.0)
+            / (fwd_mul_strike.pow(one_beta * 0.5)
+                * ((one_beta * fwd_div_strike.ln()).powi(2) / 24.0
+                    + (one_beta * fwd_div_strike.ln()).powi(4) / 1920.0
+                    + 1.0))
             * z
             / x_z
     } else {
         //ATM
-        alpha / fwd.powf(one_beta)
-            * (1.0
-                + term
-                    * (((one_beta * alpha).powi(2) / (24.0 * fwd.powf(2.0 * one_beta)))
-                        + alpha * beta * nu * rho / (4.0 * fwd.powf(one_beta))
-                        + (2.0 - 3.0 * rho.powi(2)) * nu.powi(2) / 24.0))
+        alpha / fwd.pow(one_beta)
+            * (term
+                * ((one_beta * alpha).powi(2) / (fwd.pow(one_beta * 2.0) * 24.0)
+                    + alpha * beta * nu * rho / (fwd.pow(one_beta) * 4.0)
+                    + (-rho.powi(2) * 3.0 + 2.0) * nu.powi(2) / 24.0)
+                + 1.0)
     }
 }
+
+/// シフト(変位)したフォワード F + shift と行使価格 K + shift のSABRの対数正規ボラティリティ(shifted Blackのボラティリティ)を返します。
+/// * `strike` - 行使価格
+/// * `fwd` - フォワード
+/// * `term` - 満期
+/// * `params` - SABRのパラメータとシフト(F + shift > 0、K + shift > 0 となるように取る)
+pub fn shifted_sabr_lognormal<T: Scalar>(strike: T, fwd: T, term: T, params: &SabrParams<T>) -> T {
+    let shift = params.shift;
+    sabr_lognormal(
+        strike + shift,
+        fwd + shift,
+        term,
+        params.beta,
+        params.alpha,
+        params.rho,
+        params.nu,
+    )
+}
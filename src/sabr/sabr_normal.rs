@@ -0,0 +1,153 @@
+use super::SabrParams;
+use crate::ad::scalar::Scalar;
+
+// Hagan et al. 2002 のノーマル・ボラティリティ(Bachelierモデルのインプライド・ボラティリティ)
+// beta = 0 (ノーマルSABR) はフォワードと行使価格が負でも使える。beta > 0 はフォワードと行使価格が正の場合だけ使え、
+// 負の金利ではシフト(shifted_sabr_normal)を使う。
+// 引数の型をDualやVarにするとパラメータに関する感応度を同時に計算できる。
+pub fn sabr_normal<T: Scalar>(strike: T, fwd: T, term: T, beta: T, alpha: T, rho: T, nu: T) -> T {
+    let one_beta = -beta + 1.0;
+    let is_normal = beta.value() == 0.0;
+    // (F K)^{(1 - beta) / 2}。beta = 0 では負のF、Kでも使えるようにべき乗を使わない。
+    let fwd_mul_strike_pow = |exponent: T| -> T {
+        if is_normal {
+            T::from_f64(1.0)
+        } else {
+            (fwd * strike).pow(exponent)
+        }
+    };
+    let correction = term
+        * (-beta * (-beta + 2.0) * alpha.powi(2) / (fwd_mul_strike_pow(one_beta) * 24.0)
+            + rho * alpha * nu * beta / (fwd_mul_strike_pow(one_beta * 0.5) * 4.0)
+            + (-rho.powi(2) * 3.0 + 2.0) * nu.powi(2) / 24.0)
+        + 1.0;
+
+    if (fwd.value() - strike.value()).abs() > 1e-5 {
+        // ATMでない
+        let diff = fwd - strike;
+        // (1 - beta) (F - K) / (F^{1 - beta} - K^{1 - beta})
+        let factor = if is_normal {
+            T::from_f64(1.0)
+        } else if one_beta.value() == 0.0 {
+            diff / (fwd / strike).ln()
+        } else {
+            one_beta * diff / (fwd.pow(one_beta) - strike.pow(one_beta))
+        };
+        let z = nu / alpha * diff / fwd_mul_strike_pow(beta * 0.5);
+        let x_z = (((-rho * z * 2.0 + z.powi(2) + 1.0).sqrt() + z - rho) / (-rho + 1.0)).ln();
+        alpha * factor * z / x_z * correction
+    } else {
+        //ATM
+        let fwd_pow_beta = if is_normal {
+            T::from_f64(1.0)
+        } else {
+            fwd.pow(beta)
+        };
+        alpha * fwd_pow_beta * correction
+    }
+}
+
+/// シフト(変位)したフォワード F + shift と行使価格 K + shift のSABRのノーマル・ボラティリティを返します。<br>
+/// ノーマル・ボラティリティはシフトしても変わらないので、そのままBachelierモデルの価格に使えます。
+/// * `strike` - 行使価格
+/// * `fwd` - フォワード
+/// * `term` - 満期
+/// * `params` - SABRのパラメータとシフト(F + shift > 0、K + shift > 0 となるように取る)
+pub fn shifted_sabr_normal<T: Scalar>(strike: T, fwd: T, term: T, params: &SabrParams<T>) -> T {
+    let shift = params.shift;
+    sabr_normal(
+        strike + shift,
+        fwd + shift,
+        term,
+        params.beta,
+        params.alpha,
+        params.rho,
+        params.nu,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sabr::lm::{levenberg_marquardt, Jacobian, LmConfig};
+    use crate::sabr::sabr_lognormal::{sabr_lognormal, shifted_sabr_lognormal};
+
+    #[test]
+    fn test_normal_sabr_negative_rates() {
+        // beta = 0 は負のフォワード、行使価格でも有限で、ATMの前後で連続、rho = 0 なら行使価格についてフォワードで対称になる。
+        let (fwd, term, alpha, nu) = (-0.002, 5.0, 0.006, 0.3);
+        let vol = |strike: f64, rho: f64| sabr_normal(strike, fwd, term, 0.0, alpha, rho, nu);
+        for d in [0.002, 0.005, 0.01] {
+            assert!(vol(fwd - d, -0.3).is_finite());
+            assert!((vol(fwd + d, 0.0) - vol(fwd - d, 0.0)).abs() < 1e-15);
+        }
+        let average = 0.5 * (vol(fwd + 2e-5, -0.3) + vol(fwd - 2e-5, -0.3));
+        assert!((average - vol(fwd, -0.3)).abs() < 1e-9);
+        // volのvolがなければBachelierモデル
+        assert!((sabr_normal(0.01, fwd, term, 0.0, alpha, 0.5, 1e-5) - alpha).abs() < 1e-7);
+    }
+
+    #[test]
+    fn test_normal_vs_lognormal() {
+        // beta = 1、nu -> 0 はBlackモデルで、ノーマル・ボラティリティは σ (F - K) / ln(F / K) (1 - σ^2 T / 24) となる。
+        let (fwd, strike, term, sigma) = (0.03, 0.02, 2.0, 0.2);
+        let lognormal = sabr_lognormal(strike, fwd, term, 1.0, sigma, 0.0, 1e-4);
+        assert!((lognormal - sigma).abs() < 1e-8);
+        let normal = sabr_normal(strike, fwd, term, 1.0, sigma, 0.0, 1e-4);
+        let expected =
+            sigma * (fwd - strike) / (fwd / strike).ln() * (1.0 - sigma.powi(2) * term / 24.0);
+        assert!((normal - expected).abs() < 1e-10);
+        // シフトはフォワードと行使価格をずらすのと同じ
+        let params = SabrParams {
+            alpha: 0.05,
+            beta: 0.5,
+            rho: -0.2,
+            nu: 0.4,
+            shift: 0.02,
+        };
+        let shifted = shifted_sabr_lognormal(-0.005, 0.001, term, &params);
+        assert_eq!(
+            shifted,
+            sabr_lognormal(-0.005 + 0.02, 0.001 + 0.02, term, 0.5, 0.05, -0.2, 0.4)
+        );
+        let shifted = shifted_sabr_normal(-0.005, 0.001, term, &params);
+        assert!(shifted.is_finite() && shifted > 0.0);
+    }
+
+    #[test]
+    fn test_fit_shifted_sabr_negative_strikes() {
+        // betaとシフトを固定して、負の行使価格を含むノーマル・ボラティリティのスマイルからalpha、rho、nuを復元する。
+        let (fwd, shift, term, beta) = (-0.001, 0.02, 10.0, 0.5);
+        let strikes: Vec<f64> = (0..9).map(|i| -0.008 + 0.002 * i as f64).collect();
+        let vol = |k: f64, p: &[f64]| {
+            let params = SabrParams {
+                alpha: p[0],
+                beta,
+                rho: p[1],
+                nu: p[2],
+                shift,
+            };
+            shifted_sabr_normal(k, fwd, term, &params)
+        };
+        let exact = [0.04, -0.2, 0.35];
+        let targets: Vec<f64> = strikes.iter().map(|k| vol(*k, &exact)).collect();
+        let config = LmConfig {
+            lower: vec![0.0, -1.0, 0.0],
+            upper: vec![f64::INFINITY, 1.0, f64::INFINITY],
+            ..LmConfig::new(3)
+        };
+        let report = levenberg_marquardt(
+            &vol,
+            &Jacobian::CentralDifference,
+            &strikes,
+            &targets,
+            &[0.02, 0.0, 0.2],
+            &config,
+        )
+        .unwrap();
+        assert!(report.converged);
+        for (param, exact) in report.params.iter().zip(exact.iter()) {
+            assert!((param - exact).abs() < 1e-5);
+        }
+    }
+}